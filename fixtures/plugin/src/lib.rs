@@ -0,0 +1,25 @@
+use elec::component::{Component, StampContext};
+use elec::plugin::PluginRegistry;
+use nalgebra::{DMatrix, DVector};
+
+/** A resistor, the model of the plugin loaded by the tests of the plugin registry. */
+#[derive(Debug)]
+struct Resistor {
+    resistance: f64,
+}
+
+impl Component for Resistor {
+    fn terminals(&self) -> usize {
+        2
+    }
+
+    fn stamp(&self, matrix: &mut DMatrix<f64>, _rhs: &mut DVector<f64>, ctx: &StampContext) {
+        ctx.stamp_conductance(matrix, 0, 1, 1.0 / self.resistance);
+    }
+}
+
+fn register(registry: &mut PluginRegistry) {
+    registry.register("resistor", |parameters| Box::new(Resistor { resistance: parameters[0] }));
+}
+
+elec::export_plugin!(register);
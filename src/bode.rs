@@ -0,0 +1,120 @@
+use nalgebra::Complex;
+
+/**
+Bode plot of a transfer function sampled by an AC sweep: the gain in dB and the phase in degrees
+at each frequency. The phase is unwrapped, so it is continuous across -180°. The figures are
+interpolated on a logarithmic frequency axis, the frequencies must be positive.
+*/
+#[derive(Debug, Clone)]
+pub struct Bode {
+    pub frequencies: Vec<f64>,
+    pub gain_db: Vec<f64>,
+    pub phase_degrees: Vec<f64>,
+}
+
+impl Bode {
+    /** Builds the Bode plot of a complex response, one value per frequency. */
+    pub fn new(frequencies: Vec<f64>, response: &[Complex<f64>]) -> Self {
+        if frequencies.len() != response.len() {
+            panic!("The response must have one value per frequency");
+        }
+
+        let gain_db = response.iter().map(|h| 20.0 * h.norm().log10()).collect();
+
+        let mut phase_degrees: Vec<f64> = Vec::with_capacity(response.len());
+        for h in response {
+            let mut phase = h.arg().to_degrees();
+            if let Some(previous) = phase_degrees.last() {
+                // Keep the jump to the previous phase within ±180°.
+                phase -= 360.0 * ((phase - previous) / 360.0).round();
+            }
+            phase_degrees.push(phase);
+        }
+
+        Self { frequencies, gain_db, phase_degrees }
+    }
+
+    /**
+    Returns the -3 dB bandwidth: the first frequency where the gain is 3 dB below the gain at the
+    first frequency, or None if it never is.
+    */
+    pub fn bandwidth(&self) -> Option<f64> {
+        let threshold = self.gain_db.first()? - 3.0;
+        self.crossing(&self.gain_db, threshold)
+    }
+
+    /**
+    Returns the gain margin in dB of an open loop response: the opposite of the gain where the
+    phase crosses -180°, or None if it never does.
+    */
+    pub fn gain_margin(&self) -> Option<f64> {
+        let frequency = self.crossing(&self.phase_degrees, -180.0)?;
+        Some(-self.interpolate(&self.gain_db, frequency))
+    }
+
+    /**
+    Returns the phase margin in degrees of an open loop response: 180° plus the phase where the
+    gain crosses 0 dB, or None if it never does.
+    */
+    pub fn phase_margin(&self) -> Option<f64> {
+        let frequency = self.crossing(&self.gain_db, 0.0)?;
+        Some(180.0 + self.interpolate(&self.phase_degrees, frequency))
+    }
+
+    /** Returns the first frequency where values crosses the level downwards. */
+    fn crossing(&self, values: &[f64], level: f64) -> Option<f64> {
+        let k = values.windows(2).position(|pair| pair[0] >= level && pair[1] < level)?;
+        let ratio = (values[k] - level) / (values[k] - values[k + 1]);
+        Some(self.frequency_between(k, ratio))
+    }
+
+    /** Returns values at a frequency within the sweep, interpolated on a logarithmic frequency axis. */
+    fn interpolate(&self, values: &[f64], frequency: f64) -> f64 {
+        let k = self.frequencies
+            .windows(2)
+            .position(|pair| frequency <= pair[1])
+            .unwrap_or(self.frequencies.len() - 2);
+        let ratio = (frequency / self.frequencies[k]).ln() / (self.frequencies[k + 1] / self.frequencies[k]).ln();
+        values[k] + (values[k + 1] - values[k]) * ratio
+    }
+
+    /** Returns the frequency at the given ratio between the frequencies k and k + 1, on a logarithmic axis. */
+    fn frequency_between(&self, k: usize, ratio: f64) -> f64 {
+        self.frequencies[k] * (self.frequencies[k + 1] / self.frequencies[k]).powf(ratio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** Samples H(s) = 1000 / (1 + s)^3 on a decade sweep, with s = jf. */
+    fn third_order() -> Bode {
+        let frequencies: Vec<f64> = (0..=400).map(|k| 10f64.powf(-2.0 + k as f64 / 100.0)).collect();
+        let response: Vec<Complex<f64>> = frequencies
+            .iter()
+            .map(|f| Complex::new(1000.0, 0.0) / (Complex::new(1.0, *f)).powi(3))
+            .collect();
+        Bode::new(frequencies, &response)
+    }
+
+    #[test]
+    fn phase_is_unwrapped() {
+        let bode = third_order();
+        assert!(bode.phase_degrees.windows(2).all(|pair| pair[1] <= pair[0] && pair[0] - pair[1] < 10.0));
+        // -3 * atan(100) at the last frequency, past -180°.
+        assert!((bode.phase_degrees.last().unwrap() + 3.0 * 100f64.atan().to_degrees()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stability_margins() {
+        let bode = third_order();
+        // The phase crosses -180° at f = sqrt(3), where |H| = 1000 / 8.
+        let gain_margin = bode.gain_margin().unwrap();
+        assert!((gain_margin + 20.0 * 125f64.log10()).abs() < 0.05, "gain margin {}", gain_margin);
+        // The gain crosses 0 dB at f = sqrt(99), where the phase is -3 * atan(sqrt(99)).
+        let phase_margin = bode.phase_margin().unwrap();
+        let expected = 180.0 - 3.0 * 99f64.sqrt().atan().to_degrees();
+        assert!((phase_margin - expected).abs() < 0.1, "phase margin {}", phase_margin);
+    }
+}
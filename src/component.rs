@@ -0,0 +1,92 @@
+use std::fmt::Debug;
+use nalgebra::{DMatrix, DVector};
+use crate::simulator::TimeStep;
+
+/**
+A component implemented outside of the simulator, added to a circuit with `Components::Custom`.
+
+The component writes its own equations in the matrix equation (M * X = Y) of the simulator. Its
+terminals contribute to the Kirchhoff's law of their node: the row of a node sums the intensities
+that leave the node into the components. Its branches, if any, each have a row of their own.
+*/
+pub trait Component: Debug {
+    /** Returns the number of terminals, numbered from 0. */
+    fn terminals(&self) -> usize;
+
+    /** Returns how many branch intensities the component adds to the unknowns. */
+    fn branches(&self) -> usize {
+        0
+    }
+
+    /**
+    Adds the equations of the component to the matrix (M) and the result (Y), linearized around the
+    unknowns of the context. It is called at every Newton–Raphson iteration.
+    */
+    fn stamp(&self, matrix: &mut DMatrix<f64>, rhs: &mut DVector<f64>, ctx: &StampContext);
+
+    /** Updates the state of the component from an accepted solution, the unknowns of the context. */
+    fn post_solve(&mut self, _ctx: &StampContext) {}
+}
+
+/** Where the terminals and branches of a custom component are in the matrix equation. */
+pub struct StampContext<'a> {
+    /** Row (and column) of the node of each terminal, None for the reference node. */
+    pub(crate) terminal_rows: Vec<Option<usize>>,
+    /** Row (and column) of each branch intensity. */
+    pub(crate) branch_rows: Vec<usize>,
+    pub(crate) unknowns: &'a DVector<f64>,
+    pub(crate) time_step: Option<&'a TimeStep>,
+}
+
+impl StampContext<'_> {
+    /** Returns the row of the node of a terminal, None for the reference node which has none. */
+    pub fn terminal_row(&self, idx: usize) -> Option<usize> {
+        self.terminal_rows[idx]
+    }
+
+    /** Returns the row of a branch intensity of the component. */
+    pub fn branch_row(&self, idx: usize) -> usize {
+        self.branch_rows[idx]
+    }
+
+    /** Returns the voltage of a terminal in the unknowns (the guess, or the accepted solution). */
+    pub fn voltage(&self, idx: usize) -> f64 {
+        self.terminal_rows[idx].map_or(0.0, |row| self.unknowns[row])
+    }
+
+    /** Returns a branch intensity of the component in the unknowns. */
+    pub fn branch_intensity(&self, idx: usize) -> f64 {
+        self.unknowns[self.branch_rows[idx]]
+    }
+
+    /** Returns the current time step, None for a DC solve. */
+    pub fn time_step(&self) -> Option<&TimeStep> {
+        self.time_step
+    }
+
+    /** Stamps a conductance between two terminals. */
+    pub fn stamp_conductance(&self, matrix: &mut DMatrix<f64>, from: usize, to: usize, conductance: f64) {
+        let (from, to) = (self.terminal_rows[from], self.terminal_rows[to]);
+        if let Some(from) = from {
+            matrix[(from, from)] += conductance;
+        }
+        if let Some(to) = to {
+            matrix[(to, to)] += conductance;
+        }
+        if let (Some(from), Some(to)) = (from, to) {
+            matrix[(from, to)] -= conductance;
+            matrix[(to, from)] -= conductance;
+        }
+    }
+
+    /** Stamps a constant intensity that goes through the component from one terminal to another. */
+    pub fn stamp_current(&self, rhs: &mut DVector<f64>, from: usize, to: usize, intensity: f64) {
+        // The intensity leaves the node of `from`, the constant goes to the right hand side.
+        if let Some(from) = self.terminal_rows[from] {
+            rhs[from] -= intensity;
+        }
+        if let Some(to) = self.terminal_rows[to] {
+            rhs[to] += intensity;
+        }
+    }
+}
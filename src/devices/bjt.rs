@@ -0,0 +1,51 @@
+use crate::devices::diode::shockley;
+use crate::devices::LinearizedTerminals;
+use crate::model::BjtPolarity;
+
+/** Reverse current gain, the transistor is rarely used with collector and emitter swapped. */
+const BETA_REVERSE: f64 = 1.0;
+
+/**
+Ebers–Moll (transport) model of a bipolar transistor.
+
+The terminal voltages are given as [collector, base, emitter] and the result is the linearized
+intensity entering each of those terminals:
+- Ic = is * (exp(vbe / Vt) - exp(vbc / Vt)) - is / beta_r * (exp(vbc / Vt) - 1)
+- Ib = is / beta * (exp(vbe / Vt) - 1) + is / beta_r * (exp(vbc / Vt) - 1)
+- Ie = -(Ic + Ib)
+
+A PNP transistor is an NPN transistor with every voltage and intensity reversed.
+*/
+pub fn linearize(polarity: BjtPolarity, beta: f64, is: f64, v: [f64; 3]) -> LinearizedTerminals {
+    let sign = match polarity {
+        BjtPolarity::Npn => 1.0,
+        BjtPolarity::Pnp => -1.0,
+    };
+    let [vc, vb, ve] = v.map(|v| sign * v);
+
+    let (i_forward, g_forward) = shockley(is, 1.0, vb - ve);
+    let (i_reverse, g_reverse) = shockley(is, 1.0, vb - vc);
+
+    let ic = i_forward - i_reverse - i_reverse / BETA_REVERSE;
+    let ib = i_forward / beta + i_reverse / BETA_REVERSE;
+    let ie = -(ic + ib);
+
+    // Derivatives with respect to vbe and vbc, then chained to the terminal voltages:
+    // d/dvc = -d/dvbc, d/dvb = d/dvbe + d/dvbc, d/dve = -d/dvbe.
+    let dic = (g_forward, -g_reverse - g_reverse / BETA_REVERSE);
+    let dib = (g_forward / beta, g_reverse / BETA_REVERSE);
+    let die = (-(dic.0 + dib.0), -(dic.1 + dib.1));
+    let row = |(d_vbe, d_vbc): (f64, f64)| vec![-d_vbc, d_vbe + d_vbc, -d_vbe];
+
+    let linearized = LinearizedTerminals::tangent(
+        &[vc, vb, ve],
+        vec![ic, ib, ie],
+        vec![row(dic), row(dib), row(die)],
+    );
+
+    // Reversing both the voltages and the intensities keeps the jacobian but reverses the constants.
+    LinearizedTerminals {
+        conductances: linearized.conductances,
+        currents: linearized.currents.into_iter().map(|i| sign * i).collect(),
+    }
+}
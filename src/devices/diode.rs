@@ -0,0 +1,35 @@
+use crate::devices::Linearized;
+
+/** Thermal voltage kT/q at 300K, in volts. */
+pub const THERMAL_VOLTAGE: f64 = 0.025852;
+
+/**
+Exponent above which the exponential is continued by its tangent. Without it, a bad guess during
+the Newton–Raphson iteration overflows to infinity.
+*/
+const MAX_EXPONENT: f64 = 40.0;
+
+/**
+Shockley equation of a PN junction: i = is * (exp(v / (n * Vt)) - 1).
+
+Returns the intensity and its derivative (the conductance) at voltage v.
+*/
+pub fn shockley(is: f64, n: f64, v: f64) -> (f64, f64) {
+    let nvt = n * THERMAL_VOLTAGE;
+    let x = v / nvt;
+
+    if x > MAX_EXPONENT {
+        let e = MAX_EXPONENT.exp();
+        let intensity = is * (e * (1.0 + x - MAX_EXPONENT) - 1.0);
+        (intensity, is * e / nvt)
+    } else {
+        let e = x.exp();
+        (is * (e - 1.0), is * e / nvt)
+    }
+}
+
+/** Linearizes the Shockley equation around the voltage v. */
+pub fn linearize(is: f64, n: f64, v: f64) -> Linearized {
+    let (intensity, conductance) = shockley(is, n, v);
+    Linearized::tangent(v, intensity, conductance)
+}
@@ -0,0 +1,24 @@
+/** Resistance of a blown fuse, in ohms. It is not infinite so that no node is left floating. */
+pub const BLOWN_RESISTANCE: f64 = 1e12;
+
+/**
+Accumulates the melting integral of a fuse over a time step and returns it, with whether the
+fuse blew.
+
+Without a melting integral, the fuse blows as soon as the intensity exceeds its rating. Otherwise,
+i² is integrated while the intensity is above the rating, and the fuse blows once the integral
+reaches melting_i2t. A DC solve (no dt) lasts forever, so any overload blows the fuse.
+*/
+pub fn melt(rated_current: f64, melting_i2t: Option<f64>, i2t: f64, intensity: f64, dt: Option<f64>) -> (f64, bool) {
+    if intensity.abs() <= rated_current {
+        return (i2t, false);
+    }
+
+    match (melting_i2t, dt) {
+        (Some(melting_i2t), Some(dt)) => {
+            let i2t = i2t + intensity * intensity * dt;
+            (i2t, i2t >= melting_i2t)
+        }
+        _ => (i2t, true),
+    }
+}
@@ -0,0 +1,22 @@
+use crate::devices::Linearized;
+
+/** Resistance of a conducting ideal diode, in ohms. It is not zero so the matrix stays regular. */
+const ON_RESISTANCE: f64 = 1e-6;
+
+/** Conductance of a blocking ideal diode, in siemens. */
+const OFF_CONDUCTANCE: f64 = 1e-12;
+
+/**
+Piecewise-linear diode: a fixed drop vf when conducting, an open circuit when blocking.
+
+Instead of a tangent, the region is selected from the voltage v of the guess and the diode is
+replaced by the straight line of that region. Each region being linear, the iteration stops as
+soon as the regions of the guess and of the solution agree, usually after one or two solves.
+*/
+pub fn linearize(vf: f64, v: f64) -> Linearized {
+    if v > vf {
+        Linearized { conductance: 1.0 / ON_RESISTANCE, current: -vf / ON_RESISTANCE }
+    } else {
+        Linearized { conductance: OFF_CONDUCTANCE, current: 0.0 }
+    }
+}
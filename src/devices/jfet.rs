@@ -0,0 +1,20 @@
+use crate::devices::{mosfet, LinearizedTerminals};
+use crate::model::Channel;
+
+/**
+Shichman–Hodges model of a JFET, ignoring the gate junction current.
+
+The terminal voltages are given as [drain, gate, source]. In saturation, Id = idss * (1 - vgs / vp)^2,
+which is the square law of a MOSFET whose threshold is vp and kp = 2 * idss / vp^2, so the MOSFET
+model is reused as is.
+*/
+pub fn linearize(channel: Channel, idss: f64, vp: f64, v: [f64; 3]) -> LinearizedTerminals {
+    // The MOSFET model mirrors a P-channel device into an N-channel one, threshold included.
+    let vth = match channel {
+        Channel::N => vp,
+        Channel::P => -vp,
+    };
+    let kp = 2.0 * idss / (vp * vp);
+
+    mosfet::linearize(channel, vth, kp, 0.0, v)
+}
@@ -0,0 +1,17 @@
+/** Resistance of a filament heated temperature kelvins above the ambient, linear in temperature. */
+pub fn resistance(cold_resistance: f64, temperature_coefficient: f64, temperature: f64) -> f64 {
+    cold_resistance * (1.0 + temperature_coefficient * temperature)
+}
+
+/**
+Integrates the temperature of the filament over a time step. The filament is a first order
+thermal system, C * dT/dt = P - T / R, integrated exactly for a constant power:
+
+T(t + dt) = P * R + (T(t) - P * R) * exp(-dt / (R * C))
+
+It does not blow up with a time step much longer than the thermal time constant.
+*/
+pub fn heat(temperature: f64, power: f64, thermal_resistance: f64, thermal_capacitance: f64, dt: f64) -> f64 {
+    let steady = power * thermal_resistance;
+    steady + (temperature - steady) * (-dt / (thermal_resistance * thermal_capacitance)).exp()
+}
@@ -0,0 +1,33 @@
+use crate::devices::diode::THERMAL_VOLTAGE;
+use crate::devices::Linearized;
+
+/**
+Emission coefficient used to round the knee of the LED characteristic. LEDs are far from ideal
+junctions, 2 is a typical value.
+*/
+const EMISSION_COEFFICIENT: f64 = 2.0;
+
+/**
+LED characteristic: no intensity below the forward voltage vf, then a straight line of slope 1/rs.
+
+The knee is smoothed (softplus) so the Newton–Raphson iteration sees a continuous derivative:
+i = n * Vt / rs * ln(1 + exp((v - vf) / (n * Vt)))
+
+Returns the intensity and its derivative (the conductance) at voltage v.
+*/
+pub fn intensity(vf: f64, rs: f64, v: f64) -> (f64, f64) {
+    let nvt = EMISSION_COEFFICIENT * THERMAL_VOLTAGE;
+    let x = (v - vf) / nvt;
+
+    // ln(1 + exp(x)) = x + ln(1 + exp(-x)), which does not overflow for a large x.
+    let softplus = if x > 0.0 { x + (-x).exp().ln_1p() } else { x.exp().ln_1p() };
+    let sigmoid = 1.0 / (1.0 + (-x).exp());
+
+    (nvt / rs * softplus, sigmoid / rs)
+}
+
+/** Linearizes the LED characteristic around the voltage v. */
+pub fn linearize(vf: f64, rs: f64, v: f64) -> Linearized {
+    let (intensity, conductance) = intensity(vf, rs, v);
+    Linearized::tangent(v, intensity, conductance)
+}
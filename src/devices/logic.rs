@@ -0,0 +1,36 @@
+use crate::model::LogicFunction;
+
+/** Whether a logic input is high, the threshold being half of the supply. */
+pub fn is_high(v: f64, v_ground: f64, v_supply: f64) -> bool {
+    v - v_ground > (v_supply - v_ground) / 2.0
+}
+
+/** Next state of an SR latch, the reset input having the priority. */
+pub fn latch(q: bool, set: bool, reset: bool) -> bool {
+    if reset {
+        false
+    } else if set {
+        true
+    } else {
+        q
+    }
+}
+
+/** Output of a logic gate for the given inputs. */
+pub fn evaluate(function: LogicFunction, inputs: &[bool]) -> bool {
+    match function {
+        LogicFunction::Not => !inputs[0],
+        LogicFunction::And => inputs.iter().all(|input| *input),
+        LogicFunction::Or => inputs.iter().any(|input| *input),
+        LogicFunction::Xor => inputs.iter().filter(|input| **input).count() % 2 == 1,
+    }
+}
+
+/** Next state of a D flip-flop, which samples D on the rising edges of the clock. */
+pub fn flip_flop(q: bool, d: bool, clock_previous: bool, clock: bool) -> bool {
+    if clock && !clock_previous {
+        d
+    } else {
+        q
+    }
+}
@@ -0,0 +1,15 @@
+use std::f64::consts::PI;
+
+/**
+Flux linkage of a saturating magnetic core as a function of the magnetizing intensity, following an
+arctangent B-H curve:
+flux = 2 * saturation_flux / pi * atan(pi * inductance * i / (2 * saturation_flux))
+
+The curve starts with a slope of inductance and flattens towards +/- saturation_flux. Returns the
+flux linkage and its derivative (the differential inductance) at the intensity i.
+*/
+pub fn flux(inductance: f64, saturation_flux: f64, i: f64) -> (f64, f64) {
+    let x = PI * inductance * i / (2.0 * saturation_flux);
+    let flux = 2.0 * saturation_flux / PI * x.atan();
+    (flux, inductance / (1.0 + x * x))
+}
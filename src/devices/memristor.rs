@@ -0,0 +1,12 @@
+/** HP (linear ion drift) memristor: the resistance interpolates between r_on and r_off with the state x. */
+pub fn resistance(r_on: f64, r_off: f64, x: f64) -> f64 {
+    r_on * x + r_off * (1.0 - x)
+}
+
+/**
+Integrates the state of the memristor over a time step (forward Euler): dx/dt = drift * i.
+The state is bounded to [0, 1], the doped region cannot be wider than the device.
+*/
+pub fn integrate(drift: f64, x: f64, intensity: f64, dt: f64) -> f64 {
+    (x + drift * intensity * dt).clamp(0.0, 1.0)
+}
@@ -0,0 +1,74 @@
+pub mod bjt;
+pub mod magnetic_core;
+pub mod memristor;
+pub mod diode;
+pub mod fuse;
+pub mod ideal_diode;
+pub mod jfet;
+pub mod lamp;
+pub mod led;
+pub mod logic;
+pub mod mosfet;
+pub mod motor;
+pub mod noise;
+pub mod opamp;
+pub mod optocoupler;
+pub mod potentiometer;
+pub mod regulator;
+pub mod spark_gap;
+pub mod switch;
+pub mod table;
+pub mod transmission_line;
+pub mod transfer_function;
+pub mod triode;
+pub mod tvs;
+pub mod waveform;
+pub mod zener;
+
+/**
+Linearized model of a nonlinear two-terminal device around an operating point.
+
+The intensity through the device is approximated by i = conductance * v + current, which can be
+stamped like a resistor in parallel with a current source.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Linearized {
+    pub conductance: f64,
+    pub current: f64,
+}
+
+impl Linearized {
+    /** Builds the tangent of a device at voltage v, given its intensity and derivative there. */
+    pub fn tangent(v: f64, intensity: f64, conductance: f64) -> Self {
+        Self {
+            conductance,
+            current: intensity - conductance * v,
+        }
+    }
+}
+
+/**
+Linearized model of a nonlinear device with more than two terminals around an operating point.
+
+The intensity entering the terminal t is approximated by
+i_t = sum_k(conductances[t][k] * v_k) + currents[t], where v_k is the voltage of the terminal k.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearizedTerminals {
+    pub conductances: Vec<Vec<f64>>,
+    pub currents: Vec<f64>,
+}
+
+impl LinearizedTerminals {
+    /** Builds the tangent of a device at the terminal voltages v, given its intensities and jacobian there. */
+    pub fn tangent(v: &[f64], intensities: Vec<f64>, conductances: Vec<Vec<f64>>) -> Self {
+        let currents = intensities.iter()
+            .zip(&conductances)
+            .map(|(intensity, row)| {
+                intensity - row.iter().zip(v).map(|(g, v)| g * v).sum::<f64>()
+            })
+            .collect();
+
+        Self { conductances, currents }
+    }
+}
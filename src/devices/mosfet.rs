@@ -0,0 +1,66 @@
+use crate::devices::LinearizedTerminals;
+use crate::model::Channel;
+
+/**
+Shichman–Hodges (SPICE level 1) model of a MOSFET whose bulk is tied to the source.
+
+The terminal voltages are given as [drain, gate, source] and the result is the linearized intensity
+entering each of those terminals. For an N-channel MOSFET with vds >= 0:
+- cutoff (vgs <= vth): Id = 0
+- triode (vds < vgs - vth): Id = kp * ((vgs - vth) * vds - vds^2 / 2) * (1 + lambda * vds)
+- saturation: Id = kp / 2 * (vgs - vth)^2 * (1 + lambda * vds)
+
+The device is symmetrical: when vds < 0, the drain and the source swap their roles. A P-channel
+MOSFET is an N-channel MOSFET with every voltage and intensity reversed.
+*/
+pub fn linearize(channel: Channel, vth: f64, kp: f64, lambda: f64, v: [f64; 3]) -> LinearizedTerminals {
+    let sign = match channel {
+        Channel::N => 1.0,
+        Channel::P => -1.0,
+    };
+    let [vd, vg, vs] = v.map(|v| sign * v);
+
+    let (id, conductances) = if vd >= vs {
+        let (id, gm, gds) = drain_current(vth, kp, lambda, vg - vs, vd - vs);
+        (id, [gds, gm, -gm - gds])
+    } else {
+        // The intensity flows from the source to the drain, the source acts as the drain.
+        let (id, gm, gds) = drain_current(vth, kp, lambda, vg - vd, vs - vd);
+        (-id, [gm + gds, -gm, -gds])
+    };
+
+    let linearized = LinearizedTerminals::tangent(
+        &[vd, vg, vs],
+        vec![id, 0.0, -id],
+        vec![conductances.to_vec(), vec![0.0; 3], conductances.map(|g| -g).to_vec()],
+    );
+
+    // Reversing both the voltages and the intensities keeps the jacobian but reverses the constants.
+    LinearizedTerminals {
+        conductances: linearized.conductances,
+        currents: linearized.currents.into_iter().map(|i| sign * i).collect(),
+    }
+}
+
+/** Returns the drain current and its derivatives gm = dId/dvgs and gds = dId/dvds, for vds >= 0. */
+fn drain_current(vth: f64, kp: f64, lambda: f64, vgs: f64, vds: f64) -> (f64, f64, f64) {
+    let vov = vgs - vth;
+    if vov <= 0.0 {
+        return (0.0, 0.0, 0.0);
+    }
+
+    let modulation = 1.0 + lambda * vds;
+    if vds < vov {
+        let shape = vov * vds - vds * vds / 2.0;
+        let id = kp * shape * modulation;
+        let gm = kp * vds * modulation;
+        let gds = kp * (vov - vds) * modulation + kp * shape * lambda;
+        (id, gm, gds)
+    } else {
+        let shape = vov * vov / 2.0;
+        let id = kp * shape * modulation;
+        let gm = kp * vov * modulation;
+        let gds = kp * shape * lambda;
+        (id, gm, gds)
+    }
+}
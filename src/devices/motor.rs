@@ -0,0 +1,20 @@
+/**
+Integrates the speed of a DC motor over a time step (forward Euler): J * dw/dt = ke * i - load,
+where the load torque always opposes the rotation like dry friction. A motor at rest does not start
+until its torque exceeds the load, and a decelerating motor stops instead of reversing.
+*/
+pub fn accelerate(back_emf_constant: f64, inertia: f64, load_torque: f64, speed: f64, intensity: f64, dt: f64) -> f64 {
+    let torque = back_emf_constant * intensity;
+    let direction = if speed != 0.0 { speed.signum() } else { torque.signum() };
+
+    if speed == 0.0 && torque.abs() <= load_torque {
+        return 0.0;
+    }
+
+    let new_speed = speed + (torque - load_torque * direction) * dt / inertia;
+    if speed != 0.0 && new_speed.signum() != speed.signum() {
+        0.0
+    } else {
+        new_speed
+    }
+}
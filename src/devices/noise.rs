@@ -0,0 +1,69 @@
+use std::f64::consts::PI;
+use crate::model::NoiseDistribution;
+
+/** SplitMix64 finalizer, scrambles the bits of a 64-bit integer. */
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/** Returns the index-th number of the stream of a seed, uniformly distributed in [0, 1). */
+fn uniform(seed: u64, index: u64) -> f64 {
+    (mix(seed ^ mix(index)) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/** Returns the index-th bit of the pseudo-random binary sequence of a seed. */
+pub fn bit(seed: u64, index: u64) -> bool {
+    uniform(seed, index) < 0.5
+}
+
+/**
+Returns the sample of a noise source for a time step. The samples only depend on the seed and the
+index of the step, so a simulation can be reproduced exactly.
+*/
+pub fn sample(distribution: NoiseDistribution, seed: u64, step: u64) -> f64 {
+    match distribution {
+        NoiseDistribution::Uniform { amplitude } => amplitude * (2.0 * uniform(seed, 2 * step) - 1.0),
+        NoiseDistribution::Gaussian { standard_deviation } => {
+            // Box–Muller transform, 1 - u keeps the logarithm finite.
+            let u1 = 1.0 - uniform(seed, 2 * step);
+            let u2 = uniform(seed, 2 * step + 1);
+            standard_deviation * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_reproducible() {
+        let distribution = NoiseDistribution::Uniform { amplitude: 1.0 };
+        assert_eq!(sample(distribution, 42, 7), sample(distribution, 42, 7));
+        assert_ne!(sample(distribution, 42, 7), sample(distribution, 43, 7));
+        assert_ne!(sample(distribution, 42, 7), sample(distribution, 42, 8));
+    }
+
+    #[test]
+    fn samples_follow_the_distribution() {
+        let count = 100_000;
+        let statistics = |distribution| {
+            let samples: Vec<f64> = (0..count).map(|step| sample(distribution, 1, step)).collect();
+            let mean = samples.iter().sum::<f64>() / count as f64;
+            let variance = samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / count as f64;
+            (samples, mean, variance.sqrt())
+        };
+
+        let (samples, mean, deviation) = statistics(NoiseDistribution::Uniform { amplitude: 2.0 });
+        assert!(samples.iter().all(|x| x.abs() <= 2.0));
+        assert!(mean.abs() < 0.02, "mean of {}", mean);
+        assert!((deviation - 2.0 / 3f64.sqrt()).abs() < 0.02, "standard deviation of {}", deviation);
+
+        let (_, mean, deviation) = statistics(NoiseDistribution::Gaussian { standard_deviation: 0.5 });
+        assert!(mean.abs() < 0.01, "mean of {}", mean);
+        assert!((deviation - 0.5).abs() < 0.01, "standard deviation of {}", deviation);
+    }
+}
@@ -0,0 +1,76 @@
+use std::f64::consts::PI;
+
+/**
+Voltage of the internal source of an op-amp, seen as a function of the differential input vd.
+
+Either it follows the input (E = gain * vd + offset), or it is stuck at a limit (rails or slew
+rate) and does not depend on the input anymore.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    Linear { gain: f64, offset: f64 },
+    Clipped(f64),
+}
+
+/**
+Single-pole op-amp macromodel: tau * dE/dt = gain * vd - E, with tau = 1 / (2 * pi * bandwidth).
+
+At DC, E = gain * vd. During a time step (Backward Euler), the pole gives
+E = (gain * vd * dt / tau + E_previous) / (1 + dt / tau), and E cannot move faster than the slew
+rate. In both cases, E is clipped between the rails.
+
+- vd: differential input voltage around which the output is evaluated (the guess).
+- step: duration of the time step and E at the beginning of it, None at DC.
+*/
+pub fn output(gain: f64, bandwidth: f64, slew_rate: f64, rails: (f64, f64), vd: f64, step: Option<(f64, f64)>) -> Output {
+    let (v_min, v_max) = rails;
+
+    let (linear_gain, offset, low, high) = match step {
+        None => (gain, 0.0, v_min, v_max),
+        Some((dt, e_previous)) => {
+            let ratio = dt * 2.0 * PI * bandwidth;
+            let max_step = slew_rate * dt;
+            (
+                gain * ratio / (1.0 + ratio),
+                e_previous / (1.0 + ratio),
+                v_min.max(e_previous - max_step),
+                v_max.min(e_previous + max_step),
+            )
+        }
+    };
+
+    let e = linear_gain * vd + offset;
+    if e > high {
+        Output::Clipped(high)
+    } else if e < low {
+        Output::Clipped(low)
+    } else {
+        Output::Linear { gain: linear_gain, offset }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dc_output_follows_the_gain_between_the_rails() {
+        assert_eq!(output(1e5, 10.0, 1e6, (-15.0, 15.0), 1e-5, None), Output::Linear { gain: 1e5, offset: 0.0 });
+        assert_eq!(output(1e5, 10.0, 1e6, (-15.0, 15.0), 1e-3, None), Output::Clipped(15.0));
+        assert_eq!(output(1e5, 10.0, 1e6, (-15.0, 15.0), -1e-3, None), Output::Clipped(-15.0));
+    }
+
+    #[test]
+    fn step_output_is_slew_rate_limited() {
+        // A large input would move the output by 100V in one step, the slew rate allows 1V.
+        assert_eq!(output(1e5, 10.0, 1e6, (-15.0, 15.0), 1.0, Some((1e-6, 2.0))), Output::Clipped(3.0));
+
+        // A small input follows the pole: the gain is reduced by dt / tau over 1 + dt / tau.
+        let Output::Linear { gain, offset } = output(1e5, 10.0, 1e6, (-15.0, 15.0), 0.0, Some((1e-6, 2.0))) else {
+            panic!("The output must be linear");
+        };
+        let ratio = 1e-6 * 2.0 * PI * 10.0;
+        assert!((gain - 1e5 * ratio / (1.0 + ratio)).abs() < 1e-9);
+        assert!((offset - 2.0 / (1.0 + ratio)).abs() < 1e-12);
+    }
+}
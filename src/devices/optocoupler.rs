@@ -0,0 +1,43 @@
+use crate::devices::{led, LinearizedTerminals};
+
+/**
+Collector-emitter resistance of the saturated phototransistor, in ohms. The characteristic is
+piecewise linear rather than smooth, which the Newton–Raphson iteration goes through without
+oscillating.
+*/
+const SATURATION_RESISTANCE: f64 = 10.0;
+
+/**
+Optocoupler: an LED whose forward intensity drives a phototransistor through the current transfer
+ratio (CTR).
+
+The terminal voltages are given as [anode, cathode, collector, emitter] and the result is the
+linearized intensity entering each of those terminals:
+- If = LED intensity at vf, rs (see `led::intensity`)
+- Ic = min(ctr * If, vce / SATURATION_RESISTANCE)
+*/
+pub fn linearize(vf: f64, rs: f64, ctr: f64, v: [f64; 4]) -> LinearizedTerminals {
+    let [va, vk, vc, ve] = v;
+
+    let (i_forward, g_forward) = led::intensity(vf, rs, va - vk);
+    let i_photo = ctr * i_forward;
+    let i_saturated = (vc - ve) / SATURATION_RESISTANCE;
+
+    // Intensity and derivatives of Ic with respect to the LED voltage and vce.
+    let (ic, dic) = if i_saturated < i_photo {
+        (i_saturated, (0.0, 1.0 / SATURATION_RESISTANCE))
+    } else {
+        (i_photo, (ctr * g_forward, 0.0))
+    };
+
+    LinearizedTerminals::tangent(
+        &v,
+        vec![i_forward, -i_forward, ic, -ic],
+        vec![
+            vec![g_forward, -g_forward, 0.0, 0.0],
+            vec![-g_forward, g_forward, 0.0, 0.0],
+            vec![dic.0, -dic.0, dic.1, -dic.1],
+            vec![-dic.0, dic.0, -dic.1, dic.1],
+        ],
+    )
+}
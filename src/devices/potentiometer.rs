@@ -0,0 +1,13 @@
+/**
+Smallest resistance of a side of the track, as a fraction of the whole track. A wiper sitting on an
+end would otherwise be a 0Ω resistor, which cannot be stamped.
+*/
+const MIN_FRACTION: f64 = 1e-9;
+
+/**
+Returns the resistances between terminal 0 and the wiper, and between the wiper and terminal 1.
+*/
+pub fn resistances(resistance: f64, position: f64) -> (f64, f64) {
+    let position = position.clamp(MIN_FRACTION, 1.0 - MIN_FRACTION);
+    (resistance * position, resistance * (1.0 - position))
+}
@@ -0,0 +1,31 @@
+/** Operating region of a linear regulator, which selects the equation of its output. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    /** Vout - Vground = nominal - impedance * Iout. */
+    Regulation,
+    /** The input is too low: Vout - Vin = -dropout - impedance * Iout. */
+    Dropout,
+    /** Iout = current_limit, the output voltage is set by the load. */
+    CurrentLimit,
+}
+
+/**
+Selects the region of a linear regulator from the terminal voltages and the output intensity of
+the guess. The regulated output is the lowest of the nominal voltage and the input minus the
+dropout, and the regulator limits its current when the load would draw more, which also shows as
+an output below the voltage it would have at the limit.
+
+The terminal voltages are given as [input, output, ground].
+*/
+pub fn region(nominal: f64, dropout: f64, impedance: f64, current_limit: f64, v: [f64; 3], i_out: f64) -> Region {
+    let [v_in, v_out, v_ground] = v;
+    let regulated = (v_ground + nominal).min(v_in - dropout);
+
+    if i_out > current_limit || v_out < regulated - impedance * current_limit {
+        Region::CurrentLimit
+    } else if v_ground + nominal <= v_in - dropout {
+        Region::Regulation
+    } else {
+        Region::Dropout
+    }
+}
@@ -0,0 +1,32 @@
+use crate::devices::Linearized;
+
+/** Conductance of a gap that is not struck, in siemens. */
+const OFF_CONDUCTANCE: f64 = 1e-12;
+
+/**
+Piecewise-linear characteristic of a spark gap (or a neon lamp). Once struck, it holds the holding
+voltage in either direction plus the drop across the arc resistance, and nothing flows under the
+holding voltage. Like the ideal diode, the segment is selected from the voltage v of the guess.
+*/
+pub fn linearize(struck: bool, holding_voltage: f64, arc_resistance: f64, v: f64) -> Linearized {
+    if !struck || v.abs() <= holding_voltage {
+        return Linearized { conductance: OFF_CONDUCTANCE, current: 0.0 };
+    }
+
+    Linearized {
+        conductance: 1.0 / arc_resistance,
+        current: -v.signum() * holding_voltage / arc_resistance,
+    }
+}
+
+/**
+Next state of the gap from an accepted solution: it strikes when the voltage exceeds the strike
+voltage, and goes out when the intensity falls under the extinction current.
+*/
+pub fn is_struck(struck: bool, strike_voltage: f64, extinction_current: f64, v: f64, intensity: f64) -> bool {
+    if struck {
+        intensity.abs() >= extinction_current
+    } else {
+        v.abs() > strike_voltage
+    }
+}
@@ -0,0 +1,11 @@
+/**
+Returns whether a switch is closed at the given time, knowing its state at t = 0 and the times at
+which it toggles.
+*/
+pub fn is_closed(closed: bool, toggle_times: &[f64], time: f64) -> bool {
+    let toggles = toggle_times.iter()
+        .filter(|toggle_time| **toggle_time <= time)
+        .count();
+
+    closed ^ (toggles % 2 == 1)
+}
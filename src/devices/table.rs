@@ -0,0 +1,17 @@
+/**
+Piecewise-linear interpolation of an I–V table of (voltage, intensity) points sorted by voltage.
+Outside of the table, the first and last segments are extended.
+
+Returns the intensity and its derivative (the conductance) at voltage v.
+*/
+pub fn interpolate(table: &[(f64, f64)], v: f64) -> (f64, f64) {
+    let segment = table.windows(2)
+        .position(|points| v < points[1].0)
+        .unwrap_or(table.len() - 2);
+
+    let (v0, i0) = table[segment];
+    let (v1, i1) = table[segment + 1];
+    let conductance = (i1 - i0) / (v1 - v0);
+
+    (i0 + conductance * (v - v0), conductance)
+}
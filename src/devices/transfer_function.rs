@@ -0,0 +1,102 @@
+use nalgebra::{DMatrix, DVector};
+
+/**
+State-space realization of a rational transfer function H(s): x' = A * x + B * u and
+y = C * x + D * u, in the controllable canonical form.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateSpace {
+    pub a: DMatrix<f64>,
+    pub b: DVector<f64>,
+    pub c: DVector<f64>,
+    pub d: f64,
+}
+
+/**
+Realizes H(s) = numerator(s) / denominator(s), the coefficients being ordered by increasing power
+of s. The transfer function must be proper: the numerator cannot have a higher degree than the
+denominator.
+*/
+pub fn realize(numerator: &[f64], denominator: &[f64]) -> StateSpace {
+    let order = denominator.len() - 1;
+    let leading = denominator[order];
+
+    // Both polynomials are normalized so that the denominator is monic.
+    let a_coefficients: Vec<f64> = denominator.iter().map(|a| a / leading).collect();
+    let mut b_coefficients = vec![0.0; order + 1];
+    for (power, b) in numerator.iter().enumerate() {
+        b_coefficients[power] = b / leading;
+    }
+
+    let mut a = DMatrix::zeros(order, order);
+    for i in 1..order {
+        a[(i - 1, i)] = 1.0;
+    }
+    for i in 0..order {
+        a[(order - 1, i)] = -a_coefficients[i];
+    }
+
+    let mut b = DVector::zeros(order);
+    if order > 0 {
+        b[order - 1] = 1.0;
+    }
+
+    let d = b_coefficients[order];
+    let c = DVector::from_fn(order, |i, _| b_coefficients[i] - a_coefficients[i] * d);
+
+    StateSpace { a, b, c, d }
+}
+
+impl StateSpace {
+    /**
+    Discretizes the realization over a time step (backward Euler), and returns the output as
+    y = gain * u + offset, where u is the input at the end of the step and x the state at its
+    beginning.
+    */
+    pub fn discretize(&self, x: &DVector<f64>, dt: f64) -> (f64, f64) {
+        let m = self.step_matrix(dt);
+        let gain = self.c.dot(&(&m * &self.b)) * dt + self.d;
+        let offset = self.c.dot(&(&m * x));
+        (gain, offset)
+    }
+
+    /** Returns the state at the end of a time step (backward Euler), given the input there. */
+    pub fn advance(&self, x: &DVector<f64>, u: f64, dt: f64) -> DVector<f64> {
+        self.step_matrix(dt) * (x + &self.b * (u * dt))
+    }
+
+    /** Returns the state in DC steady state for the input u, None if H(s) has a pole at 0. */
+    pub fn steady_state(&self, u: f64) -> Option<DVector<f64>> {
+        if self.b.is_empty() {
+            // A pure gain does not have any state.
+            return Some(DVector::zeros(0));
+        }
+        self.a.clone().lu().solve(&(-&self.b * u))
+    }
+
+    /** (I - dt * A)^-1, which maps the state of a backward Euler step. */
+    fn step_matrix(&self, dt: f64) -> DMatrix<f64> {
+        let order = self.b.len();
+        if order == 0 {
+            return DMatrix::zeros(0, 0);
+        }
+        (DMatrix::identity(order, order) - &self.a * dt)
+            .try_inverse()
+            .expect("The time step is too large for the transfer function")
+    }
+}
+
+/** Evaluates H(jw) = numerator(jw) / denominator(jw), returned as (real part, imaginary part). */
+pub fn frequency_response(numerator: &[f64], denominator: &[f64], angular_frequency: f64) -> (f64, f64) {
+    let evaluate = |coefficients: &[f64]| {
+        // Horner's method with complex arithmetic, in (re, im) pairs.
+        coefficients.iter().rev().fold((0.0, 0.0), |(re, im), coefficient| {
+            (coefficient - im * angular_frequency, re * angular_frequency)
+        })
+    };
+
+    let (n_re, n_im) = evaluate(numerator);
+    let (d_re, d_im) = evaluate(denominator);
+    let norm = d_re * d_re + d_im * d_im;
+    ((n_re * d_re + n_im * d_im) / norm, (n_im * d_re - n_re * d_im) / norm)
+}
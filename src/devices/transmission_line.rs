@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+/** Voltages and intensities at both ports of a transmission line at a given time. */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Sample {
+    pub time: f64,
+    pub v1: f64,
+    pub i1: f64,
+    pub v2: f64,
+    pub i2: f64,
+}
+
+/**
+Returns the state of the line at the given time, linearly interpolated in the history. Before the
+first sample the line is at rest in its first state, and after the last one it stays in it.
+*/
+pub fn delayed(history: &VecDeque<Sample>, time: f64) -> Sample {
+    let (Some(first), Some(last)) = (history.front(), history.back()) else {
+        return Sample { time, ..Sample::default() };
+    };
+    if time <= first.time {
+        return Sample { time, ..*first };
+    }
+    if time >= last.time {
+        return Sample { time, ..*last };
+    }
+
+    let i = history.iter().position(|sample| sample.time >= time).unwrap();
+    let (before, after) = (&history[i - 1], &history[i]);
+    let ratio = (time - before.time) / (after.time - before.time);
+    let lerp = |a: f64, b: f64| a + (b - a) * ratio;
+
+    Sample {
+        time,
+        v1: lerp(before.v1, after.v1),
+        i1: lerp(before.i1, after.i1),
+        v2: lerp(before.v2, after.v2),
+        i2: lerp(before.i2, after.i2),
+    }
+}
+
+/**
+Records a sample and forgets the ones that are older than the delay, except the last of them which
+is still needed to interpolate.
+*/
+pub fn record(history: &mut VecDeque<Sample>, sample: Sample, delay: f64) {
+    history.push_back(sample);
+    while history.len() > 2 && history[1].time <= sample.time - delay {
+        history.pop_front();
+    }
+}
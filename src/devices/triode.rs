@@ -0,0 +1,47 @@
+use crate::devices::LinearizedTerminals;
+use crate::model::TriodeModel;
+
+/**
+Koren model of a triode, ignoring the grid current.
+
+The terminal voltages are given as [plate, grid, cathode]:
+- E1 = vpk / kp * ln(1 + exp(kp * (1 / mu + vgk / sqrt(kvb + vpk^2))))
+- Ip = E1^ex / kg1 when E1 > 0, 0 otherwise.
+*/
+pub fn linearize(model: &TriodeModel, v: [f64; 3]) -> LinearizedTerminals {
+    let [vp, vg, vk] = v;
+    let (ip, g_plate, g_grid) = plate_current(model, vp - vk, vg - vk);
+
+    LinearizedTerminals::tangent(
+        &v,
+        vec![ip, 0.0, -ip],
+        vec![
+            vec![g_plate, g_grid, -g_plate - g_grid],
+            vec![0.0; 3],
+            vec![-g_plate, -g_grid, g_plate + g_grid],
+        ],
+    )
+}
+
+/** Returns the plate current and its derivatives with respect to vpk and vgk. */
+fn plate_current(model: &TriodeModel, vpk: f64, vgk: f64) -> (f64, f64, f64) {
+    let TriodeModel { mu, ex, kg1, kp, kvb } = *model;
+
+    let root = (kvb + vpk * vpk).sqrt();
+    let z = kp * (1.0 / mu + vgk / root);
+    // ln(1 + exp(z)) and its derivative, without overflowing for a large z.
+    let softplus = if z > 30.0 { z } else { z.exp().ln_1p() };
+    let sigmoid = 1.0 / (1.0 + (-z).exp());
+
+    let e1 = vpk / kp * softplus;
+    if e1 <= 0.0 {
+        return (0.0, 0.0, 0.0);
+    }
+
+    let de1_dvpk = softplus / kp - sigmoid * vgk * vpk * vpk / root.powi(3);
+    let de1_dvgk = vpk * sigmoid / root;
+
+    let ip = e1.powf(ex) / kg1;
+    let dip_de1 = ex * e1.powf(ex - 1.0) / kg1;
+    (ip, dip_de1 * de1_dvpk, dip_de1 * de1_dvgk)
+}
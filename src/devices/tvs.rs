@@ -0,0 +1,21 @@
+use crate::devices::{led, Linearized};
+
+/**
+Bidirectional TVS diode: it blocks below the breakdown voltage in both directions, then clamps
+with the slope 1/rc. It is modeled as two LED characteristics head to tail:
+
+i = iled(vbr, rc, v) - iled(vbr, rc, -v)
+
+Returns the intensity and its derivative (the conductance) at voltage v.
+*/
+pub fn intensity(vbr: f64, rc: f64, v: f64) -> (f64, f64) {
+    let (forward, forward_conductance) = led::intensity(vbr, rc, v);
+    let (reverse, reverse_conductance) = led::intensity(vbr, rc, -v);
+    (forward - reverse, forward_conductance + reverse_conductance)
+}
+
+/** Linearizes the TVS characteristic around the voltage v. */
+pub fn linearize(vbr: f64, rc: f64, v: f64) -> Linearized {
+    let (intensity, conductance) = intensity(vbr, rc, v);
+    Linearized::tangent(v, intensity, conductance)
+}
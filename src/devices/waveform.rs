@@ -0,0 +1,63 @@
+use std::f64::consts::PI;
+
+/**
+SPICE PULSE waveform: v[0] until the delay, then rises to v[1] in rise, stays there for width and
+falls back to v[0] in fall, every period (once if the period is 0).
+*/
+pub fn pulse(v: [f64; 2], delay: f64, rise: f64, fall: f64, width: f64, period: f64, t: f64) -> f64 {
+    if t < delay {
+        return v[0];
+    }
+
+    let t = if period > 0.0 { (t - delay) % period } else { t - delay };
+    if t < rise {
+        v[0] + (v[1] - v[0]) * t / rise
+    } else if t < rise + width {
+        v[1]
+    } else if t < rise + width + fall {
+        v[1] + (v[0] - v[1]) * (t - rise - width) / fall
+    } else {
+        v[0]
+    }
+}
+
+/**
+Piecewise-linear waveform through (time, value) points sorted by time, which holds its first and
+last values outside of them. Two points at the same time make a step.
+*/
+pub fn piecewise_linear(points: &[(f64, f64)], t: f64) -> f64 {
+    let Some(next) = points.iter().position(|(time, _)| t < *time) else {
+        return points[points.len() - 1].1;
+    };
+    if next == 0 {
+        return points[0].1;
+    }
+
+    let (t0, v0) = points[next - 1];
+    let (t1, v1) = points[next];
+    v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+}
+
+/**
+SPICE EXP waveform: v[0] until the rise delay, then an exponential approach to v[1] with the rise
+time constant, and from the fall delay an exponential return to v[0] with the fall time constant.
+*/
+pub fn exponential(v: [f64; 2], rise_delay: f64, rise_tau: f64, fall_delay: f64, fall_tau: f64, t: f64) -> f64 {
+    let mut value = v[0];
+    if t > rise_delay {
+        value += (v[1] - v[0]) * (1.0 - (-(t - rise_delay) / rise_tau).exp());
+    }
+    if t > fall_delay {
+        value += (v[0] - v[1]) * (1.0 - (-(t - fall_delay) / fall_tau).exp());
+    }
+    value
+}
+
+/**
+SPICE SFFM waveform: a carrier whose phase is modulated by a sine at the signal frequency,
+offset + amplitude * sin(2 * pi * carrier * t + modulation_index * sin(2 * pi * signal * t)).
+*/
+pub fn frequency_modulated(offset: f64, amplitude: f64, carrier_frequency: f64, modulation_index: f64, signal_frequency: f64, t: f64) -> f64 {
+    let modulation = modulation_index * (2.0 * PI * signal_frequency * t).sin();
+    offset + amplitude * (2.0 * PI * carrier_frequency * t + modulation).sin()
+}
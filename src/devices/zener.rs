@@ -0,0 +1,16 @@
+use crate::devices::diode::shockley;
+use crate::devices::Linearized;
+
+/**
+Zener diode: a forward Shockley junction in parallel with a reverse one that breaks down at vz.
+
+i = is * (exp(v / (n * Vt)) - 1) - is * exp(-(v + vz) / (n * Vt))
+*/
+pub fn linearize(is: f64, n: f64, vz: f64, v: f64) -> Linearized {
+    let (forward, forward_conductance) = shockley(is, n, v);
+    let (breakdown, breakdown_conductance) = shockley(is, n, -(v + vz));
+
+    // The breakdown junction is shifted by is so that no intensity flows at v = 0.
+    let intensity = forward - (breakdown + is);
+    Linearized::tangent(v, intensity, forward_conductance + breakdown_conductance)
+}
@@ -0,0 +1,160 @@
+use std::fmt::Debug;
+use crate::devices::logic;
+use crate::model::LogicFunction;
+
+/** Change of a digital signal at a time, in seconds. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Change {
+    pub time: f64,
+    pub signal: usize,
+    pub value: bool,
+}
+
+/**
+Process of the digital engine (a gate, a clock, the firmware of a microcontroller...), evaluated
+each time one of the signals it is sensitive to changes.
+*/
+pub trait Process: Debug {
+    /** Returns the signals the process is sensitive to. */
+    fn sensitivity(&self) -> Vec<usize>;
+
+    /** Reacts to a change of a signal at the given time, returning the changes it schedules, at that time or later. */
+    fn evaluate(&mut self, time: f64, signals: &[bool]) -> Vec<Change>;
+}
+
+/** Logic gate whose output follows its inputs after a propagation delay, in seconds. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gate {
+    pub function: LogicFunction,
+    pub inputs: Vec<usize>,
+    pub output: usize,
+    pub delay: f64,
+}
+
+impl Process for Gate {
+    fn sensitivity(&self) -> Vec<usize> {
+        self.inputs.clone()
+    }
+
+    fn evaluate(&mut self, time: f64, signals: &[bool]) -> Vec<Change> {
+        let inputs: Vec<bool> = self.inputs.iter().map(|input| signals[*input]).collect();
+        vec![Change { time: time + self.delay, signal: self.output, value: logic::evaluate(self.function, &inputs) }]
+    }
+}
+
+/**
+Clock toggling its output every half period, in seconds. It is sensitive to its own output, so it
+must be started by scheduling a first change of its output.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clock {
+    pub output: usize,
+    pub period: f64,
+}
+
+impl Process for Clock {
+    fn sensitivity(&self) -> Vec<usize> {
+        vec![self.output]
+    }
+
+    fn evaluate(&mut self, time: f64, signals: &[bool]) -> Vec<Change> {
+        vec![Change { time: time + self.period / 2.0, signal: self.output, value: !signals[self.output] }]
+    }
+}
+
+/**
+Event-driven digital simulator: the signals only change at scheduled times, and each change
+evaluates the processes sensitive to the signal, which schedule the following changes. It runs
+alongside the analog solver in `Simulator::transient_mixed`, the `DigitalOutput` and
+`DigitalInput` components being the boundaries between both.
+*/
+#[derive(Debug)]
+pub struct DigitalEngine {
+    signals: Vec<bool>,
+    /** The pending changes, sorted by time, in the order they were scheduled at equal times. */
+    queue: Vec<Change>,
+    processes: Vec<Box<dyn Process>>,
+    /** Every change of a signal applied, in order. */
+    history: Vec<Change>,
+}
+
+impl DigitalEngine {
+    /** Creates an engine with the given number of signals, all low. */
+    pub fn new(signal_count: usize) -> Self {
+        Self { signals: vec![false; signal_count], queue: Vec::new(), processes: Vec::new(), history: Vec::new() }
+    }
+
+    pub fn add_process(&mut self, process: Box<dyn Process>) {
+        self.processes.push(process);
+    }
+
+    /** Schedules a change of a signal. */
+    pub fn schedule(&mut self, change: Change) {
+        if change.signal >= self.signals.len() {
+            panic!("The signal {} does not exist", change.signal);
+        }
+        let position = self.queue.partition_point(|pending| pending.time <= change.time);
+        self.queue.insert(position, change);
+    }
+
+    pub fn signals(&self) -> &[bool] {
+        &self.signals
+    }
+
+    /** Returns the changes of the signals applied so far, in order, to plot the digital waveforms. */
+    pub fn history(&self) -> &[Change] {
+        &self.history
+    }
+
+    /** Returns the time of the next pending change. */
+    pub fn next_time(&self) -> Option<f64> {
+        self.queue.first().map(|change| change.time)
+    }
+
+    /**
+    Applies the pending changes up to the given time included, with the changes they cause at
+    those times. A change to the current level of a signal does not evaluate any process.
+    */
+    pub fn advance(&mut self, time: f64) {
+        while self.next_time().is_some_and(|next| next <= time) {
+            let change = self.queue.remove(0);
+            if self.signals[change.signal] == change.value {
+                continue;
+            }
+            self.signals[change.signal] = change.value;
+            self.history.push(change);
+
+            let mut scheduled = Vec::new();
+            for process in &mut self.processes {
+                if process.sensitivity().contains(&change.signal) {
+                    scheduled.extend(process.evaluate(change.time, &self.signals));
+                }
+            }
+            for scheduled in scheduled {
+                if scheduled.time < change.time {
+                    panic!("A process cannot schedule a change in the past");
+                }
+                self.schedule(scheduled);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_through_a_gate() {
+        let mut engine = DigitalEngine::new(2);
+        engine.add_process(Box::new(Clock { output: 0, period: 2.0 }));
+        engine.add_process(Box::new(Gate { function: LogicFunction::Not, inputs: vec![0], output: 1, delay: 0.5 }));
+        engine.schedule(Change { time: 0.0, signal: 0, value: true });
+
+        engine.advance(2.0);
+        let changes: Vec<(f64, usize, bool)> = engine.history().iter().map(|change| (change.time, change.signal, change.value)).collect();
+        assert_eq!(changes, vec![(0.0, 0, true), (1.0, 0, false), (1.5, 1, true), (2.0, 0, true)]);
+        assert_eq!(engine.next_time(), Some(2.5));
+        assert_eq!(engine.signals(), &[true, true]);
+    }
+}
@@ -10,7 +10,7 @@ impl DisjointSet {
     pub fn new(terminal_ids: Vec<TerminalID>) -> Self {
         let mut elements = HashMap::new();
         for edge in terminal_ids {
-            elements.insert(edge.clone(), edge.clone());
+            elements.insert(edge, edge);
         }
 
         Self { elements }
@@ -23,26 +23,24 @@ impl DisjointSet {
     }
 
     fn find(&self, terminal_id: &TerminalID) -> TerminalID {
-        let mut id = terminal_id.clone();
+        let mut id = *terminal_id;
         while self.elements.get(&id).expect("Terminal ID is not in the set") != &id {
-            id = self.elements[&id].clone();
+            id = self.elements[&id];
         }
         id
     }
 
     pub fn into_terminal_groups(self) -> Vec<Vec<TerminalID>> {
         let mut sets = HashMap::new();
-        for (terminal_id, _) in &self.elements {
+        for terminal_id in self.elements.keys() {
             let root = self.find(terminal_id);
 
             sets.entry(root)
                 .or_insert_with(Vec::new)
-                .push(terminal_id.clone());
+                .push(*terminal_id);
         }
 
-        sets.into_iter()
-            .map(|(_, set)| set)
-            .collect()
+        sets.into_values().collect()
     }
 }
 
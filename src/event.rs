@@ -0,0 +1,91 @@
+use crate::expression::Expression;
+
+/** Direction in which a quantity crosses a level. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Crossing {
+    Rising,
+    Falling,
+    Either,
+}
+
+impl Crossing {
+    /** Returns whether going from before to after, both relative to the level, is a crossing in this direction. */
+    pub fn crosses(&self, before: f64, after: f64) -> bool {
+        let rising = before < 0.0 && after >= 0.0;
+        let falling = before > 0.0 && after <= 0.0;
+        match self {
+            Crossing::Rising => rising,
+            Crossing::Falling => falling,
+            Crossing::Either => rising || falling,
+        }
+    }
+}
+
+/** Event watched by a transient analysis, see `Simulator::transient_with_events`. */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /** An expression of the unknowns and the time crosses a level, e.g. V(3.1) rising through 2.5. */
+    Threshold { expression: Expression, level: f64, direction: Crossing },
+    /** A scheduled time, in seconds, e.g. an edge of a generator. */
+    Breakpoint(f64),
+}
+
+impl Event {
+    /** An expression crossing 0. */
+    pub fn zero_crossing(expression: Expression, direction: Crossing) -> Self {
+        Event::Threshold { expression, level: 0.0, direction }
+    }
+}
+
+/** An event that happened during a transient analysis. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EventOccurrence {
+    /** Index of the event in the watched events. */
+    pub event: usize,
+    /** Time the analysis landed on the event, in seconds. */
+    pub time: f64,
+}
+
+/** Condition ending a transient analysis early, see `Simulator::transient_until`. */
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopCondition {
+    /** The event happens, e.g. V(3.1) rising through 4.5. */
+    Event(Event),
+    /**
+    The circuit has settled: every unknown stayed within the tolerances of its current value over
+    the last duration, in seconds.
+    */
+    SteadyState { duration: f64, relative_tolerance: f64, absolute_tolerance: f64 },
+}
+
+/** Why a transient analysis stopped. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StopReason {
+    /** The condition of this index was met. */
+    Condition(usize),
+    /** The analysis reached its stop time without meeting any condition. */
+    Completed,
+}
+
+/** Why and when a transient analysis stopped. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stop {
+    pub reason: StopReason,
+    /** Time of the last point, in seconds. */
+    pub time: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossing_directions() {
+        assert!(Crossing::Rising.crosses(-1.0, 0.0));
+        assert!(!Crossing::Rising.crosses(1.0, -1.0));
+        assert!(Crossing::Falling.crosses(1.0, -1.0));
+        assert!(!Crossing::Falling.crosses(0.0, -1.0));
+        assert!(Crossing::Either.crosses(-1.0, 1.0) && Crossing::Either.crosses(1.0, -1.0));
+        assert!(!Crossing::Either.crosses(1.0, 2.0));
+    }
+}
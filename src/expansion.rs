@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use crate::model::{Circuit, ComponentID, Components, Polarity, TerminalID};
+
+/**
+A network of primitive components that a composite component is made of.
+
+Terminals are local to the network: (part, idx) is the terminal idx of the part-th component.
+*/
+struct Network {
+    parts: Vec<Components>,
+    edges: Vec<((usize, usize), (usize, usize))>,
+    /** The local terminal each terminal of the composite component is connected to. */
+    terminals: Vec<(usize, usize)>,
+}
+
+impl Network {
+    /** Chains bipoles in series, terminal 0 of the first one to terminal 1 of the last one. */
+    fn series(parts: Vec<Components>) -> Self {
+        let edges = (1..parts.len())
+            .map(|part| ((part - 1, 1), (part, 0)))
+            .collect();
+        let terminals = vec![(0, 0), (parts.len() - 1, 1)];
+
+        Self { parts, edges, terminals }
+    }
+
+    /** Builds a network from the groups of local terminals that are connected together. */
+    fn from_nodes(parts: Vec<Components>, nodes: &[&[(usize, usize)]], terminals: Vec<(usize, usize)>) -> Self {
+        let edges = nodes
+            .iter()
+            .flat_map(|node| node[1..].iter().map(|terminal| (node[0], *terminal)))
+            .collect();
+
+        Self { parts, edges, terminals }
+    }
+
+    /** Adds a bipole across the two terminals of the network. */
+    fn shunt(mut self, part: Components) -> Self {
+        let shunt_id = self.parts.len();
+        self.parts.push(part);
+        self.edges.push((self.terminals[0], (shunt_id, 0)));
+        self.edges.push((self.terminals[1], (shunt_id, 1)));
+        self
+    }
+}
+
+/** A circuit whose composite components have been replaced by primitive ones. */
+pub struct Expansion {
+    /**
+    The composite components are kept at their position so that component IDs do not change, but
+    they are not connected anymore: their parts are appended at the end of the components.
+    */
+    pub circuit: Circuit,
+    /** For each terminal of a composite component, the terminal of a part it is connected to. */
+    pub terminals: HashMap<TerminalID, TerminalID>,
+    /** For each part, the composite component it was expanded from. */
+    pub parents: HashMap<ComponentID, ComponentID>,
+}
+
+/**
+Returns the network of primitive components a component is made of, None if it is primitive. A
+composite component may give its own parts away, it is not used anymore once expanded.
+*/
+fn get_network(component: &mut Components) -> Option<Network> {
+    if let Components::Oriented { component, polarity } = component {
+        let terminals = match polarity {
+            Polarity::Normal => vec![(0, 0), (0, 1)],
+            Polarity::Reversed => vec![(0, 1), (0, 0)],
+        };
+        let part = std::mem::replace(component.as_mut(), Components::Wire);
+        return Some(Network { parts: vec![part], edges: vec![], terminals });
+    }
+
+    match &*component {
+        Components::RealCapacitor { capacitance, esr, esl } => {
+            let mut parts = vec![Components::Capacitor(*capacitance)];
+            parts.extend(esr.map(Components::Resistor));
+            parts.extend(esl.map(Components::Inductor));
+            Some(Network::series(parts))
+        }
+        Components::RealInductor { inductance, dcr, parallel_capacitance } => {
+            let mut parts = vec![Components::Inductor(*inductance)];
+            parts.extend(dcr.map(Components::Resistor));
+            let network = Network::series(parts);
+            Some(match parallel_capacitance {
+                Some(capacitance) => network.shunt(Components::Capacitor(*capacitance)),
+                None => network,
+            })
+        }
+        Components::NortonSource { current, parallel_resistance } => {
+            Some(Network::series(vec![Components::CurrentGenerator(*current)]).shunt(Components::Resistor(*parallel_resistance)))
+        }
+        Components::SolarCell { photocurrent, irradiance, is, n, series_resistance, shunt_resistance } => {
+            // The junction is the positive terminal of the source (0, 1) and the negative one is
+            // the terminal 0 of the cell.
+            Some(Network {
+                parts: vec![
+                    Components::CurrentGenerator(photocurrent * irradiance),
+                    Components::Diode { is: *is, n: *n },
+                    Components::Resistor(*shunt_resistance),
+                    Components::Resistor(*series_resistance),
+                ],
+                edges: vec![
+                    ((0, 1), (1, 0)),
+                    ((0, 1), (2, 0)),
+                    ((0, 1), (3, 0)),
+                    ((0, 0), (1, 1)),
+                    ((0, 0), (2, 1)),
+                ],
+                terminals: vec![(0, 0), (3, 1)],
+            })
+        }
+        Components::ThreePhaseSource { amplitude, frequency } => {
+            let phase = |shift: f64| Components::SineGenerator { amplitude: *amplitude, frequency: *frequency, phase: shift, dc_offset: 0.0 };
+            // Every generator has the neutral on its terminal 0.
+            Some(Network {
+                parts: vec![phase(0.0), phase(-2.0 * PI / 3.0), phase(2.0 * PI / 3.0)],
+                edges: vec![((0, 0), (1, 0)), ((0, 0), (2, 0))],
+                terminals: vec![(0, 1), (1, 1), (2, 1), (0, 0)],
+            })
+        }
+        Components::Timer555 => Some(timer_555()),
+        Components::BuckConverter { duty, inductance, capacitance } => {
+            // The DC transformer has its primary on the input: V_primary = V_secondary / duty.
+            let input: &[_] = &[(0, 1)];
+            let ground: &[_] = &[(0, 0), (0, 2), (2, 0)];
+            let switch: &[_] = &[(0, 3), (1, 0)];
+            let output: &[_] = &[(1, 1), (2, 1)];
+            Some(Network::from_nodes(
+                vec![
+                    Components::Transformer { ratio: 1.0 / duty },
+                    Components::Inductor(*inductance),
+                    Components::Capacitor(*capacitance),
+                ],
+                &[input, ground, switch, output],
+                vec![(0, 1), (1, 1), (0, 0)],
+            ))
+        }
+        Components::BoostConverter { duty, inductance, capacitance } => {
+            // The DC transformer has its primary on the inductor: V_primary = (1 - duty) * V_output.
+            let input: &[_] = &[(0, 0)];
+            let switch: &[_] = &[(0, 1), (1, 1)];
+            let ground: &[_] = &[(1, 0), (1, 2), (2, 0)];
+            let output: &[_] = &[(1, 3), (2, 1)];
+            Some(Network::from_nodes(
+                vec![
+                    Components::Inductor(*inductance),
+                    Components::Transformer { ratio: 1.0 - duty },
+                    Components::Capacitor(*capacitance),
+                ],
+                &[input, switch, ground, output],
+                vec![(0, 0), (1, 3), (1, 0)],
+            ))
+        }
+        Components::Loudspeaker { re, le, fs, qms, qes } => {
+            // The motional impedance: Cmes = Qes / (w * Re), Lces = 1 / (w^2 * Cmes) and
+            // Res = Re * Qms / Qes, where w = 2 * pi * fs.
+            let angular_frequency = 2.0 * PI * fs;
+            let capacitance = qes / (angular_frequency * re);
+            let voice_coil: &[_] = &[(0, 1), (1, 0)];
+            let motional_input: &[_] = &[(1, 1), (2, 0), (3, 0), (4, 0)];
+            let motional_output: &[_] = &[(2, 1), (3, 1), (4, 1)];
+            Some(Network::from_nodes(
+                vec![
+                    Components::Resistor(*re),
+                    Components::Inductor(*le),
+                    Components::Inductor(1.0 / (angular_frequency * angular_frequency * capacitance)),
+                    Components::Capacitor(capacitance),
+                    Components::Resistor(re * qms / qes),
+                ],
+                &[voice_coil, motional_input, motional_output],
+                vec![(0, 0), (2, 1)],
+            ))
+        }
+        _ => None,
+    }
+}
+
+/** The inside of a 555 timer, the reset pin acts under 0.7V. */
+fn timer_555() -> Network {
+    let parts = vec![
+        // The divider sets the thresholds at 2/3 (control) and 1/3 of the supply.
+        Components::Resistor(5e3),
+        Components::Resistor(5e3),
+        Components::Resistor(5e3),
+        // Trigger, threshold and reset comparators.
+        Components::Comparator { output_resistance: 10.0 },
+        Components::Comparator { output_resistance: 10.0 },
+        Components::VoltageGenerator(0.7),
+        Components::Comparator { output_resistance: 10.0 },
+        // The threshold and reset comparators both reset the latch (diode OR).
+        Components::IdealDiode { forward_voltage: 0.0 },
+        Components::IdealDiode { forward_voltage: 0.0 },
+        Components::Resistor(100e3),
+        Components::SrLatch { output_resistance: 10.0 },
+        // The discharge switch is closed while the latch is reset.
+        Components::ControlledSwitch { on_resistance: 10.0, off_resistance: 1e9, threshold: 1.0 },
+    ];
+
+    let supply: &[_] = &[(0, 0), (3, 4), (4, 4), (6, 4), (10, 5)];
+    let control: &[_] = &[(0, 1), (1, 0), (4, 1)];
+    let low_threshold: &[_] = &[(1, 1), (2, 0), (3, 0)];
+    let ground: &[_] = &[(2, 1), (3, 3), (4, 3), (5, 0), (6, 3), (9, 1), (10, 4), (11, 1), (11, 2)];
+    let set: &[_] = &[(3, 2), (10, 0)];
+    let threshold_reset: &[_] = &[(4, 2), (7, 0)];
+    let reference: &[_] = &[(5, 1), (6, 0)];
+    let pin_reset: &[_] = &[(6, 2), (8, 0)];
+    let reset: &[_] = &[(7, 1), (8, 1), (9, 0), (10, 1)];
+    let not_q: &[_] = &[(10, 3), (11, 3)];
+
+    Network::from_nodes(
+        parts,
+        &[supply, control, low_threshold, ground, set, threshold_reset, reference, pin_reset, reset, not_q],
+        vec![(2, 1), (3, 1), (10, 2), (6, 1), (0, 1), (4, 0), (11, 0), (0, 0)],
+    )
+}
+
+/** Replaces the composite components of a circuit by the primitive components they are made of. */
+pub fn expand(circuit: Circuit) -> Expansion {
+    let Circuit { mut components, mut terminal_edges, couplings, tolerances, temperature_coefficients, initial_conditions, thermal_models, ratings } = circuit;
+    let mut terminals = HashMap::new();
+    let mut parents = HashMap::new();
+
+    // The parts are appended while iterating, so composite parts are expanded too.
+    let mut component_id = 0;
+    while component_id < components.len() {
+        let Some(network) = get_network(&mut components[component_id]) else {
+            component_id += 1;
+            continue;
+        };
+
+        let first_part_id = components.len();
+        let local_to_global = |(part, idx): (usize, usize)| TerminalID::new(first_part_id + part, idx);
+
+        for (idx, local) in network.terminals.iter().enumerate() {
+            terminals.insert(TerminalID::new(component_id, idx), local_to_global(*local));
+        }
+        for (left, right) in &mut terminal_edges {
+            for terminal_id in [left, right] {
+                if let Some(moved) = terminals.get(terminal_id) {
+                    *terminal_id = *moved;
+                }
+            }
+        }
+        terminal_edges.extend(network.edges.iter().map(|(left, right)| (local_to_global(*left), local_to_global(*right))));
+        parents.extend((first_part_id..first_part_id + network.parts.len()).map(|part_id| (ComponentID(part_id), ComponentID(component_id))));
+        components.extend(network.parts);
+        component_id += 1;
+    }
+
+    // A composite part is itself expanded: follow the terminals down to a primitive part.
+    let resolved = terminals.keys()
+        .map(|terminal_id| {
+            let mut moved = terminals[terminal_id];
+            while let Some(next) = terminals.get(&moved) {
+                moved = *next;
+            }
+            (*terminal_id, moved)
+        })
+        .collect();
+
+    Expansion {
+        circuit: Circuit { components, terminal_edges, couplings, tolerances, temperature_coefficients, initial_conditions, thermal_models, ratings },
+        terminals: resolved,
+        parents,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** Expands a composite component whose terminal 1 is wired to a resistor. */
+    fn expand_one(component: Components) -> Expansion {
+        expand(Circuit::new(
+            vec![Components::Resistor(1.0), component],
+            vec![(TerminalID::new(0, 0), TerminalID::new(1, 1))],
+        ))
+    }
+
+    /** Returns the part each terminal of the composite component 1 is moved to, as (part, idx) from its first part. */
+    fn terminal_map(expansion: &Expansion, count: usize) -> Vec<(usize, usize)> {
+        (0..count)
+            .map(|idx| {
+                let terminal_id = expansion.terminals[&TerminalID::new(1, idx)];
+                (terminal_id.component_id.0 - 2, terminal_id.idx)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn timer_555_terminals() {
+        let expansion = expand_one(Components::Timer555);
+        assert_eq!(expansion.circuit.components.len(), 2 + 12);
+        assert!(matches!(expansion.circuit.components[2 + 10], Components::SrLatch { .. }));
+        assert_eq!(
+            terminal_map(&expansion, 8),
+            vec![(2, 1), (3, 1), (10, 2), (6, 1), (0, 1), (4, 0), (11, 0), (0, 0)],
+        );
+
+        // The trigger pin is moved to the trigger comparator in the edges too.
+        assert!(expansion.circuit.terminal_edges.contains(&(TerminalID::new(0, 0), TerminalID::new(2 + 3, 1))));
+    }
+
+    #[test]
+    fn buck_converter_terminals() {
+        let expansion = expand_one(Components::BuckConverter { duty: 0.25, inductance: 1e-3, capacitance: 1e-6 });
+        assert!(matches!(expansion.circuit.components[2], Components::Transformer { ratio } if ratio == 4.0));
+        assert!(matches!(expansion.circuit.components[3], Components::Inductor(inductance) if inductance == 1e-3));
+        assert_eq!(terminal_map(&expansion, 3), vec![(0, 1), (1, 1), (0, 0)]);
+        assert!(expansion.circuit.terminal_edges.contains(&(TerminalID::new(0, 0), TerminalID::new(3, 1))));
+    }
+
+    #[test]
+    fn boost_converter_terminals() {
+        let expansion = expand_one(Components::BoostConverter { duty: 0.25, inductance: 1e-3, capacitance: 1e-6 });
+        assert!(matches!(expansion.circuit.components[3], Components::Transformer { ratio } if ratio == 0.75));
+        assert!(matches!(expansion.circuit.components[4], Components::Capacitor(capacitance) if capacitance == 1e-6));
+        assert_eq!(terminal_map(&expansion, 3), vec![(0, 0), (1, 3), (1, 0)]);
+        assert!(expansion.circuit.terminal_edges.contains(&(TerminalID::new(0, 0), TerminalID::new(3, 3))));
+    }
+}
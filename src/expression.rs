@@ -0,0 +1,389 @@
+use crate::model::{ComponentID, TerminalID};
+
+/** An unknown of the circuit an expression depends on. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variable {
+    /** V(c.t): voltage of the terminal t of the component c. */
+    Voltage(TerminalID),
+    /** I(c): branch intensity of the component c. */
+    Intensity(ComponentID),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Exp,
+    Ln,
+    Sqrt,
+    Abs,
+    Tanh,
+}
+
+/**
+Arithmetic expression over the unknowns of a circuit and the time, for example
+`3*V(1.0) - 0.5*I(2) + sin(2*pi*50*t)`.
+
+- V(c.t) is the voltage of the terminal t of the component c, I(c) the branch intensity of the
+  component c (a voltage generator, an inductor, ...) and t the time in seconds.
+- The operators are + - * / and ^, the functions sin, cos, exp, ln, sqrt, abs and tanh, and pi is
+  a constant.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Variable(Variable),
+    Time,
+    Negate(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Power(Box<Expression>, Box<Expression>),
+    Call(Function, Box<Expression>),
+}
+
+impl Expression {
+    /** Parses an expression, see `Expression` for the syntax. */
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser { source: source.as_bytes(), position: 0 };
+        let expression = parser.sum()?;
+        parser.skip_whitespace();
+        if parser.position != source.len() {
+            return Err(format!("Unexpected character at position {}", parser.position));
+        }
+        Ok(expression)
+    }
+
+    /** Returns the variables the expression depends on, without duplicates. */
+    pub fn variables(&self) -> Vec<Variable> {
+        let mut variables = Vec::new();
+        self.collect_variables(&mut variables);
+        variables
+    }
+
+    fn collect_variables(&self, variables: &mut Vec<Variable>) {
+        match self {
+            Expression::Variable(variable) => {
+                if !variables.contains(variable) {
+                    variables.push(*variable);
+                }
+            }
+            Expression::Number(_) | Expression::Time => {}
+            Expression::Negate(operand) | Expression::Call(_, operand) => operand.collect_variables(variables),
+            Expression::Add(left, right) | Expression::Subtract(left, right) | Expression::Multiply(left, right)
+            | Expression::Divide(left, right) | Expression::Power(left, right) => {
+                left.collect_variables(variables);
+                right.collect_variables(variables);
+            }
+        }
+    }
+
+    /** Evaluates the expression with the given values of the variables, at the given time. */
+    pub fn evaluate(&self, value: &dyn Fn(Variable) -> f64, time: f64) -> f64 {
+        self.evaluate_derivative(value, time, None).0
+    }
+
+    /**
+    Evaluates the expression and its derivative with respect to a variable (forward mode automatic
+    differentiation), with respect to nothing if None.
+    */
+    pub fn evaluate_derivative(&self, value: &dyn Fn(Variable) -> f64, time: f64, with_respect_to: Option<Variable>) -> (f64, f64) {
+        let eval = |expression: &Expression| expression.evaluate_derivative(value, time, with_respect_to);
+
+        match self {
+            Expression::Number(number) => (*number, 0.0),
+            Expression::Time => (time, 0.0),
+            Expression::Variable(variable) => {
+                (value(*variable), if Some(*variable) == with_respect_to { 1.0 } else { 0.0 })
+            }
+            Expression::Negate(operand) => {
+                let (v, d) = eval(operand);
+                (-v, -d)
+            }
+            Expression::Add(left, right) => {
+                let ((a, da), (b, db)) = (eval(left), eval(right));
+                (a + b, da + db)
+            }
+            Expression::Subtract(left, right) => {
+                let ((a, da), (b, db)) = (eval(left), eval(right));
+                (a - b, da - db)
+            }
+            Expression::Multiply(left, right) => {
+                let ((a, da), (b, db)) = (eval(left), eval(right));
+                (a * b, chain(b, da) + chain(a, db))
+            }
+            Expression::Divide(left, right) => {
+                let ((a, da), (b, db)) = (eval(left), eval(right));
+                (a / b, (chain(b, da) - chain(a, db)) / (b * b))
+            }
+            Expression::Power(left, right) => {
+                let ((a, da), (b, db)) = (eval(left), eval(right));
+                let v = a.powf(b);
+                // d(a^b) = b * a^(b - 1) * da + a^b * ln(a) * db.
+                let d = chain(b * a.powf(b - 1.0), da) + chain(v * a.ln(), db);
+                (v, d)
+            }
+            Expression::Call(function, operand) => {
+                let (x, dx) = eval(operand);
+                let (v, derivative) = match function {
+                    Function::Sin => (x.sin(), x.cos()),
+                    Function::Cos => (x.cos(), -x.sin()),
+                    Function::Exp => (x.exp(), x.exp()),
+                    Function::Ln => (x.ln(), 1.0 / x),
+                    Function::Sqrt => (x.sqrt(), 0.5 / x.sqrt()),
+                    Function::Abs => (x.abs(), x.signum()),
+                    Function::Tanh => (x.tanh(), 1.0 - x.tanh() * x.tanh()),
+                };
+                (v, chain(derivative, dx))
+            }
+        }
+    }
+}
+
+/**
+Returns the term outer * inner of a derivative, 0 when the inner derivative is 0 even if outer is
+not finite, e.g. for sqrt(t) derived with respect to a voltage at t = 0.
+*/
+fn chain(outer: f64, inner: f64) -> f64 {
+    if inner == 0.0 { 0.0 } else { outer * inner }
+}
+
+/** Recursive descent parser, one method per precedence level. */
+struct Parser<'a> {
+    source: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.position < self.source.len() && self.source[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.source.get(self.position).copied()
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), String> {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' at position {}", expected as char, self.position))
+        }
+    }
+
+    /** sum := product (('+' | '-') product)* */
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut left = self.product()?;
+        loop {
+            match self.peek() {
+                Some(b'+') => {
+                    self.position += 1;
+                    left = Expression::Add(Box::new(left), Box::new(self.product()?));
+                }
+                Some(b'-') => {
+                    self.position += 1;
+                    left = Expression::Subtract(Box::new(left), Box::new(self.product()?));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    /** product := unary (('*' | '/') unary)* */
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(b'*') => {
+                    self.position += 1;
+                    left = Expression::Multiply(Box::new(left), Box::new(self.unary()?));
+                }
+                Some(b'/') => {
+                    self.position += 1;
+                    left = Expression::Divide(Box::new(left), Box::new(self.unary()?));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    /** unary := '-' unary | power */
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+            return Ok(Expression::Negate(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    /** power := primary ('^' unary)?, right associative. */
+    fn power(&mut self) -> Result<Expression, String> {
+        let base = self.primary()?;
+        if self.peek() == Some(b'^') {
+            self.position += 1;
+            return Ok(Expression::Power(Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    /** primary := number | '(' sum ')' | name | name '(' arguments ')' */
+    fn primary(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some(b'(') => {
+                self.position += 1;
+                let expression = self.sum()?;
+                self.expect(b')')?;
+                Ok(expression)
+            }
+            Some(c) if c.is_ascii_digit() || c == b'.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.name();
+                match name.as_str() {
+                    "t" | "time" => Ok(Expression::Time),
+                    "pi" => Ok(Expression::Number(std::f64::consts::PI)),
+                    "V" => {
+                        self.expect(b'(')?;
+                        let component_id = self.integer()?;
+                        self.expect(b'.')?;
+                        let idx = self.integer()?;
+                        self.expect(b')')?;
+                        Ok(Expression::Variable(Variable::Voltage(TerminalID::new(component_id, idx))))
+                    }
+                    "I" => {
+                        self.expect(b'(')?;
+                        let component_id = self.integer()?;
+                        self.expect(b')')?;
+                        Ok(Expression::Variable(Variable::Intensity(ComponentID(component_id))))
+                    }
+                    _ => {
+                        let function = match name.as_str() {
+                            "sin" => Function::Sin,
+                            "cos" => Function::Cos,
+                            "exp" => Function::Exp,
+                            "ln" => Function::Ln,
+                            "sqrt" => Function::Sqrt,
+                            "abs" => Function::Abs,
+                            "tanh" => Function::Tanh,
+                            _ => return Err(format!("Unknown name '{}'", name)),
+                        };
+                        self.expect(b'(')?;
+                        let argument = self.sum()?;
+                        self.expect(b')')?;
+                        Ok(Expression::Call(function, Box::new(argument)))
+                    }
+                }
+            }
+            _ => Err(format!("Unexpected end or character at position {}", self.position)),
+        }
+    }
+
+    fn name(&mut self) -> String {
+        let start = self.position;
+        while self.position < self.source.len() && self.source[self.position].is_ascii_alphanumeric() {
+            self.position += 1;
+        }
+        String::from_utf8_lossy(&self.source[start..self.position]).into_owned()
+    }
+
+    fn integer(&mut self) -> Result<usize, String> {
+        self.skip_whitespace();
+        let start = self.position;
+        while self.position < self.source.len() && self.source[self.position].is_ascii_digit() {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.source[start..self.position])
+            .unwrap()
+            .parse()
+            .map_err(|_| format!("Expected an integer at position {}", start))
+    }
+
+    fn number(&mut self) -> Result<Expression, String> {
+        let start = self.position;
+        while self.position < self.source.len() {
+            let c = self.source[self.position];
+            let exponent_sign = (c == b'-' || c == b'+') && matches!(self.source[self.position - 1], b'e' | b'E');
+            if !(c.is_ascii_digit() || c == b'.' || c == b'e' || c == b'E' || exponent_sign) {
+                break;
+            }
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.source[start..self.position]).unwrap();
+        text.parse()
+            .map(Expression::Number)
+            .map_err(|_| format!("Invalid number '{}' at position {}", text, start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(source: &str) -> f64 {
+        Expression::parse(source).unwrap().evaluate(&|_| 0.0, 0.0)
+    }
+
+    #[test]
+    fn parse_follows_precedence() {
+        assert_eq!(evaluate("1 + 2 * 3 ^ 2"), 19.0);
+        assert_eq!(evaluate("2 - 3 - 4"), -5.0);
+        assert_eq!(evaluate("8 / 4 / 2"), 1.0);
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(evaluate("-2 ^ 2"), -4.0);
+        assert_eq!(evaluate("(1 + 2) * 3"), 9.0);
+        assert_eq!(evaluate("2 * -3"), -6.0);
+        assert_eq!(evaluate("1.5e-3 * 2e+3"), 3.0);
+    }
+
+    #[test]
+    fn parse_reads_variables() {
+        let expression = Expression::parse("3*V(1.0) - 0.5*I(2) + t").unwrap();
+        assert_eq!(expression.variables(), vec![Variable::Voltage(TerminalID::new(1, 0)), Variable::Intensity(ComponentID(2))]);
+
+        let value = |variable| match variable {
+            Variable::Voltage(_) => 2.0,
+            Variable::Intensity(_) => 4.0,
+        };
+        assert_eq!(expression.evaluate(&value, 1.0), 5.0);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_expressions() {
+        assert!(Expression::parse("1 +").is_err());
+        assert!(Expression::parse("(1 + 2").is_err());
+        assert!(Expression::parse("foo(1)").is_err());
+        assert!(Expression::parse("1 2").is_err());
+    }
+
+    #[test]
+    fn derivative_matches_finite_differences() {
+        let expression = Expression::parse("V(1.1)^3 / (1 + exp(V(1.1))) + sin(2*V(1.1)) * sqrt(V(2.0)) - tanh(V(1.1) - 1) * ln(V(1.1))").unwrap();
+        let x = Variable::Voltage(TerminalID::new(1, 1));
+        let at = |v: f64| move |variable| if variable == x { v } else { 0.7 };
+
+        for v in [0.3, 1.0, 2.5] {
+            let (_, derivative) = expression.evaluate_derivative(&at(v), 0.0, Some(x));
+            let h = 1e-6;
+            let finite_difference = (expression.evaluate(&at(v + h), 0.0) - expression.evaluate(&at(v - h), 0.0)) / (2.0 * h);
+            assert!((derivative - finite_difference).abs() < 1e-6 * (1.0 + derivative.abs()), "{} != {}", derivative, finite_difference);
+        }
+    }
+
+    #[test]
+    fn derivative_skips_terms_that_do_not_vary() {
+        let (x, y) = (Variable::Voltage(TerminalID::new(1, 1)), Variable::Voltage(TerminalID::new(2, 0)));
+        let at = |variable| if variable == x { 3.0 } else { 0.0 };
+
+        // sqrt(t) has an infinite derivative at t = 0, which does not depend on V(1.1).
+        let (v, derivative) = Expression::parse("sqrt(t) * V(1.1)").unwrap().evaluate_derivative(&at, 0.0, Some(x));
+        assert_eq!((v, derivative), (0.0, 0.0));
+
+        let (v, derivative) = Expression::parse("V(1.1) + sqrt(V(2.0))").unwrap().evaluate_derivative(&at, 0.0, Some(x));
+        assert_eq!((v, derivative), (3.0, 1.0));
+        assert_eq!(Expression::parse("V(1.1) + sqrt(V(2.0))").unwrap().evaluate_derivative(&at, 0.0, Some(y)).1, f64::INFINITY);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::expression::Expression;
+use crate::model::{Circuit, Components, TerminalID};
+
+/** Resistance of a short fault, in ohms. */
+pub const SHORT_RESISTANCE: f64 = 1e-3;
+/** Resistance left in series with an opened terminal, in ohms, so that no terminal is left unconnected. */
+pub const OPEN_RESISTANCE: f64 = 1e9;
+
+/** A fault injected into a circuit. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Fault {
+    /** The terminal is disconnected from its node, e.g. a broken lead or a cracked solder joint. */
+    Open(TerminalID),
+    /** The two terminals are connected together, e.g. a shorted capacitor or a solder bridge. */
+    Short(TerminalID, TerminalID),
+}
+
+impl Fault {
+    /**
+    Returns the usual faults of every component of a circuit: each terminal opened and each pair
+    of terminals shorted. Only the terminal 0 of a two-terminal component is opened, opening the
+    other one being the same fault. Grounds and wires are not faulted.
+    */
+    pub fn all(circuit: &Circuit) -> Vec<Fault> {
+        let mut faults = Vec::new();
+        for (component_id, component) in circuit.components.iter().enumerate() {
+            let count = component.terminal_count();
+            if count < 2 || matches!(component, Components::Wire) {
+                continue;
+            }
+
+            let opened = if count == 2 { 1 } else { count };
+            faults.extend((0..opened).map(|idx| Fault::Open(TerminalID::new(component_id, idx))));
+            for a in 0..count {
+                faults.extend((a + 1..count).map(|b| Fault::Short(TerminalID::new(component_id, a), TerminalID::new(component_id, b))));
+            }
+        }
+        faults
+    }
+
+    /**
+    Injects the fault into a circuit. The resistors of the fault are appended to the components,
+    so the IDs of the existing ones do not change. An opened terminal is detached from the
+    terminals it was connected to, which stay connected to each other, and joined to them by
+    `OPEN_RESISTANCE`.
+    */
+    pub fn apply(&self, circuit: &mut Circuit) {
+        let resistor_id = circuit.components.len();
+        match *self {
+            Fault::Open(terminal_id) => {
+                let neighbours: Vec<TerminalID> = circuit.terminal_edges
+                    .iter()
+                    .filter_map(|(left, right)| {
+                        if *left == terminal_id {
+                            Some(*right)
+                        } else if *right == terminal_id {
+                            Some(*left)
+                        } else {
+                            None
+                        }
+                    })
+                    .filter(|neighbour| *neighbour != terminal_id)
+                    .collect();
+                let Some(first) = neighbours.first().copied() else {
+                    panic!("The terminal {} of the component {} is not connected", terminal_id.idx, terminal_id.component_id.0);
+                };
+
+                circuit.terminal_edges.retain(|(left, right)| *left != terminal_id && *right != terminal_id);
+                circuit.terminal_edges.extend(neighbours.windows(2).map(|pair| (pair[0], pair[1])));
+                circuit.components.push(Components::Resistor(OPEN_RESISTANCE));
+                circuit.terminal_edges.push((terminal_id, TerminalID::new(resistor_id, 0)));
+                circuit.terminal_edges.push((TerminalID::new(resistor_id, 1), first));
+            }
+            Fault::Short(a, b) => {
+                circuit.components.push(Components::Resistor(SHORT_RESISTANCE));
+                circuit.terminal_edges.push((a, TerminalID::new(resistor_id, 0)));
+                circuit.terminal_edges.push((TerminalID::new(resistor_id, 1), b));
+            }
+        }
+    }
+}
+
+/**
+Settings of a fault-injection analysis, see `Simulator::fault_analysis`: the faults injected one
+at a time, and the outputs measured at the operating point of each faulty circuit. An output
+deviates when it moves from its nominal value by more than the tolerances.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultInjection {
+    pub faults: Vec<Fault>,
+    pub outputs: Vec<Expression>,
+    pub relative_tolerance: f64,
+    pub absolute_tolerance: f64,
+}
+
+impl FaultInjection {
+    /** Injects every fault of `Fault::all`, with the default tolerances (5% and 1 mV or 1 mA). */
+    pub fn new(circuit: &Circuit, outputs: Vec<Expression>) -> Self {
+        Self { faults: Fault::all(circuit), outputs, relative_tolerance: 0.05, absolute_tolerance: 1e-3 }
+    }
+
+    /** Returns whether a measured output deviates from its nominal value, a NaN always deviating. */
+    pub(crate) fn deviates(&self, nominal: f64, value: f64) -> bool {
+        let within = (value - nominal).abs() <= self.absolute_tolerance + self.relative_tolerance * nominal.abs();
+        !within
+    }
+}
+
+/** Outcome of a fault, see `FaultReport`. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultResult {
+    pub fault: Fault,
+    /** The outputs measured with the fault, None if the faulty circuit did not converge. */
+    pub values: Option<Vec<f64>>,
+    /** Indices of the outputs that deviate, all of them if the faulty circuit did not converge. */
+    pub deviations: Vec<usize>,
+}
+
+impl FaultResult {
+    /** Returns whether the fault is detected by at least one output. */
+    pub fn detected(&self) -> bool {
+        !self.deviations.is_empty()
+    }
+}
+
+/** Result of a fault-injection analysis, like an FMEA table. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultReport {
+    /** The outputs measured without any fault. */
+    pub nominal: Vec<f64>,
+    pub results: Vec<FaultResult>,
+}
+
+impl FaultReport {
+    /** Returns the faults no output detects. */
+    pub fn undetected(&self) -> Vec<Fault> {
+        self.results.iter().filter(|result| !result.detected()).map(|result| result.fault).collect()
+    }
+
+    /** Returns the fraction of the faults detected by the outputs, 1 if there are none. */
+    pub fn coverage(&self) -> f64 {
+        if self.results.is_empty() {
+            return 1.0;
+        }
+        self.results.iter().filter(|result| result.detected()).count() as f64 / self.results.len() as f64
+    }
+}
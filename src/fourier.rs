@@ -0,0 +1,122 @@
+use std::f64::consts::PI;
+
+/** Number of points the last period of a waveform is resampled to, at least. */
+const SAMPLES_PER_PERIOD: usize = 1024;
+
+/** Component of a waveform at a multiple of the fundamental frequency: amplitude * sin(2 pi f t + phase). */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Harmonic {
+    /** Frequency, in Hz. */
+    pub frequency: f64,
+    pub amplitude: f64,
+    /** Phase, in radians, the sine being the reference like for the sine generator. */
+    pub phase: f64,
+}
+
+/**
+Fourier analysis of the last period of a transient waveform, like the `.four` analysis of SPICE.
+The waveform should have reached its steady state.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fourier {
+    /** Mean value of the waveform over the period. */
+    pub dc: f64,
+    /** The fundamental followed by the harmonics, harmonics[k] being at (k + 1) times the fundamental frequency. */
+    pub harmonics: Vec<Harmonic>,
+}
+
+impl Fourier {
+    /**
+    Analyzes the last period of a waveform sampled at the given times (e.g. a waveform of a
+    `Transient`), up to the given harmonic count, the fundamental included. The waveform is
+    linearly interpolated between the time points.
+    */
+    pub fn analyze(times: &[f64], values: &[f64], fundamental: f64, harmonic_count: usize) -> Self {
+        if times.len() != values.len() || times.len() < 2 {
+            panic!("The waveform must have one value per time and at least two points");
+        }
+        let period = 1.0 / fundamental;
+        let end = times[times.len() - 1];
+        let start = end - period;
+        if start < times[0] {
+            panic!("The waveform must last at least one period of the fundamental");
+        }
+
+        let samples_count = SAMPLES_PER_PERIOD.max(4 * harmonic_count);
+        let samples: Vec<(f64, f64)> = (0..samples_count)
+            .map(|j| {
+                let time = start + period * j as f64 / samples_count as f64;
+                (time, interpolate(times, values, time))
+            })
+            .collect();
+
+        let dc = samples.iter().map(|(_, value)| value).sum::<f64>() / samples_count as f64;
+        let harmonics = (1..=harmonic_count)
+            .map(|k| {
+                let frequency = fundamental * k as f64;
+                // value = amplitude * (cos(phase) * sin(wt) + sin(phase) * cos(wt))
+                let (mut sin, mut cos) = (0.0, 0.0);
+                for (time, value) in &samples {
+                    let angle = 2.0 * PI * frequency * time;
+                    sin += value * angle.sin();
+                    cos += value * angle.cos();
+                }
+                let (sin, cos) = (2.0 * sin / samples_count as f64, 2.0 * cos / samples_count as f64);
+                Harmonic { frequency, amplitude: sin.hypot(cos), phase: cos.atan2(sin) }
+            })
+            .collect();
+
+        Self { dc, harmonics }
+    }
+
+    /**
+    Returns the total harmonic distortion: the RMS value of the harmonics above the fundamental
+    relative to the fundamental, 0.01 for 1%.
+    */
+    pub fn thd(&self) -> f64 {
+        let Some((fundamental, harmonics)) = self.harmonics.split_first() else {
+            return 0.0;
+        };
+        harmonics.iter().map(|harmonic| harmonic.amplitude * harmonic.amplitude).sum::<f64>().sqrt() / fundamental.amplitude
+    }
+}
+
+/** Linearly interpolates a waveform at a time within its time points, which are sorted. */
+pub(crate) fn interpolate(times: &[f64], values: &[f64], time: f64) -> f64 {
+    let k = times.partition_point(|t| *t <= time).clamp(1, times.len() - 1);
+    let (t0, t1) = (times[k - 1], times[k]);
+    if t1 == t0 {
+        return values[k];
+    }
+    values[k - 1] + (values[k] - values[k - 1]) * (time - t0) / (t1 - t0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thd_of_known_harmonics() {
+        // A 50 Hz fundamental with a third and a fifth harmonic, over two periods.
+        let times: Vec<f64> = (0..=40_000).map(|k| k as f64 * 1e-6).collect();
+        let values: Vec<f64> = times
+            .iter()
+            .map(|t| 1.0 + (2.0 * PI * 50.0 * t).sin() + 0.1 * (2.0 * PI * 150.0 * t).sin() + 0.05 * (2.0 * PI * 250.0 * t + 0.5).sin())
+            .collect();
+
+        let fourier = Fourier::analyze(&times, &values, 50.0, 5);
+        assert!((fourier.dc - 1.0).abs() < 1e-6);
+        assert!((fourier.harmonics[0].amplitude - 1.0).abs() < 1e-6);
+        assert!(fourier.harmonics[1].amplitude < 1e-6);
+        assert!((fourier.harmonics[2].amplitude - 0.1).abs() < 1e-6);
+        assert!((fourier.harmonics[4].phase - 0.5).abs() < 1e-4);
+        assert!((fourier.thd() - (0.1f64.powi(2) + 0.05f64.powi(2)).sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn thd_of_a_pure_sine_is_zero() {
+        let times: Vec<f64> = (0..=1000).map(|k| k as f64 * 1e-5).collect();
+        let values: Vec<f64> = times.iter().map(|t| 3.0 * (2.0 * PI * 100.0 * t).sin()).collect();
+        assert!(Fourier::analyze(&times, &values, 100.0, 9).thd() < 1e-4);
+    }
+}
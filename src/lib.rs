@@ -0,0 +1,19 @@
+pub mod bode;
+pub mod event;
+pub mod component;
+pub mod expression;
+pub mod fault;
+pub mod fourier;
+pub mod model;
+pub mod monte_carlo;
+pub mod optimization;
+pub mod plugin;
+pub mod recording;
+pub mod report;
+pub mod simulator;
+pub mod spectrum;
+pub mod symbolic;
+pub mod devices;
+pub mod digital;
+mod disjoint_set;
+mod expansion;
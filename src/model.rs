@@ -1,13 +1,724 @@
-#[derive(Debug)]
+use crate::component::Component;
+use crate::expression::Expression;
+
+#[derive(Debug, Default)]
 pub struct Circuit {
     pub components: Vec<Components>,
     pub terminal_edges: Vec<(TerminalID, TerminalID)>,
+    pub couplings: Vec<MutualCoupling>,
+    pub tolerances: Vec<Tolerance>,
+    pub temperature_coefficients: Vec<TemperatureCoefficient>,
+    pub initial_conditions: Vec<InitialCondition>,
+    pub thermal_models: Vec<ThermalModel>,
+    pub ratings: Vec<Rating>,
+}
+
+impl Circuit {
+    /** Creates a circuit of the components connected by the edges, the other fields being empty. */
+    pub fn new(components: Vec<Components>, terminal_edges: Vec<(TerminalID, TerminalID)>) -> Self {
+        Self { components, terminal_edges, ..Default::default() }
+    }
+}
+
+/** Temperature at which the nominal values of the components are given, in °C. */
+pub const NOMINAL_TEMPERATURE: f64 = 27.0;
+
+/**
+Magnetic coupling between two inductors, the dotted end of each inductor being its terminal 1.
+- k: coupling coefficient between 0 and 1, the mutual inductance is k * sqrt(L1 * L2).
+*/
+#[derive(Debug)]
+pub struct MutualCoupling {
+    pub inductors: (ComponentID, ComponentID),
+    pub k: f64,
+}
+
+/**
+Manufacturing tolerance of a component with a single value (resistance, capacitance, ...), for the
+analyses that vary component values, see `Simulator::monte_carlo`. The other analyses use the
+nominal value.
+- relative: maximum relative deviation from the nominal value, 0.05 for ±5%.
+*/
+#[derive(Debug)]
+pub struct Tolerance {
+    pub component: ComponentID,
+    pub relative: f64,
+}
+
+impl Tolerance {
+    /** Returns the lowest and highest values of a component with the given nominal value. */
+    pub fn range(&self, nominal: f64) -> (f64, f64) {
+        let deviation = (nominal * self.relative).abs();
+        (nominal - deviation, nominal + deviation)
+    }
+}
+
+/**
+Initial condition of a capacitor (its voltage V1 - V0) or of an inductor (the intensity entering
+its terminal 1), used instead of the DC operating point when a transient analysis uses the initial
+conditions.
+*/
+#[derive(Debug)]
+pub struct InitialCondition {
+    pub component: ComponentID,
+    pub value: f64,
+}
+
+/**
+Temperature coefficients of a resistor: R(T) = R * (1 + tc1 * dT + tc2 * dT^2), where R is the
+resistance at the nominal temperature and dT = T - NOMINAL_TEMPERATURE.
+- tc1: first order coefficient, in 1/K.
+- tc2: second order coefficient, in 1/K^2.
+*/
+#[derive(Debug)]
+pub struct TemperatureCoefficient {
+    pub component: ComponentID,
+    pub tc1: f64,
+    pub tc2: f64,
+}
+
+impl TemperatureCoefficient {
+    /** Returns the factor applied to the nominal value at the given temperature in °C. */
+    pub fn factor(&self, temperature: f64) -> f64 {
+        let delta = temperature - NOMINAL_TEMPERATURE;
+        1.0 + self.tc1 * delta + self.tc2 * delta * delta
+    }
+}
+
+/**
+Thermal model of a component for the electro-thermal transient analysis: the power it dissipates
+heats it through a thermal RC to the ambient (the temperature of the circuit), and its temperature
+is used for its temperature coefficients, so self-heating is simulated.
+- thermal_resistance: between the component and the ambient, in K/W.
+- thermal_capacitance: heat capacity of the component, in J/K.
+*/
+#[derive(Debug)]
+pub struct ThermalModel {
+    pub component: ComponentID,
+    pub thermal_resistance: f64,
+    pub thermal_capacitance: f64,
+}
+
+/**
+Maximum ratings of a component, checked after a solve by `Simulator::design_rule_check`. A rating
+that is None is not checked.
+- max_power: power absorbed, in watts.
+- max_voltage: between any two of its terminals, in volts.
+- max_current: entering any of its terminals, in amperes.
+*/
+#[derive(Debug)]
+pub struct Rating {
+    pub component: ComponentID,
+    pub max_power: Option<f64>,
+    pub max_voltage: Option<f64>,
+    pub max_current: Option<f64>,
 }
 
 #[derive(Debug)]
 pub enum Components {
     Resistor(f64),
     VoltageGenerator(f64),
+    /** Capacitance in farads. Open circuit at DC, companion model during a time step. */
+    Capacitor(f64),
+    /** Inductance in henries. Short circuit at DC, companion model during a time step. */
+    Inductor(f64),
+    /**
+    Shockley diode, terminal 0 is the anode and terminal 1 the cathode.
+    - is: saturation current in amperes.
+    - n: emission coefficient (ideality factor).
+    */
+    Diode { is: f64, n: f64 },
+    /**
+    Zener diode, terminal 0 is the anode and terminal 1 the cathode.
+    - is: saturation current in amperes.
+    - n: emission coefficient (ideality factor).
+    - vz: reverse breakdown voltage in volts.
+    */
+    Zener { is: f64, n: f64, vz: f64 },
+    /**
+    Light emitting diode, terminal 0 is the anode and terminal 1 the cathode.
+    - vf: forward voltage in volts.
+    - rs: series resistance in ohms, the slope of the characteristic above vf.
+    - max_current: maximum forward current in amperes, reported when exceeded.
+    */
+    Led { vf: f64, rs: f64, max_current: f64 },
+    /**
+    Bipolar junction transistor (Ebers–Moll), terminal 0 is the collector, 1 the base and 2 the
+    emitter.
+    - beta: forward current gain.
+    - is: saturation current in amperes.
+    */
+    Bjt { polarity: BjtPolarity, beta: f64, is: f64 },
+    /**
+    MOSFET (SPICE level 1) with its bulk tied to the source, terminal 0 is the drain, 1 the gate and
+    2 the source.
+    - vth: threshold voltage in volts, given as a magnitude for a P-channel MOSFET.
+    - kp: transconductance parameter (including W/L) in A/V².
+    - lambda: channel-length modulation in 1/V.
+    */
+    Mosfet { channel: Channel, vth: f64, kp: f64, lambda: f64 },
+    /**
+    Junction field-effect transistor, terminal 0 is the drain, 1 the gate and 2 the source.
+    - idss: drain current in amperes when vgs = 0.
+    - vp: pinch-off voltage in volts, negative for an N-channel JFET.
+    */
+    Jfet { channel: Channel, idss: f64, vp: f64 },
+    /** Triode vacuum tube (Koren model), terminal 0 is the plate, 1 the grid and 2 the cathode. */
+    Triode(TriodeModel),
+    /**
+    Op-amp macromodel, terminal 0 is the non-inverting input, 1 the inverting input and 2 the
+    output. The output voltage is relative to the ground.
+    - gain: open-loop DC gain.
+    - bandwidth: open-loop -3dB frequency in hertz (single pole).
+    - output_resistance: in ohms.
+    - slew_rate: maximum rate of change of the output in V/s.
+    - rails: minimum and maximum output voltages in volts.
+    */
+    OpAmp { gain: f64, bandwidth: f64, output_resistance: f64, slew_rate: f64, rails: (f64, f64) },
+    /**
+    Ideal transformer, terminals 0 and 1 are the primary winding and terminals 2 and 3 the secondary
+    winding, the dotted ends being 1 and 3.
+    - ratio: turns ratio N1 / N2, so that v_primary = ratio * v_secondary.
+    */
+    Transformer { ratio: f64 },
+    /**
+    Transformer whose core saturates, terminals are the same as an ideal transformer. It is an
+    ideal transformer with a nonlinear magnetizing inductance across the primary winding.
+    - ratio: turns ratio N1 / N2.
+    - magnetizing_inductance: inductance of the unsaturated core seen from the primary, in henries.
+    - saturation_flux: flux linkage of the primary winding when saturated, in webers.
+    */
+    SaturatingTransformer { ratio: f64, magnetizing_inductance: f64, saturation_flux: f64 },
+    /**
+    Voltage-controlled voltage source (E), terminals 0 and 1 are the output like a voltage generator
+    and terminals 2 and 3 sense the controlling voltage: V1 - V0 = gain * (V3 - V2).
+    */
+    Vcvs { gain: f64 },
+    /**
+    Voltage-controlled current source (G), terminals 2 and 3 sense the controlling voltage and an
+    intensity transconductance * (V3 - V2) enters terminal 1 and leaves by terminal 0, like the
+    intensity of a voltage generator.
+    */
+    Vccs { transconductance: f64 },
+    /**
+    Current-controlled voltage source (H), terminals 0 and 1 are the output like a voltage
+    generator: V1 - V0 = transresistance * I, where I is the branch intensity of the control
+    component (a voltage generator, an inductor, ...).
+    */
+    Ccvs { control: ComponentID, transresistance: f64 },
+    /**
+    Current-controlled current source (F), an intensity gain * I enters terminal 1 and leaves by
+    terminal 0, where I is the branch intensity of the control component.
+    */
+    Cccs { control: ComponentID, gain: f64 },
+    /**
+    Switch that toggles at scheduled times, seen as a resistor.
+    - on_resistance, off_resistance: resistance when closed and open, in ohms.
+    - closed: state at t = 0.
+    - toggle_times: times at which the switch changes state, in seconds.
+    */
+    Switch { on_resistance: f64, off_resistance: f64, closed: bool, toggle_times: Vec<f64> },
+    /**
+    Electromechanical relay, terminals 0 and 1 are the coil and terminals 2 and 3 the contacts. The
+    contacts start open, close when the coil intensity rises above pull_in_current and open again
+    when it falls below drop_out_current.
+    - coil_resistance, coil_inductance: the coil, in ohms and henries.
+    - pull_in_current, drop_out_current: thresholds on the coil intensity, in amperes.
+    - on_resistance, off_resistance: resistance of the contacts when closed and open, in ohms.
+    */
+    Relay {
+        coil_resistance: f64,
+        coil_inductance: f64,
+        pull_in_current: f64,
+        drop_out_current: f64,
+        on_resistance: f64,
+        off_resistance: f64,
+    },
+    /**
+    Potentiometer, terminals 0 and 1 are the ends of the track and terminal 2 the wiper.
+    - resistance: resistance of the whole track, in ohms.
+    - position: wiper position from 0 (on terminal 0) to 1 (on terminal 1).
+    */
+    Potentiometer { resistance: f64, position: f64 },
+    /**
+    Fuse, a small resistance that opens for good once the intensity exceeds its rating.
+    - resistance: resistance when intact, in ohms.
+    - rated_current: intensity above which the fuse melts, in amperes.
+    - melting_i2t: energy integral (A²s) needed to melt it, None to blow instantly.
+    */
+    Fuse { resistance: f64, rated_current: f64, melting_i2t: Option<f64> },
+    /**
+    Resistor whose I–V characteristic is interpolated from a table (varistor, ...), the intensity
+    going from terminal 0 to terminal 1 for v = V0 - V1.
+    - table: (voltage, intensity) points sorted by voltage, at least two of them.
+    */
+    TableResistor { table: Vec<(f64, f64)> },
+    /**
+    Memristor (HP linear ion drift model), whose resistance depends on the charge that went through
+    it from terminal 0 to terminal 1.
+    - r_on, r_off: resistances when fully doped (x = 1) and undoped (x = 0), in ohms.
+    - drift: speed of the state, mu_v * r_on / D², in 1/C.
+    - x: initial state between 0 and 1.
+    */
+    Memristor { r_on: f64, r_off: f64, drift: f64, x: f64 },
+    /**
+    Battery, terminal 1 being the positive one. It is an open-circuit voltage depending on the state
+    of charge, in series with an internal resistance. The state of charge is integrated over time.
+    - ocv: (state of charge, voltage) points sorted by state of charge, at least two of them.
+    - internal_resistance: in ohms.
+    - capacity: in ampere-hours.
+    - soc: initial state of charge between 0 and 1.
+    */
+    Battery { ocv: Vec<(f64, f64)>, internal_resistance: f64, capacity: f64, soc: f64 },
+    /**
+    Supercapacitor, made of two branches in parallel: the capacitance in series with its ESR, and
+    the leakage resistance.
+    - capacitance: in farads.
+    - esr: equivalent series resistance, in ohms.
+    - leakage_resistance: in ohms.
+    */
+    Supercapacitor { capacitance: f64, esr: f64, leakage_resistance: f64 },
+    /**
+    Capacitor with its parasitics, expanded into a capacitor, a resistor (ESR) and an inductor (ESL)
+    in series.
+    - capacitance: in farads.
+    - esr: equivalent series resistance in ohms, None to leave it out.
+    - esl: equivalent series inductance in henries, None to leave it out.
+    */
+    RealCapacitor { capacitance: f64, esr: Option<f64>, esl: Option<f64> },
+    /**
+    Inductor with its parasitics, expanded into an inductor in series with a resistor (DCR), the
+    winding capacitance being across both.
+    - inductance: in henries.
+    - dcr: DC resistance of the winding in ohms, None to leave it out.
+    - parallel_capacitance: in farads, sets the self-resonant frequency. None to leave it out.
+    */
+    RealInductor { inductance: f64, dcr: Option<f64>, parallel_capacitance: Option<f64> },
+    /**
+    Lossless transmission line, modeled by the method of characteristics: each port sees the
+    characteristic impedance in series with a source set by the other port one delay earlier.
+    - Terminals: port 1 on 0 and 1, port 2 on 2 and 3.
+    - impedance: characteristic impedance in ohms.
+    - delay: propagation delay in seconds. Setting a longer time step panics.
+
+    At DC, the line is a pair of wires.
+    */
+    TransmissionLine { impedance: f64, delay: f64 },
+    /**
+    Ideal gyrator, a pair of cross-coupled voltage-controlled current sources: an intensity
+    conductance * V2 enters port 1 and -conductance * V1 enters port 2, where V1 = V1 - V0 and
+    V2 = V3 - V2. A capacitor C on port 2 is seen as an inductor C / conductance^2 on port 1.
+    - Terminals: port 1 on 0 and 1, port 2 on 2 and 3.
+    - conductance: gyration conductance in siemens.
+    */
+    Gyrator { conductance: f64 },
+    /**
+    Optocoupler, an LED driving a phototransistor: the collector intensity is ctr times the LED
+    intensity while the phototransistor is not saturated.
+    - Terminals: anode 0, cathode 1, collector 2, emitter 3.
+    - vf: forward voltage of the LED in volts.
+    - rs: series resistance of the LED in ohms.
+    - ctr: current transfer ratio, the collector intensity over the LED intensity.
+    */
+    Optocoupler { vf: f64, rs: f64, ctr: f64 },
+    /**
+    Bidirectional transient voltage suppressor, symmetric so the terminals are interchangeable.
+    - breakdown_voltage: voltage above which it conducts, in either direction, in volts.
+    - clamping_resistance: slope of the characteristic above the breakdown voltage, in ohms.
+    */
+    Tvs { breakdown_voltage: f64, clamping_resistance: f64 },
+    /**
+    Idealized diode, terminal 0 is the anode and terminal 1 the cathode: a fixed drop when it
+    conducts and an open circuit when it blocks. It is solved by selecting its region rather than
+    following an exponential, which is faster and always converges on simple circuits.
+    - forward_voltage: drop when conducting in volts, 0 for a perfect diode.
+    */
+    IdealDiode { forward_voltage: f64 },
+    /**
+    Incandescent lamp, whose filament resistance rises with its temperature, itself driven by the
+    dissipated power. The filament is cold at the start, so it draws an inrush current.
+    - cold_resistance: resistance at the ambient temperature in ohms.
+    - temperature_coefficient: relative increase of the resistance per kelvin.
+    - thermal_resistance: temperature rise per dissipated watt at equilibrium, in K/W.
+    - thermal_capacitance: heat needed to raise the filament by one kelvin, in J/K.
+    */
+    Lamp { cold_resistance: f64, temperature_coefficient: f64, thermal_resistance: f64, thermal_capacitance: f64 },
+    /**
+    DC motor, the armature is an inductor in series with a resistor and the back-EMF:
+    V1 - V0 = R * I + L * dI/dt + ke * w. The intensity entering terminal 1 drives the rotor forward.
+    - armature_resistance: in ohms.
+    - armature_inductance: in henries.
+    - back_emf_constant: ke in V.s/rad, which is also the torque constant in N.m/A.
+    - inertia: of the rotor and its load, in kg.m^2.
+    - load_torque: torque opposing the rotation, in N.m.
+
+    The motor starts at rest and its speed is integrated during a time step.
+    */
+    DcMotor { armature_resistance: f64, armature_inductance: f64, back_emf_constant: f64, inertia: f64, load_torque: f64 },
+    /** Intensity in amperes, which enters terminal 0 and leaves by terminal 1 into the circuit. */
+    CurrentGenerator(f64),
+    /**
+    Norton equivalent source, expanded into a current generator (oriented like `CurrentGenerator`)
+    with a resistor in parallel.
+    - current: short-circuit intensity in amperes.
+    - parallel_resistance: in ohms.
+    */
+    NortonSource { current: f64, parallel_resistance: f64 },
+    /**
+    Solar cell (single diode model), expanded into a photocurrent source in parallel with a diode and
+    a shunt resistor, behind a series resistor. Terminal 0 is the negative side and terminal 1 the
+    positive one. A photodiode is a solar cell used in reverse.
+    - photocurrent: short-circuit intensity under one sun (1000 W/m²), in amperes.
+    - irradiance: in suns, the photocurrent is proportional to it.
+    - is, n: saturation current and emission coefficient of the diode.
+    - series_resistance, shunt_resistance: in ohms.
+    */
+    SolarCell { photocurrent: f64, irradiance: f64, is: f64, n: f64, series_resistance: f64, shunt_resistance: f64 },
+    /**
+    Sinusoidal voltage generator:
+    V1 - V0 = dc_offset + amplitude * sin(2 * pi * frequency * t + phase).
+    - amplitude: peak voltage in volts.
+    - frequency: in hertz.
+    - phase: in radians.
+    - dc_offset: in volts.
+    */
+    SineGenerator { amplitude: f64, frequency: f64, phase: f64, dc_offset: f64 },
+    /**
+    Pulse voltage generator (SPICE PULSE): V1 - V0 is v1 until the delay, then rises to v2 in rise,
+    stays there for width and falls back to v1 in fall. The pulse repeats every period, or only
+    once if the period is 0. Times are in seconds.
+    */
+    PulseGenerator { v1: f64, v2: f64, delay: f64, rise: f64, fall: f64, width: f64, period: f64 },
+    /**
+    Piecewise-linear voltage generator (SPICE PWL): V1 - V0 is interpolated between (time, voltage)
+    points sorted by time, and holds the first and last voltages outside of them. The points can
+    be loaded from a recorded CSV or WAV file, see `recording`.
+    */
+    PwlVoltage { points: Vec<(f64, f64)> },
+    /**
+    Exponential voltage generator (SPICE EXP): V1 - V0 is v1 until rise_delay, then approaches v2
+    with the time constant rise_tau, and from fall_delay returns towards v1 with the time constant
+    fall_tau. Times are in seconds.
+    */
+    ExpGenerator { v1: f64, v2: f64, rise_delay: f64, rise_tau: f64, fall_delay: f64, fall_tau: f64 },
+    /**
+    Single-frequency FM voltage generator (SPICE SFFM):
+    V1 - V0 = offset + amplitude * sin(2 * pi * carrier_frequency * t + modulation_index * sin(2 * pi * signal_frequency * t)).
+    - offset, amplitude: in volts.
+    - carrier_frequency, signal_frequency: in hertz.
+    */
+    FmGenerator { offset: f64, amplitude: f64, carrier_frequency: f64, modulation_index: f64, signal_frequency: f64 },
+    /**
+    Pseudo-random binary sequence voltage generator: V1 - V0 is low or high during each bit period,
+    the bits being reproducible for a given seed.
+    - low, high: in volts.
+    - bit_period: in seconds.
+    */
+    PrbsGenerator { low: f64, high: f64, bit_period: f64, seed: u64 },
+    /**
+    Piecewise-linear current generator, like `PwlVoltage` for an intensity that enters terminal 0
+    and leaves by terminal 1, like a current generator.
+    */
+    PwlCurrent { points: Vec<(f64, f64)> },
+    /**
+    Balanced three-phase source, expanded into three sinusoidal generators 120° apart (A, B, C
+    sequence) sharing the neutral.
+    - Terminals: phases A, B and C on 0, 1 and 2, neutral on 3.
+    - amplitude: peak phase to neutral voltage in volts.
+    - frequency: in hertz.
+    */
+    ThreePhaseSource { amplitude: f64, frequency: f64 },
+    /**
+    Behavioral low-dropout linear regulator. The output intensity is drawn from the input, the
+    ground terminal only serves as the reference.
+    - Terminals: input 0, output 1, ground 2.
+    - nominal_voltage: regulated output voltage in volts.
+    - dropout_voltage: minimum difference between the input and the output in volts.
+    - output_impedance: drop of the output per ampere, in ohms.
+    - current_limit: maximum output intensity in amperes.
+    */
+    Regulator { nominal_voltage: f64, dropout_voltage: f64, output_impedance: f64, current_limit: f64 },
+    /**
+    Comparator, the output is connected to the supply through output_resistance when V0 > V1 and
+    to the ground otherwise. The inputs do not draw any intensity.
+    - Terminals: non-inverting input 0, inverting input 1, output 2, ground 3, supply 4.
+    */
+    Comparator { output_resistance: f64 },
+    /**
+    Voltage-controlled switch, terminals 0 and 1 are the contacts and terminals 2 and 3 sense the
+    control voltage: it is closed when V3 - V2 > threshold.
+    - on_resistance, off_resistance: resistance of the closed and open switch in ohms.
+    - threshold: control voltage in volts.
+    */
+    ControlledSwitch { on_resistance: f64, off_resistance: f64, threshold: f64 },
+    /**
+    SR latch, the reset input having the priority. The inputs are high above half of the supply
+    and the state only changes when a solution is committed. Each output is connected to the
+    supply (high) or the ground (low) through output_resistance. The latch starts reset.
+    - Terminals: set 0, reset 1, Q 2, not Q 3, ground 4, supply 5.
+    */
+    SrLatch { output_resistance: f64 },
+    /**
+    Logic gate, the inputs are high above half of the supply and do not draw any intensity. The
+    output is connected to the supply (high) or the ground (low) through output_resistance.
+    - Terminals: the inputs from 0 (a single one for NOT), then the output, ground and supply.
+    */
+    LogicGate { function: LogicFunction, output_resistance: f64 },
+    /**
+    D flip-flop, Q takes the value of D on the rising edges of the clock, which are detected when a
+    solution is committed. The inputs and outputs are like the ones of the SR latch, and it also
+    starts reset.
+    - Terminals: D 0, clock 1, Q 2, not Q 3, ground 4, supply 5.
+    */
+    DFlipFlop { output_resistance: f64 },
+    /**
+    D/A boundary of the mixed-signal transient analysis, see `Simulator::transient_mixed`: drives
+    V1 - V0 to high or low, according to a signal of the digital engine, through its output
+    resistance in ohms. The signal is low in the other analyses.
+    */
+    DigitalOutput { signal: usize, low: f64, high: f64, output_resistance: f64 },
+    /**
+    A/D boundary of the mixed-signal transient analysis: sets a signal of the digital engine to
+    whether V1 - V0 is above the threshold in volts, without drawing any current.
+    */
+    DigitalInput { signal: usize, threshold: f64 },
+    /**
+    555 timer, expanded into its resistor divider, threshold and trigger comparators, SR latch and
+    discharge switch. Every pin must be connected, the reset pin to the supply when unused.
+    - Terminals: the pin numbers minus one, that is ground 0, trigger 1, output 2, reset 3,
+      control 4, threshold 5, discharge 6 and supply 7.
+    */
+    Timer555,
+    /**
+    Averaged buck converter: the switching cell is replaced by its average over a period, an ideal
+    DC transformer that gives duty times the input voltage, followed by the LC output filter.
+    - Terminals: input 0, output 1, ground 2.
+    - duty: fraction of the period the switch is closed, between 0 and 1 excluded.
+    - inductance, capacitance: of the output filter.
+    */
+    BuckConverter { duty: f64, inductance: f64, capacitance: f64 },
+    /**
+    Averaged boost converter: the input inductor is followed by the average of the switching cell,
+    an ideal DC transformer that gives the output voltage times (1 - duty), and the output capacitor.
+    - Terminals: input 0, output 1, ground 2.
+    - duty: fraction of the period the switch is closed, between 0 and 1 excluded.
+    - inductance, capacitance: of the input inductor and output capacitor.
+    */
+    BoostConverter { duty: f64, inductance: f64, capacitance: f64 },
+    /**
+    Loudspeaker seen from its terminals, from its Thiele-Small parameters: the voice coil resistance
+    and inductance in series with the motional impedance of the cone, a parallel RLC network that
+    resonates at fs.
+    - re, le: resistance and inductance of the voice coil, in ohms and henries.
+    - fs: resonance frequency in hertz.
+    - qms, qes: mechanical and electrical quality factors at resonance.
+    */
+    Loudspeaker { re: f64, le: f64, fs: f64, qms: f64, qes: f64 },
+    /**
+    Spark gap or neon lamp, symmetric so the terminals are interchangeable. It is open until the
+    voltage exceeds the strike voltage, then it conducts with the holding voltage across it until
+    the intensity falls under the extinction current. The state only changes when a solution is
+    committed.
+    - strike_voltage, holding_voltage: in volts.
+    - arc_resistance: slope of the characteristic while struck, in ohms.
+    - extinction_current: in amperes.
+    */
+    SparkGap { strike_voltage: f64, holding_voltage: f64, arc_resistance: f64, extinction_current: f64 },
+    /**
+    Current probe, a 0V voltage generator whose intensity can be read by its label. Like the
+    intensity of a voltage generator, it is positive when it enters terminal 1 and leaves by
+    terminal 0.
+    */
+    CurrentProbe { label: String },
+    /**
+    Voltmeter, reads V1 - V0 and loads the circuit with its input resistance in ohms.
+    */
+    Voltmeter { label: String, input_resistance: f64 },
+    /**
+    Ammeter, reads the intensity entering terminal 1 and leaving by terminal 0, with the drop of its
+    burden resistance in ohms.
+    */
+    Ammeter { label: String, burden_resistance: f64 },
+    /**
+    Wire (jumper) between its two terminals, which are merged into a single node instead of being
+    stamped as a 0Ω resistor.
+    */
+    Wire,
+    /**
+    Ground, its single terminal 0 is the reference node (V = 0). Several grounds are the same node.
+    Without any ground, an arbitrary node is the reference.
+    */
+    Ground,
+    /**
+    Behavioral voltage source (B): V1 - V0 is given by an expression over the unknowns and the
+    time, see `Expression`. It is linearized around the guess when the expression is nonlinear.
+    */
+    BehavioralVoltage(Expression),
+    /**
+    Behavioral current source (B): an intensity given by an expression enters terminal 1 and
+    leaves by terminal 0, like the intensity of a voltage generator.
+    */
+    BehavioralCurrent(Expression),
+    /**
+    Linear block defined by its transfer function H(s) = numerator(s) / denominator(s), the
+    coefficients being ordered by increasing power of s. Like a VCVS, terminals 0 and 1 are the
+    output and terminals 2 and 3 sense the input: V1 - V0 = H(s) * (V3 - V2).
+
+    The transfer function must be proper. At DC the output is H(0) times the input, or holds its
+    state when H(s) has a pole at 0 (an integrator).
+    */
+    TransferFunction { numerator: Vec<f64>, denominator: Vec<f64> },
+    /**
+    Noise voltage source, V1 - V0 is a new random sample at each committed time step and 0 at DC.
+    The samples are reproducible for a given seed.
+    */
+    NoiseVoltage { distribution: NoiseDistribution, seed: u64 },
+    /**
+    Noise current source, like `NoiseVoltage` but the intensity enters terminal 0 and leaves by
+    terminal 1, like a current generator.
+    */
+    NoiseCurrent { distribution: NoiseDistribution, seed: u64 },
+    /**
+    Two-terminal component with an explicit orientation, see `Polarity`. It is expanded into the
+    inner component, whose terminals are swapped when reversed.
+    */
+    Oriented { component: Box<Components>, polarity: Polarity },
+    /** Component implemented outside of the simulator, see `Component`. */
+    Custom(Box<dyn Component>),
+}
+
+impl Components {
+    /**
+    Returns the value of a component described by a single value (resistance, capacitance,
+    inductance, voltage or intensity of a generator, gain of a controlled source), None for the
+    other components.
+    */
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            Components::Resistor(value) | Components::Capacitor(value) | Components::Inductor(value)
+            | Components::VoltageGenerator(value) | Components::CurrentGenerator(value)
+            | Components::Vcvs { gain: value } | Components::Vccs { transconductance: value }
+            | Components::Ccvs { transresistance: value, .. } | Components::Cccs { gain: value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+
+    /** Mutable access to the value of a component, see `value`. */
+    pub fn value_mut(&mut self) -> Option<&mut f64> {
+        match self {
+            Components::Resistor(value) | Components::Capacitor(value) | Components::Inductor(value)
+            | Components::VoltageGenerator(value) | Components::CurrentGenerator(value)
+            | Components::Vcvs { gain: value } | Components::Vccs { transconductance: value }
+            | Components::Ccvs { transresistance: value, .. } | Components::Cccs { gain: value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /** Returns the number of terminals of the component, numbered from 0. */
+    pub fn terminal_count(&self) -> usize {
+        match self {
+            Components::Ground => 1,
+            Components::Resistor(_) | Components::VoltageGenerator(_) | Components::Capacitor(_) | Components::Inductor(_)
+            | Components::Diode { .. } | Components::Zener { .. } | Components::Led { .. } | Components::Ccvs { .. }
+            | Components::Cccs { .. } | Components::Switch { .. } | Components::Fuse { .. } | Components::TableResistor { .. }
+            | Components::DigitalOutput { .. } | Components::DigitalInput { .. }
+            | Components::Memristor { .. } | Components::Battery { .. } | Components::Supercapacitor { .. }
+            | Components::RealCapacitor { .. } | Components::RealInductor { .. } | Components::Tvs { .. }
+            | Components::IdealDiode { .. } | Components::Lamp { .. } | Components::DcMotor { .. }
+            | Components::CurrentGenerator(_) | Components::NortonSource { .. } | Components::SolarCell { .. } | Components::SineGenerator { .. }
+            | Components::PulseGenerator { .. } | Components::PwlVoltage { .. } | Components::PwlCurrent { .. }
+            | Components::ExpGenerator { .. } | Components::FmGenerator { .. } | Components::PrbsGenerator { .. }
+            | Components::SparkGap { .. } | Components::CurrentProbe { .. } | Components::Voltmeter { .. }
+            | Components::Ammeter { .. } | Components::Wire | Components::BehavioralVoltage(_)
+            | Components::BehavioralCurrent(_) | Components::Loudspeaker { .. } | Components::Oriented { .. } | Components::NoiseVoltage { .. } | Components::NoiseCurrent { .. } => 2,
+            Components::Bjt { .. } | Components::Mosfet { .. } | Components::Jfet { .. } | Components::Triode(_) | Components::OpAmp { .. }
+            | Components::Potentiometer { .. } | Components::Regulator { .. } | Components::BuckConverter { .. }
+            | Components::BoostConverter { .. } => 3,
+            Components::Transformer { .. } | Components::SaturatingTransformer { .. } | Components::Vcvs { .. }
+            | Components::Vccs { .. } | Components::Relay { .. } | Components::TransmissionLine { .. }
+            | Components::Gyrator { .. } | Components::Optocoupler { .. } | Components::ThreePhaseSource { .. }
+            | Components::ControlledSwitch { .. } | Components::TransferFunction { .. } => 4,
+            Components::Comparator { .. } => 5,
+            Components::SrLatch { .. } | Components::DFlipFlop { .. } => 6,
+            Components::LogicGate { function, .. } => function.input_count() + 3,
+            Components::Timer555 => 8,
+            Components::Custom(component) => component.terminals(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BjtPolarity {
+    Npn,
+    Pnp,
+}
+
+/**
+Orientation of a two-terminal component. The sign conventions of the components are given from
+their terminal 0 to their terminal 1: a voltage generator sets V1 - V0 and a current generator
+drives its intensity out of terminal 1. Reversing a component swaps its terminals 0 and 1, which
+flips a generator without renumbering its edges.
+*/
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Polarity {
+    Normal,
+    Reversed,
+}
+
+/** Distribution of the samples of a noise source, both have a zero mean. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NoiseDistribution {
+    Gaussian { standard_deviation: f64 },
+    /** Uniform between -amplitude and amplitude. */
+    Uniform { amplitude: f64 },
+}
+
+/** Boolean function of a logic gate. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LogicFunction {
+    Not,
+    And,
+    Or,
+    Xor,
+}
+
+impl LogicFunction {
+    /** Returns the number of inputs of a gate with this function. */
+    pub fn input_count(&self) -> usize {
+        match self {
+            LogicFunction::Not => 1,
+            LogicFunction::And | LogicFunction::Or | LogicFunction::Xor => 2,
+        }
+    }
+}
+
+/**
+Plate characteristics of a triode for the Koren model.
+- mu: amplification factor.
+- ex: exponent of the plate current.
+- kg1: inverse of the perveance.
+- kp, kvb: shape of the characteristics at low plate voltages.
+*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TriodeModel {
+    pub mu: f64,
+    pub ex: f64,
+    pub kg1: f64,
+    pub kp: f64,
+    pub kvb: f64,
+}
+
+impl TriodeModel {
+    pub const TWELVE_AX7: TriodeModel = TriodeModel { mu: 100.0, ex: 1.4, kg1: 1060.0, kp: 600.0, kvb: 300.0 };
+    pub const TWELVE_AT7: TriodeModel = TriodeModel { mu: 60.0, ex: 1.35, kg1: 460.0, kp: 300.0, kvb: 300.0 };
+    pub const TWELVE_AU7: TriodeModel = TriodeModel { mu: 21.5, ex: 1.3, kg1: 1180.0, kp: 84.0, kvb: 300.0 };
+}
+
+/** Doping of the channel of a field-effect transistor. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Channel {
+    N,
+    P,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
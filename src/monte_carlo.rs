@@ -0,0 +1,105 @@
+use crate::devices::noise;
+use crate::model::NoiseDistribution;
+
+/** Distribution of the value of a component within its tolerance, see `Tolerance`. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ToleranceDistribution {
+    /** Uniform over the tolerance range. */
+    Uniform,
+    /** Gaussian around the nominal value, the tolerance being sigmas standard deviations (3 is usual). */
+    Gaussian { sigmas: f64 },
+}
+
+/**
+Settings of a Monte Carlo analysis: the values of the components with a tolerance are drawn for
+each trial. The draws only depend on the seed, so an analysis can be reproduced exactly.
+*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MonteCarlo {
+    pub trials: usize,
+    pub seed: u64,
+    pub distribution: ToleranceDistribution,
+}
+
+impl MonteCarlo {
+    /**
+    Returns the relative deviation of the k-th of count components with a tolerance, for a trial.
+    */
+    pub(crate) fn deviation(&self, trial: usize, k: usize, count: usize, relative: f64) -> f64 {
+        let distribution = match self.distribution {
+            ToleranceDistribution::Uniform => NoiseDistribution::Uniform { amplitude: relative },
+            ToleranceDistribution::Gaussian { sigmas } => NoiseDistribution::Gaussian { standard_deviation: relative / sigmas },
+        };
+        noise::sample(distribution, self.seed, (trial * count + k) as u64)
+    }
+}
+
+/** Result of a Monte Carlo analysis, the result of the analysis for each trial. */
+#[derive(Debug, Clone)]
+pub struct MonteCarloResult<T> {
+    pub trials: Vec<T>,
+}
+
+impl<T> MonteCarloResult<T> {
+    /** Returns the statistics of a measure of the trials (a voltage, a gain, a bandwidth, ...). */
+    pub fn statistics(&self, measure: impl Fn(&T) -> f64) -> Statistics {
+        let values: Vec<f64> = self.trials.iter().map(measure).collect();
+        Statistics::new(&values)
+    }
+}
+
+/** Aggregate statistics of the values of a measure over the trials. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Statistics {
+    pub mean: f64,
+    /** Sample standard deviation, 0 with a single value. */
+    pub standard_deviation: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Statistics {
+    pub fn new(values: &[f64]) -> Self {
+        if values.is_empty() {
+            panic!("Statistics need at least one value");
+        }
+
+        let count = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / count;
+        let variance = if values.len() > 1 {
+            values.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>() / (count - 1.0)
+        } else {
+            0.0
+        };
+
+        Self {
+            mean,
+            standard_deviation: variance.sqrt(),
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_of_values() {
+        let statistics = Statistics::new(&[1.0, 2.0, 3.0, 6.0]);
+        assert_eq!(statistics.mean, 3.0);
+        assert!((statistics.standard_deviation - (14.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!((statistics.min, statistics.max), (1.0, 6.0));
+        assert_eq!(Statistics::new(&[4.0]).standard_deviation, 0.0);
+    }
+
+    #[test]
+    fn gaussian_deviations_follow_the_tolerance() {
+        let settings = MonteCarlo { trials: 10_000, seed: 1, distribution: ToleranceDistribution::Gaussian { sigmas: 3.0 } };
+        let deviations: Vec<f64> = (0..settings.trials).map(|trial| settings.deviation(trial, 0, 1, 0.03)).collect();
+        let statistics = Statistics::new(&deviations);
+        assert!(statistics.mean.abs() < 1e-3);
+        assert!((statistics.standard_deviation - 0.01).abs() < 5e-4);
+    }
+}
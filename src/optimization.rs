@@ -0,0 +1,172 @@
+use crate::model::ComponentID;
+
+/**
+Component value adjusted by an optimization, within bounds. A value with positive bounds is
+searched on a logarithmic scale, as component values usually span decades.
+*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Parameter {
+    pub component: ComponentID,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Parameter {
+    /** Returns the value at the position u between 0 (min) and 1 (max). */
+    pub(crate) fn value(&self, u: f64) -> f64 {
+        if self.min > 0.0 {
+            self.min * (self.max / self.min).powf(u)
+        } else {
+            self.min + (self.max - self.min) * u
+        }
+    }
+
+    /** Returns the position of a value between 0 (min) and 1 (max), clamped to the bounds. */
+    pub(crate) fn position(&self, value: f64) -> f64 {
+        let u = if self.min > 0.0 {
+            (value.max(self.min) / self.min).ln() / (self.max / self.min).ln()
+        } else {
+            (value - self.min) / (self.max - self.min)
+        };
+        u.clamp(0.0, 1.0)
+    }
+}
+
+/**
+Settings of an optimization of component values, see `Simulator::optimize`. The Nelder–Mead
+simplex search stops after max_iterations, or once the simplex is smaller than the tolerance,
+measured as a fraction of the bounds of each parameter.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Optimization {
+    pub parameters: Vec<Parameter>,
+    pub max_iterations: usize,
+    pub tolerance: f64,
+}
+
+impl Optimization {
+    /** Optimizes the given parameters, with the default iterations and tolerance (500 and 1e-6). */
+    pub fn new(parameters: Vec<Parameter>) -> Self {
+        Self { parameters, max_iterations: 500, tolerance: 1e-6 }
+    }
+}
+
+/** Result of an optimization: the best values found, in the order of the parameters, and their error. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizationResult {
+    pub values: Vec<f64>,
+    pub error: f64,
+    pub iterations: usize,
+    /** Whether the simplex shrank below the tolerance before the last iteration. */
+    pub converged: bool,
+}
+
+/**
+Returns the root mean square of the differences between values and their targets, e.g. the gains
+in dB of a Bode plot and the desired ones, to use as the error of an optimization.
+*/
+pub fn rms_error(values: &[f64], targets: &[f64]) -> f64 {
+    if values.len() != targets.len() || values.is_empty() {
+        panic!("There must be as many values as targets, and at least one");
+    }
+    let squares: f64 = values.iter().zip(targets).map(|(value, target)| (value - target) * (value - target)).sum();
+    (squares / values.len() as f64).sqrt()
+}
+
+/**
+Minimizes error over the unit hypercube with the Nelder–Mead simplex method, starting from a
+simplex around start. Returns the best point, its error, the iterations run and whether the
+simplex converged.
+*/
+pub(crate) fn nelder_mead(optimization: &Optimization, start: Vec<f64>, mut error: impl FnMut(&[f64]) -> f64) -> (Vec<f64>, f64, usize, bool) {
+    const INITIAL_STEP: f64 = 0.1;
+    let clamp = |point: Vec<f64>| point.into_iter().map(|u| u.clamp(0.0, 1.0)).collect::<Vec<f64>>();
+    // The point from a towards b at the given factor of their distance.
+    let along = |a: &[f64], b: &[f64], factor: f64| a.iter().zip(b).map(|(a, b)| a + factor * (b - a)).collect::<Vec<f64>>();
+
+    let mut simplex: Vec<(Vec<f64>, f64)> = vec![(start.clone(), error(&start))];
+    for k in 0..start.len() {
+        let mut vertex = start.clone();
+        vertex[k] += if vertex[k] + INITIAL_STEP <= 1.0 { INITIAL_STEP } else { -INITIAL_STEP };
+        let value = error(&vertex);
+        simplex.push((vertex, value));
+    }
+
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < optimization.max_iterations {
+        simplex.sort_by(|left, right| left.1.total_cmp(&right.1));
+        let size = simplex[1..]
+            .iter()
+            .flat_map(|(vertex, _)| vertex.iter().zip(&simplex[0].0).map(|(u, best)| (u - best).abs()))
+            .fold(0.0, f64::max);
+        if size <= optimization.tolerance {
+            converged = true;
+            break;
+        }
+        iterations += 1;
+
+        let (worst, worst_error) = simplex.last().unwrap().clone();
+        let others = &simplex[..simplex.len() - 1];
+        let centroid: Vec<f64> = (0..start.len())
+            .map(|k| others.iter().map(|(vertex, _)| vertex[k]).sum::<f64>() / others.len() as f64)
+            .collect();
+
+        let reflected = clamp(along(&centroid, &worst, -1.0));
+        let reflected_error = error(&reflected);
+        let replacement = if reflected_error < simplex[0].1 {
+            let expanded = clamp(along(&centroid, &worst, -2.0));
+            let expanded_error = error(&expanded);
+            if expanded_error < reflected_error { Some((expanded, expanded_error)) } else { Some((reflected, reflected_error)) }
+        } else if reflected_error < others.last().unwrap().1 {
+            Some((reflected, reflected_error))
+        } else {
+            // Contract towards the better of the worst and the reflected points.
+            let (outside, outside_error) = if reflected_error < worst_error { (reflected, reflected_error) } else { (worst, worst_error) };
+            let contracted = along(&centroid, &outside, 0.5);
+            let contracted_error = error(&contracted);
+            (contracted_error < outside_error).then_some((contracted, contracted_error))
+        };
+
+        match replacement {
+            Some(vertex) => *simplex.last_mut().unwrap() = vertex,
+            None => {
+                // Shrink every vertex towards the best one.
+                let best = simplex[0].0.clone();
+                for (vertex, value) in &mut simplex[1..] {
+                    *vertex = along(&best, vertex, 0.5);
+                    *value = error(vertex);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|left, right| left.1.total_cmp(&right.1));
+    let (best, best_error) = simplex.swap_remove(0);
+    (best, best_error, iterations, converged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_are_logarithmic_for_positive_bounds() {
+        let parameter = Parameter { component: ComponentID(0), min: 10.0, max: 1e3 };
+        assert!((parameter.value(0.5) - 100.0).abs() < 1e-9);
+        assert!((parameter.position(100.0) - 0.5).abs() < 1e-12);
+        assert_eq!(parameter.position(1.0), 0.0);
+
+        let linear = Parameter { component: ComponentID(0), min: -1.0, max: 1.0 };
+        assert_eq!(linear.value(0.75), 0.5);
+    }
+
+    #[test]
+    fn nelder_mead_finds_the_minimum_of_a_quadratic() {
+        let optimization = Optimization::new(vec![Parameter { component: ComponentID(0), min: 0.0, max: 1.0 }; 2]);
+        let (best, error, _, converged) = nelder_mead(&optimization, vec![0.5, 0.5], |u| (u[0] - 0.2).powi(2) + (u[1] - 0.9).powi(2));
+        assert!(converged);
+        assert!((best[0] - 0.2).abs() < 1e-5 && (best[1] - 0.9).abs() < 1e-5);
+        assert!(error < 1e-9);
+    }
+}
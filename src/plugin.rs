@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use crate::component::Component;
+use crate::model::Components;
+
+/** Builds a component of a model from its parameters. */
+pub type ComponentFactory = fn(&[f64]) -> Box<dyn Component>;
+
+/**
+Name of the function a plugin library exports to register its models, with the signature of
+`PluginRegistration`.
+*/
+pub const REGISTRATION_SYMBOL: &str = "elec_register_plugin";
+
+/**
+Name of the function a plugin library exports to tell the version of this crate it is built
+against, as an `extern "C" fn() -> *const c_char` returning a `PLUGIN_VERSION`.
+*/
+pub const VERSION_SYMBOL: &str = "elec_plugin_version";
+
+/** Version of this crate, NUL-terminated, that a plugin library must be built against. */
+pub const PLUGIN_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/**
+Registration function of a plugin library. It is called with the Rust ABI, so the library must be
+built by the same compiler against the same version of this crate.
+*/
+pub type PluginRegistration = fn(&mut PluginRegistry);
+
+/**
+Exports the registration function of a plugin library, along with the version of this crate it is
+built against, under the names `load` looks for. The function must be a `PluginRegistration`.
+*/
+#[macro_export]
+macro_rules! export_plugin {
+    ($registration:path) => {
+        #[no_mangle]
+        pub extern "C" fn elec_plugin_version() -> *const ::std::ffi::c_char {
+            $crate::plugin::PLUGIN_VERSION.as_ptr().cast()
+        }
+
+        #[no_mangle]
+        pub fn elec_register_plugin(registry: &mut $crate::plugin::PluginRegistry) {
+            let registration: $crate::plugin::PluginRegistration = $registration;
+            registration(registry)
+        }
+    };
+}
+
+/** Component models by name, registered by the application or loaded from plugin libraries. */
+#[derive(Default)]
+pub struct PluginRegistry {
+    factories: HashMap<String, ComponentFactory>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /** Registers a model, replacing any model with the same name. */
+    pub fn register(&mut self, name: &str, factory: ComponentFactory) {
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    /** Builds a component of the given model, None if no such model is registered. */
+    pub fn create(&self, name: &str, parameters: &[f64]) -> Option<Components> {
+        self.factories.get(name).map(|factory| Components::Custom(factory(parameters)))
+    }
+
+    /** Returns the names of the registered models. */
+    pub fn models(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+
+    /**
+    Loads a plugin library (.so, .dylib) and lets it register its models. The library is never
+    unloaded, the components it builds point to its code. A library that does not export its
+    version, or that is built against another version of this crate, is rejected before its
+    registration function is called.
+
+    # Safety
+    The library runs arbitrary code, and must be built with `export_plugin` by the same compiler,
+    which the version does not tell.
+    */
+    #[cfg(unix)]
+    pub unsafe fn load(&mut self, path: &std::path::Path) -> Result<(), String> {
+        use std::ffi::{c_char, CStr, CString};
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = CString::new(path.as_os_str().as_bytes()).map_err(|err| err.to_string())?;
+        let version_symbol = CString::new(VERSION_SYMBOL).unwrap();
+        let symbol = CString::new(REGISTRATION_SYMBOL).unwrap();
+
+        let handle = dl::dlopen(path.as_ptr(), dl::RTLD_NOW);
+        if handle.is_null() {
+            return Err(dl::last_error());
+        }
+
+        let version = dl::dlsym(handle, version_symbol.as_ptr());
+        if version.is_null() {
+            return Err(format!("The library is not a plugin, it does not export {}", VERSION_SYMBOL));
+        }
+        let version: extern "C" fn() -> *const c_char = std::mem::transmute(version);
+        let version = CStr::from_ptr(version());
+        if version.to_bytes_with_nul() != PLUGIN_VERSION.as_bytes() {
+            return Err(format!(
+                "The plugin is built against version {} instead of {}",
+                version.to_string_lossy(),
+                PLUGIN_VERSION.trim_end_matches('\0'),
+            ));
+        }
+
+        let registration = dl::dlsym(handle, symbol.as_ptr());
+        if registration.is_null() {
+            return Err(dl::last_error());
+        }
+
+        let registration: PluginRegistration = std::mem::transmute(registration);
+        registration(self);
+        Ok(())
+    }
+}
+
+/** The dynamic loader of the platform. */
+#[cfg(unix)]
+mod dl {
+    use std::ffi::{c_char, c_int, c_void, CStr};
+
+    pub const RTLD_NOW: c_int = 2;
+
+    #[link(name = "dl")]
+    extern "C" {
+        pub fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+        pub fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlerror() -> *const c_char;
+    }
+
+    /** Returns the message of the last error of the loader. */
+    pub unsafe fn last_error() -> String {
+        let message = dlerror();
+        if message.is_null() {
+            "Unknown error".to_string()
+        } else {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Circuit, TerminalID};
+    use crate::simulator::Simulator;
+
+    /** Builds the plugin library of the fixtures and returns its path. */
+    #[cfg(unix)]
+    fn build_fixture() -> std::path::PathBuf {
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/plugin/Cargo.toml");
+        // The target directory of the tests is locked while they run, the fixture has its own.
+        let target = std::env::current_exe().unwrap().ancestors().nth(3).unwrap().join("plugin-fixture");
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let status = std::process::Command::new(cargo)
+            .args(["build", "--quiet", "--manifest-path", manifest, "--target-dir"])
+            .arg(&target)
+            .status()
+            .expect("Cargo could not be run");
+        assert!(status.success(), "The plugin fixture did not build");
+
+        let name = format!("{}elec_plugin_fixture{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX);
+        target.join("debug").join(name)
+    }
+
+    #[test]
+    fn register_models() {
+        let mut registry = PluginRegistry::new();
+        assert!(registry.create("resistor", &[1.0]).is_none());
+
+        registry.register("resistor", |_| panic!("Not built in this test"));
+        assert_eq!(registry.models().collect::<Vec<_>>(), vec!["resistor"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn load_rejects_missing_libraries() {
+        let mut registry = PluginRegistry::new();
+        let result = unsafe { registry.load(std::path::Path::new("/nonexistent/libplugin.so")) };
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn load_plugin_library() {
+        let mut registry = PluginRegistry::new();
+        unsafe { registry.load(&build_fixture()) }.unwrap();
+        assert_eq!(registry.models().collect::<Vec<_>>(), vec!["resistor"]);
+
+        // 1V across the 1 kΩ resistor of the plugin.
+        let resistor = registry.create("resistor", &[1e3]).unwrap();
+        let mut simulator = Simulator::new(Circuit::new(
+            vec![Components::Ground, Components::VoltageGenerator(1.0), Components::CurrentProbe { label: "I".to_string() }, resistor],
+            vec![
+                (TerminalID::new(1, 0), TerminalID::new(0, 0)),
+                (TerminalID::new(1, 1), TerminalID::new(2, 0)),
+                (TerminalID::new(2, 1), TerminalID::new(3, 0)),
+                (TerminalID::new(3, 1), TerminalID::new(0, 0)),
+            ],
+        ));
+        let unknowns = simulator.solve();
+        let intensity = simulator.probe_current(&unknowns, "I").unwrap();
+        assert!((intensity.abs() - 1e-3).abs() < 1e-12, "{}A through the plugin resistor", intensity);
+    }
+}
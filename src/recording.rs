@@ -0,0 +1,190 @@
+use std::path::Path;
+
+/**
+Loads the (time, value) points of a recorded waveform from a CSV file, for a `PwlVoltage` or a
+`PwlCurrent`. Each line holds a time in seconds and a value separated by a comma, a semicolon or
+whitespace. A first line that is not numeric is a header and is skipped.
+*/
+pub fn load_csv(path: &Path) -> Result<Vec<(f64, f64)>, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    parse_csv(&content)
+}
+
+fn parse_csv(content: &str) -> Result<Vec<(f64, f64)>, String> {
+    let mut points = Vec::new();
+
+    for (line_idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line
+            .split([',', ';', ' ', '\t'])
+            .filter(|field| !field.is_empty())
+            .collect();
+        let point = match fields[..] {
+            [time, value] => time.parse().and_then(|time| value.parse().map(|value| (time, value))).ok(),
+            _ => None,
+        };
+
+        match point {
+            Some(point) => points.push(point),
+            None if line_idx == 0 => continue,
+            None => return Err(format!("Invalid point on line {}", line_idx + 1)),
+        }
+    }
+
+    if points.is_empty() {
+        return Err("The file does not contain any point".to_string());
+    }
+    if points.windows(2).any(|points| points[0].0 > points[1].0) {
+        return Err("The points must be sorted by time".to_string());
+    }
+    Ok(points)
+}
+
+/**
+Loads the (time, value) points of a channel of a WAV file (PCM 8, 16, 24 or 32 bits, or 32 or 64
+bits float), for a `PwlVoltage` or a `PwlCurrent`. The samples are normalized between -1 and 1 and
+multiplied by full_scale, and are linearly interpolated between the sampling times.
+*/
+pub fn load_wav(path: &Path, channel: usize, full_scale: f64) -> Result<Vec<(f64, f64)>, String> {
+    let content = std::fs::read(path).map_err(|err| err.to_string())?;
+    parse_wav(&content, channel, full_scale)
+}
+
+/** Format of the samples of a WAV file, from its "fmt " chunk. */
+struct WavFormat {
+    float: bool,
+    channels: usize,
+    sample_rate: u32,
+    bits_per_sample: usize,
+}
+
+fn parse_wav(content: &[u8], channel: usize, full_scale: f64) -> Result<Vec<(f64, f64)>, String> {
+    if content.len() < 12 || &content[0..4] != b"RIFF" || &content[8..12] != b"WAVE" {
+        return Err("Not a WAV file".to_string());
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut offset = 12;
+    while offset + 8 <= content.len() {
+        let id = &content[offset..offset + 4];
+        let size = u32::from_le_bytes(content[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let body = content
+            .get(offset + 8..offset + 8 + size)
+            .ok_or("Truncated WAV chunk")?;
+
+        match id {
+            b"fmt " if size >= 16 => {
+                let audio_format = u16::from_le_bytes([body[0], body[1]]);
+                // WAVE_FORMAT_EXTENSIBLE gives the actual format in its sub-format GUID.
+                let audio_format = if audio_format == 0xFFFE && size >= 26 {
+                    u16::from_le_bytes([body[24], body[25]])
+                } else {
+                    audio_format
+                };
+                format = Some(WavFormat {
+                    float: match audio_format {
+                        1 => false,
+                        3 => true,
+                        _ => return Err(format!("Unsupported WAV format {}", audio_format)),
+                    },
+                    channels: u16::from_le_bytes([body[2], body[3]]) as usize,
+                    sample_rate: u32::from_le_bytes(body[4..8].try_into().unwrap()),
+                    bits_per_sample: u16::from_le_bytes([body[14], body[15]]) as usize,
+                });
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are aligned on two bytes.
+        offset += 8 + size + size % 2;
+    }
+
+    let format = format.ok_or("Missing WAV format chunk")?;
+    let data = data.ok_or("Missing WAV data chunk")?;
+    if channel >= format.channels {
+        return Err(format!("The WAV file only has {} channels", format.channels));
+    }
+
+    let bytes = format.bits_per_sample / 8;
+    let frame = bytes * format.channels;
+    if frame == 0 || data.len() < frame {
+        return Err("The WAV file does not contain any sample".to_string());
+    }
+    data.chunks_exact(frame)
+        .enumerate()
+        .map(|(idx, frame)| {
+            let sample = &frame[channel * bytes..(channel + 1) * bytes];
+            let value = match (format.float, format.bits_per_sample) {
+                (false, 8) => (sample[0] as f64 - 128.0) / 128.0,
+                (false, 16) => i16::from_le_bytes([sample[0], sample[1]]) as f64 / 32768.0,
+                // 24 bits are shifted into the top of an i32 to keep the sign.
+                (false, 24) => i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) as f64 / 2147483648.0,
+                (false, 32) => i32::from_le_bytes(sample.try_into().unwrap()) as f64 / 2147483648.0,
+                (true, 32) => f32::from_le_bytes(sample.try_into().unwrap()) as f64,
+                (true, 64) => f64::from_le_bytes(sample.try_into().unwrap()),
+                (_, bits) => return Err(format!("Unsupported WAV sample size of {} bits", bits)),
+            };
+            Ok((idx as f64 / format.sample_rate as f64, value * full_scale))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** Builds a PCM WAV file of 16 bits samples, interleaved by channel. */
+    fn wav(channels: u16, sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+        let data: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        let mut content = Vec::new();
+        content.extend(b"RIFF");
+        content.extend((36 + data.len() as u32).to_le_bytes());
+        content.extend(b"WAVEfmt ");
+        content.extend(16u32.to_le_bytes());
+        content.extend(1u16.to_le_bytes());
+        content.extend(channels.to_le_bytes());
+        content.extend(sample_rate.to_le_bytes());
+        content.extend((sample_rate * 2 * channels as u32).to_le_bytes());
+        content.extend((2 * channels).to_le_bytes());
+        content.extend(16u16.to_le_bytes());
+        content.extend(b"data");
+        content.extend((data.len() as u32).to_le_bytes());
+        content.extend(data);
+        content
+    }
+
+    #[test]
+    fn csv_with_header_and_separators() {
+        let points = parse_csv("time,value\n0,1\n0.5; 2\n\n1\t-3e-1\n").unwrap();
+        assert_eq!(points, vec![(0.0, 1.0), (0.5, 2.0), (1.0, -0.3)]);
+    }
+
+    #[test]
+    fn csv_errors() {
+        assert_eq!(parse_csv("0,1\nx,2\n"), Err("Invalid point on line 2".to_string()));
+        assert_eq!(parse_csv("1,1\n0,2\n"), Err("The points must be sorted by time".to_string()));
+        assert!(parse_csv("time,value\n").is_err());
+    }
+
+    #[test]
+    fn wav_channel_is_scaled() {
+        let content = wav(2, 4, &[0, 16384, -32768, 0, 16384, -16384]);
+        let points = parse_wav(&content, 1, 2.0).unwrap();
+        assert_eq!(points, vec![(0.0, 1.0), (0.25, 0.0), (0.5, -1.0)]);
+    }
+
+    #[test]
+    fn wav_errors() {
+        assert!(parse_wav(b"RIFF0000AVI ", 0, 1.0).is_err());
+        assert!(parse_wav(&wav(1, 8000, &[0, 1]), 1, 1.0).is_err());
+        let mut truncated = wav(1, 8000, &[0, 1]);
+        truncated.truncate(truncated.len() - 1);
+        assert!(parse_wav(&truncated, 0, 1.0).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+use crate::simulator::{ComponentReading, OperatingPoint, Quantity, RatedQuantity, Simulation, Violation};
+
+/** The matrix and the result vector of the system, then the operating point. */
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Matrix: {}", self.matrix)?;
+        writeln!(f, "Result: {:?}", self.result)?;
+        write!(f, "{}", self.operating_point)
+    }
+}
+
+/** Number of consumers listed in the power budget. */
+const LARGEST_CONSUMERS: usize = 3;
+
+/**
+One line per component, e.g. "Relay 3 (closed): 5V, 0.1A", after the unknowns and before the power
+budget.
+*/
+impl fmt::Display for OperatingPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Unknowns: {:?}", self.unknowns)?;
+        for reading in &self.readings {
+            writeln!(f, "{}", reading)?;
+        }
+
+        let budget = self.power_budget(LARGEST_CONSUMERS);
+        write!(f, "Power: {}W supplied, {}W absorbed", budget.supplied, budget.absorbed)?;
+        for (idx, (component_id, power)) in budget.largest_consumers.iter().enumerate() {
+            let separator = if idx == 0 { ", largest consumers: " } else { ", " };
+            write!(f, "{}{} {} ({}W)", separator, self.readings[component_id.0].kind, component_id.0, power)?;
+        }
+        writeln!(f)
+    }
+}
+
+impl fmt::Display for ComponentReading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.component_id.0)?;
+        if let Some(detail) = &self.detail {
+            write!(f, " ({})", detail)?;
+        }
+        for (idx, quantity) in self.quantities.iter().enumerate() {
+            let separator = if idx == 0 { ": " } else { ", " };
+            write!(f, "{}{}", separator, quantity)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.prefix, self.value, self.unit)
+    }
+}
+
+/** e.g. "Resistor 2: power 0.5W exceeds its 0.25W rating (margin -100%)". */
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, unit) = match self.quantity {
+            RatedQuantity::Power => ("power", "W"),
+            RatedQuantity::Voltage => ("voltage", "V"),
+            RatedQuantity::Current => ("current", "A"),
+        };
+        write!(f, "{} {}: {} {}{} exceeds its {}{} rating (margin {}%)", self.kind, self.component_id.0, name, self.value, unit, self.rating, unit, self.margin() * 100.0)
+    }
+}
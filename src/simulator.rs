@@ -1,281 +1,5027 @@
-use std::collections::HashMap;
-use crate::model::{Circuit, ComponentID, Components, TerminalID};
-use nalgebra::{DMatrix, DVector};
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::bode::Bode;
+use crate::digital::{Change, DigitalEngine};
+use crate::symbolic::{self, Polynomial, SymbolicTransfer};
+use crate::fault::{FaultInjection, FaultReport, FaultResult};
+use crate::event::{Event, EventOccurrence, Stop, StopCondition, StopReason};
+use crate::component::StampContext;
+use crate::optimization::{self, Optimization, OptimizationResult};
+use crate::monte_carlo::{MonteCarlo, MonteCarloResult, ToleranceDistribution};
+use crate::model::{Circuit, ComponentID, Components, TerminalID, NOMINAL_TEMPERATURE};
+use nalgebra::{Complex, ComplexField, DMatrix, DVector, Dyn, LU};
 use crate::disjoint_set::DisjointSet;
+use crate::expansion::{self, Expansion};
+use crate::expression::{Expression, Variable};
+use crate::devices::{bjt, diode, fuse, ideal_diode, jfet, lamp, led, logic, magnetic_core, memristor, mosfet, motor, opamp, optocoupler, potentiometer, noise, regulator, spark_gap, switch, table, transfer_function, transmission_line, triode, tvs, waveform, zener, Linearized, LinearizedTerminals};
+
+/**
+Conductance that forces the voltage of a capacitor to its initial condition when the initial point
+of a transient analysis is solved from the initial conditions.
+*/
+const INITIAL_CONDITION_CONDUCTANCE: f64 = 1e9;
+
+/** Absolute tolerance on the unknowns for the Newton–Raphson iteration to be converged. */
+const ABSOLUTE_TOLERANCE: f64 = 1e-9;
+
+/** Relative tolerance on the unknowns for the Newton–Raphson iteration to be converged. */
+const RELATIVE_TOLERANCE: f64 = 1e-6;
+
+/** Resolution of the time of an event, as a fraction of the time step of the transient analysis. */
+const EVENT_RESOLUTION: f64 = 1e-6;
+
+/** Settings of the Newton–Raphson iteration of `Simulator::solve`. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NewtonOptions {
+    /** Maximum number of iterations before giving up. */
+    pub max_iterations: usize,
+    /** Fraction of the Newton step applied at each iteration, between 0 excluded and 1. */
+    pub damping: f64,
+    /**
+    Largest change of an unknown in one iteration, in volts or amperes, which keeps exponential
+    models (diodes, transistors) from overflowing far from the solution.
+    */
+    pub max_step: f64,
+}
+
+impl Default for NewtonOptions {
+    fn default() -> Self {
+        Self { max_iterations: 100, damping: 1.0, max_step: f64::INFINITY }
+    }
+}
+
+/**
+Gmin stepping: conductance to ground added on every node for the first retry of a solve that did
+not converge, and the factor it is divided by until it is below the smallest one. The factor is
+reduced when a step does not converge, down to the smallest factor.
+*/
+const GMIN_START: f64 = 1e-2;
+const GMIN_FACTOR: f64 = 10.0;
+const GMIN_SMALLEST: f64 = 1e-12;
+const GMIN_SMALLEST_FACTOR: f64 = 1.01;
+
+/**
+Source stepping: first increment of the factor applied to the independent generators, which is
+halved when a step does not converge, down to the smallest increment.
+*/
+const SOURCE_STEP: f64 = 0.1;
+const SOURCE_SMALLEST_STEP: f64 = 1e-4;
+
+/** How the last solve converged. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Convergence {
+    /** The Newton–Raphson iteration converged directly. */
+    Newton,
+    /**
+    The Newton–Raphson iteration converged after a conductance to ground was added on every node
+    and progressively removed, each solve starting from the previous one.
+    */
+    GminStepping,
+    /**
+    The Newton–Raphson iteration converged after the independent generators were ramped from 0 to
+    their actual value, each solve starting from the previous one.
+    */
+    SourceStepping,
+}
+
+/** A time step over which reactive components are replaced by their companion model. */
+#[derive(Debug, Clone)]
+pub struct TimeStep {
+    /** Time at the end of the step, in seconds. */
+    pub time: f64,
+    /** Duration of the step, in seconds. */
+    pub dt: f64,
+    /** The unknowns (X) solved at the beginning of the step. */
+    pub previous: DVector<f64>,
+}
+
+/**
+Integration method of the companion models of capacitors and inductors (and coupled inductors).
+The other reactive models (supercapacitors, op-amp poles, transfer functions...) always use
+backward Euler.
+*/
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Integration {
+    /** First order, damps oscillations. */
+    #[default]
+    BackwardEuler,
+    /** Second order, keeps the energy of oscillations but may ring after a discontinuity. */
+    Trapezoidal,
+    /** Second order backward differentiation (BDF2), the first step is a backward Euler one. */
+    Gear2,
+}
+
+/**
+Approximation of the derivative of a quantity x at the end of a time step by a linear multistep
+method: x'_n = alpha * x_n + c1 * x_n-1 + c2 * x_n-2 - gamma * x'_n-1.
+*/
+struct Derivative {
+    alpha: f64,
+    c1: f64,
+    c2: f64,
+    gamma: f64,
+}
+
+/** The angular frequency of an AC analysis, with the imaginary unit of the scalar of the matrix. */
+struct AngularFrequency<T> {
+    w: f64,
+    j: T,
+}
+
+impl<T: ComplexField<RealField = f64>> AngularFrequency<T> {
+    /** Returns jw. */
+    fn jw(&self) -> T {
+        self.j.clone().scale(self.w)
+    }
+
+    /** Returns re + j * im. */
+    fn complex(&self, re: f64, im: f64) -> T {
+        T::from_real(re) + self.j.clone().scale(im)
+    }
+}
+
+/**
+How the reactive components are stamped: as open or short circuits at DC, by their companion model
+over a time step, or by their impedance at the angular frequency of an AC analysis.
+*/
+enum Regime<'a, T> {
+    Dc,
+    Transient(&'a TimeStep),
+    Ac(&'a AngularFrequency<T>),
+}
+
+/** Bounds and tolerances of the time step of an adaptive transient analysis. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StepControl {
+    /** Duration of the first time step, in seconds. */
+    pub initial_step: f64,
+    /** Shortest time step, accepted even when its error is above the tolerances. */
+    pub min_step: f64,
+    /** Longest time step. */
+    pub max_step: f64,
+    /** Relative tolerance on the local truncation error of the unknowns. */
+    pub relative_tolerance: f64,
+    /** Absolute tolerance on the local truncation error of the unknowns, in volts or amperes. */
+    pub absolute_tolerance: f64,
+}
+
+impl StepControl {
+    /** Step control between min_step and max_step, with the default tolerances (1e-3 and 1e-6). */
+    pub fn new(min_step: f64, max_step: f64) -> Self {
+        Self { initial_step: min_step, min_step, max_step, relative_tolerance: 1e-3, absolute_tolerance: 1e-6 }
+    }
+}
+
+/** Settings of a periodic steady state analysis by the shooting method, see `Simulator::periodic_steady_state`. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Shooting {
+    /** Period of the steady state, that of the generators driving the circuit, in seconds. */
+    pub period: f64,
+    /** Number of time steps per period. */
+    pub steps: usize,
+    /** Number of Newton iterations on the state at the period boundary before giving up. */
+    pub max_iterations: usize,
+    /** Relative tolerance on the difference between the unknowns at both ends of the period. */
+    pub relative_tolerance: f64,
+    /** Absolute tolerance on that difference, in volts or amperes. */
+    pub absolute_tolerance: f64,
+}
+
+impl Shooting {
+    /** Shooting over the given period and steps, with the default iterations and tolerances (20, 1e-4 and 1e-6). */
+    pub fn new(period: f64, steps: usize) -> Self {
+        Self { period, steps, max_iterations: 20, relative_tolerance: 1e-4, absolute_tolerance: 1e-6 }
+    }
+}
+
+/** Solution of the circuit at a point, see `Simulator::operating_point`. */
+#[derive(Debug, Clone)]
+pub struct OperatingPoint {
+    /** The unknowns (X), see `Simulator::terminal_voltage` and `Simulator::branch_intensity`. */
+    pub unknowns: DVector<f64>,
+    /** The terminals connected to each node, the node 0 being the ground. */
+    pub nodes: Vec<Vec<TerminalID>>,
+    /** The voltage of each node, 0 for the ground. */
+    pub node_voltages: Vec<f64>,
+    /** The branch intensities with their component, which has several of them in a row if it has several branches. */
+    pub branch_intensities: Vec<(ComponentID, f64)>,
+    /** A reading of each component of the circuit, composite ones included but not their parts. */
+    pub readings: Vec<ComponentReading>,
+}
+
+impl OperatingPoint {
+    /** Returns the power budget of the circuit, with its count largest consumers. */
+    pub fn power_budget(&self, count: usize) -> PowerBudget {
+        let powers: Vec<(ComponentID, f64)> = self.readings
+            .iter()
+            .filter_map(|reading| reading.power.map(|power| (reading.component_id, power)))
+            .collect();
+
+        let mut largest_consumers: Vec<(ComponentID, f64)> = powers.iter().copied().filter(|(_, power)| *power > 0.0).collect();
+        largest_consumers.sort_by(|left, right| right.1.total_cmp(&left.1));
+        largest_consumers.truncate(count);
+
+        PowerBudget {
+            supplied: -powers.iter().map(|(_, power)| power.min(0.0)).sum::<f64>(),
+            absorbed: powers.iter().map(|(_, power)| power.max(0.0)).sum(),
+            largest_consumers,
+        }
+    }
+}
+
+/** The system (M * X = Y) of a solved circuit and its operating point, see `Simulator::simulate`. */
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    /** The matrix (M), linearized at the operating point. */
+    pub matrix: DMatrix<f64>,
+    /** The result vector (Y). */
+    pub result: DVector<f64>,
+    pub operating_point: OperatingPoint,
+}
+
+/**
+Power budget of a circuit: the power supplied by the components that deliver power (generators,
+discharging capacitors, ...) and the power absorbed by the others, which balance each other.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerBudget {
+    /** Power supplied, in watts. */
+    pub supplied: f64,
+    /** Power absorbed, in watts. */
+    pub absorbed: f64,
+    /** The components that absorb the most power, from the largest, with their power in watts. */
+    pub largest_consumers: Vec<(ComponentID, f64)>,
+}
+
+/** Quantity a component is rated for, see `Rating`. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RatedQuantity {
+    Power,
+    Voltage,
+    Current,
+}
+
+/**
+Violation of a rating of a component found by `Simulator::design_rule_check`, e.g. "Resistor 2:
+power 0.5W exceeds its 0.25W rating".
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub component_id: ComponentID,
+    /** Kind of the component, e.g. "Resistor". */
+    pub kind: &'static str,
+    pub quantity: RatedQuantity,
+    /** Magnitude of the quantity. */
+    pub value: f64,
+    pub rating: f64,
+}
+
+impl Violation {
+    /** Returns the margin left by the component, (rating - value) / rating, negative for a violation. */
+    pub fn margin(&self) -> f64 {
+        (self.rating - self.value) / self.rating
+    }
+}
+
+/** Reading of a component at an operating point, e.g. "Relay 3 (closed): 5V, 0.1A". */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentReading {
+    pub component_id: ComponentID,
+    /** Kind of the component, e.g. "Relay". */
+    pub kind: &'static str,
+    /** State of the component, e.g. "closed". */
+    pub detail: Option<String>,
+    pub quantities: Vec<Quantity>,
+    /**
+    Power absorbed by the component in watts, negative when it delivers power. None for a custom
+    component, whose intensities are not known.
+    */
+    pub power: Option<f64>,
+}
+
+/** A value read on a component, e.g. "Vce=5V" has the prefix "Vce=" and the unit "V". */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quantity {
+    pub prefix: &'static str,
+    pub value: f64,
+    pub unit: &'static str,
+}
+
+/** Result of a transient analysis, the operating point at t = 0 followed by every time step. */
+#[derive(Debug, Clone)]
+pub struct Transient {
+    /** Time of each point, in seconds. */
+    pub times: Vec<f64>,
+    /** The unknowns (X) solved at each time, see `Simulator::terminal_voltage` and `Simulator::branch_intensity`. */
+    pub unknowns: Vec<DVector<f64>>,
+    /** The power absorbed by each component at each time, see `ComponentReading::power`. */
+    pub powers: Vec<Vec<Option<f64>>>,
+    /** The events that happened, see `Simulator::transient_with_events`. */
+    pub events: Vec<EventOccurrence>,
+}
+
+impl Transient {
+    /**
+    Returns the energy absorbed by a component over the analysis in joules, negative when it
+    delivers energy, None for a custom component.
+    */
+    pub fn energy(&self, component_id: ComponentID) -> Option<f64> {
+        let powers: Option<Vec<f64>> = self.powers.iter().map(|powers| powers[component_id.0]).collect();
+        let powers = powers?;
+
+        // Trapezoidal integration of the power over the time points.
+        Some(self.times.windows(2).zip(powers.windows(2)).map(|(times, powers)| (times[1] - times[0]) * (powers[0] + powers[1]) / 2.0).sum())
+    }
+
+    /** Returns the waveform of a quantity read from the unknowns, one value per time. */
+    pub fn waveform(&self, read: impl Fn(&DVector<f64>) -> f64) -> Vec<f64> {
+        self.unknowns.iter().map(read).collect()
+    }
+}
+
+/** Values from start to stop by increments of step, both included, like the ranges of the `.dc` analysis of SPICE. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SweepRange {
+    pub start: f64,
+    pub stop: f64,
+    /** Increment between two values, negative to sweep downwards. */
+    pub step: f64,
+}
+
+impl SweepRange {
+    /** Returns the swept values, stop being kept despite rounding. */
+    pub fn values(&self) -> Vec<f64> {
+        if self.step == 0.0 || (self.stop - self.start) * self.step < 0.0 {
+            panic!("The step must be nonzero and go from start towards stop");
+        }
+
+        // The values are computed from their index so that rounding errors do not add up.
+        let count = ((self.stop - self.start) / self.step * (1.0 + 1e-9)).floor() as usize + 1;
+        (0..count).map(|k| self.start + self.step * k as f64).collect()
+    }
+}
+
+/** Result of a DC sweep, the unknowns (X) at each value of the swept source or temperature. */
+#[derive(Debug, Clone)]
+pub struct DcSweep {
+    /** The values of the swept source in volts or amperes, or the temperatures in °C. */
+    pub values: Vec<f64>,
+    /** The unknowns (X) solved at each value, see `Simulator::terminal_voltage` and `Simulator::branch_intensity`. */
+    pub unknowns: Vec<DVector<f64>>,
+}
+
+impl DcSweep {
+    /** Returns the transfer characteristic of a quantity read from the unknowns, one value per swept value. */
+    pub fn characteristic(&self, read: impl Fn(&DVector<f64>) -> f64) -> Vec<f64> {
+        self.unknowns.iter().map(read).collect()
+    }
+}
+
+/** I–V characteristic of a two-terminal component, see `Simulator::curve_trace`. */
+#[derive(Debug, Clone)]
+pub struct CurveTrace {
+    /** The voltages across the component (V0 - V1, e.g. anode minus cathode), in volts. */
+    pub voltages: Vec<f64>,
+    /** The intensity entering the terminal 0 at each voltage, in amperes. */
+    pub currents: Vec<f64>,
+}
+
+/** Result of a nested DC sweep, one DC sweep of the inner generator per value of the outer one. */
+#[derive(Debug, Clone)]
+pub struct NestedDcSweep {
+    /** The values of the outer generator, in volts or amperes. */
+    pub outer_values: Vec<f64>,
+    /** The sweep of the inner generator at each outer value. */
+    pub sweeps: Vec<DcSweep>,
+}
+
+/** Result of a small-signal transfer function analysis, like the `.tf` analysis of SPICE. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SmallSignalTransfer {
+    /** Output voltage per volt (or ampere) of the source. */
+    pub gain: f64,
+    /** Resistance seen by the source, in ohms. */
+    pub input_resistance: f64,
+    /** Resistance seen from the output node to the ground, in ohms. */
+    pub output_resistance: f64,
+}
+
+/** Thévenin equivalent of a circuit seen between two nodes, whose Norton dual is given by `norton_current`. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Thevenin {
+    /** Open-circuit voltage, in volts. */
+    pub voltage: f64,
+    /** Equivalent resistance, in ohms, which is also the resistance of the Norton equivalent. */
+    pub resistance: f64,
+}
+
+impl Thevenin {
+    /** Returns the short-circuit intensity of the Norton equivalent, in amperes. */
+    pub fn norton_current(&self) -> f64 {
+        self.voltage / self.resistance
+    }
+}
+
+/**
+Maximum power transfer from the circuit seen between two nodes, see `Simulator::max_power_transfer`:
+its Thévenin equivalent, the load that draws the most power from it and that power. At DC (a
+frequency of 0) the voltage is the DC one, otherwise the AC amplitude, the power being then the
+average power.
+*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PowerTransfer {
+    /** In hertz, 0 at DC. */
+    pub frequency: f64,
+    /** Open-circuit voltage, in volts. */
+    pub voltage: Complex<f64>,
+    /** Impedance of the source, in ohms. */
+    pub source_impedance: Complex<f64>,
+    /** Impedance of the optimal load, the complex conjugate of the source impedance, in ohms. */
+    pub load_impedance: Complex<f64>,
+    /** Power delivered to the optimal load, in watts. */
+    pub power: f64,
+}
+
+impl PowerTransfer {
+    /** Returns the power delivered to any load, in watts, e.g. to plot the power against a swept load. */
+    pub fn load_power(&self, load_impedance: Complex<f64>) -> f64 {
+        let intensity = self.voltage / (self.source_impedance + load_impedance);
+        let power = intensity.norm_sqr() * load_impedance.re;
+        if self.frequency == 0.0 { power } else { power / 2.0 }
+    }
+}
+
+/** Frequencies of an AC sweep, in Hz, like the `.ac` analysis of SPICE. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FrequencySweep {
+    /** points frequencies evenly spaced from start to stop, both included. */
+    Linear { start: f64, stop: f64, points: usize },
+    /** points_per_decade frequencies per decade from start, up to stop. */
+    Decade { start: f64, stop: f64, points_per_decade: usize },
+    /** points_per_octave frequencies per octave from start, up to stop. */
+    Octave { start: f64, stop: f64, points_per_octave: usize },
+}
+
+impl FrequencySweep {
+    /** Returns the swept frequencies in increasing order. */
+    pub fn frequencies(&self) -> Vec<f64> {
+        let (start, stop, base, points) = match *self {
+            FrequencySweep::Linear { start, stop, points } => {
+                if points == 0 || start > stop {
+                    panic!("A linear sweep needs at least one point and start <= stop");
+                }
+                let step = if points == 1 { 0.0 } else { (stop - start) / (points - 1) as f64 };
+                return (0..points).map(|k| start + step * k as f64).collect();
+            }
+            FrequencySweep::Decade { start, stop, points_per_decade } => (start, stop, 10.0f64, points_per_decade),
+            FrequencySweep::Octave { start, stop, points_per_octave } => (start, stop, 2.0f64, points_per_octave),
+        };
+
+        if points == 0 || start <= 0.0 || start > stop {
+            panic!("A logarithmic sweep needs at least one point per interval and 0 < start <= stop");
+        }
+        // The frequencies are computed from their index so that rounding errors do not add up, and
+        // stop is kept despite rounding.
+        let count = ((stop / start).ln() / base.ln() * points as f64 * (1.0 + 1e-9)).floor() as usize + 1;
+        (0..count).map(|k| start * base.powf(k as f64 / points as f64)).collect()
+    }
+}
+
+/** Result of an AC sweep, the complex unknowns (X) at each frequency. */
+#[derive(Debug, Clone)]
+pub struct AcSweep {
+    /** The swept frequencies, in Hz. */
+    pub frequencies: Vec<f64>,
+    /** The unknowns (X) solved at each frequency, see `Simulator::terminal_voltage` and `Simulator::branch_intensity`. */
+    pub unknowns: Vec<DVector<Complex<f64>>>,
+}
+
+impl AcSweep {
+    /** Returns the response of a quantity read from the unknowns, one value per frequency. */
+    pub fn response(&self, read: impl Fn(&DVector<Complex<f64>>) -> Complex<f64>) -> Vec<Complex<f64>> {
+        self.unknowns.iter().map(read).collect()
+    }
+}
+
+/** Small-signal parameters of a nonlinear component at the operating point. */
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmallSignal {
+    /** Conductance di/dv of a two-terminal device (diode, zener, LED...), 1 / rd. */
+    Bipole { conductance: f64 },
+    /**
+    Jacobian of a device with more terminals (transistor, optocoupler): conductances[t][k] is the
+    derivative of the intensity entering the terminal t by the voltage of the terminal k, e.g. gm,
+    gpi and gds.
+    */
+    Terminals { conductances: Vec<Vec<f64>> },
+}
+
+/** Circuit linearized at its DC operating point, see `Simulator::linearize`. */
+#[derive(Debug, Clone)]
+pub struct Linearization {
+    /** The unknowns (X) at the operating point. */
+    pub unknowns: DVector<f64>,
+    /** The matrix of the circuit at the operating point, the G of the AC analysis. */
+    pub matrix: DMatrix<f64>,
+    /** The small-signal parameters of each nonlinear component. */
+    pub devices: Vec<(ComponentID, SmallSignal)>,
+}
+
+/** Impedance between two nodes over a frequency sweep, see `Simulator::impedance`. */
+#[derive(Debug, Clone)]
+pub struct ImpedanceSweep {
+    /** The swept frequencies, in Hz. */
+    pub frequencies: Vec<f64>,
+    /** The complex impedance at each frequency, in ohms. */
+    pub impedances: Vec<Complex<f64>>,
+}
+
+impl ImpedanceSweep {
+    /** Returns |Z| at each frequency, in ohms. */
+    pub fn magnitudes(&self) -> Vec<f64> {
+        self.impedances.iter().map(|z| z.norm()).collect()
+    }
+
+    /** Returns the phase of Z at each frequency, in degrees, positive for an inductive impedance. */
+    pub fn phases_degrees(&self) -> Vec<f64> {
+        self.impedances.iter().map(|z| z.arg().to_degrees()).collect()
+    }
+}
+
+/** Loop gain of a feedback loop over a frequency sweep, see `Simulator::loop_gain`. */
+#[derive(Debug, Clone)]
+pub struct LoopGain {
+    /** The swept frequencies, in Hz. */
+    pub frequencies: Vec<f64>,
+    /** The loop gain T at each frequency, the closed loop gain being A / (1 + T). */
+    pub gains: Vec<Complex<f64>>,
+}
+
+impl LoopGain {
+    /** Returns the Bode plot of the loop gain. */
+    pub fn bode(&self) -> Bode {
+        Bode::new(self.frequencies.clone(), &self.gains)
+    }
+
+    /** Returns the gain margin in dB, see `Bode::gain_margin`. */
+    pub fn gain_margin(&self) -> Option<f64> {
+        self.bode().gain_margin()
+    }
+
+    /** Returns the phase margin in degrees, see `Bode::phase_margin`. */
+    pub fn phase_margin(&self) -> Option<f64> {
+        self.bode().phase_margin()
+    }
+}
+
+/** Port of an S-parameter analysis, between the nodes of two terminals, see `Simulator::s_parameters`. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Port {
+    pub positive: TerminalID,
+    pub negative: TerminalID,
+    /** Real reference impedance of the port, in ohms (usually 50). */
+    pub impedance: f64,
+}
+
+/** Result of an S-parameter analysis, the scattering matrix of the ports at each frequency. */
+#[derive(Debug, Clone)]
+pub struct SParameters {
+    /** The swept frequencies, in Hz. */
+    pub frequencies: Vec<f64>,
+    /** The S-matrix at each frequency, matrix[(k, j)] being the wave leaving port k for a unit wave entering port j. */
+    pub matrices: Vec<DMatrix<Complex<f64>>>,
+}
+
+impl SParameters {
+    /** Returns the parameter S_kj at each frequency, e.g. (1, 0) for S21 with ports numbered from 0. */
+    pub fn parameter(&self, k: usize, j: usize) -> Vec<Complex<f64>> {
+        self.matrices.iter().map(|matrix| matrix[(k, j)]).collect()
+    }
+}
+
+/** State of a component that depends on the history of the circuit rather than on the unknowns. */
+#[derive(Debug, Clone, PartialEq)]
+enum ComponentState {
+    Relay { closed: bool },
+    Latch { q: bool },
+    /** The clock is the level at the last commit, to detect its rising edges. */
+    FlipFlop { q: bool, clock: bool },
+    SparkGap { struck: bool },
+    Fuse { blown: bool, i2t: f64 },
+    Memristor { x: f64 },
+    /** Intensity entering the terminal 0, needed by the trapezoidal method. */
+    Capacitor { current: f64 },
+    /** Temperature of the filament above the ambient, in kelvins. */
+    Lamp { temperature: f64 },
+    Battery { soc: f64 },
+    /** Speed of the rotor, in rad/s. */
+    DcMotor { speed: f64 },
+    /** The samples of the ports needed to compute the delayed sources. */
+    TransmissionLine { history: VecDeque<transmission_line::Sample> },
+    /** State vector of the realization of the transfer function. */
+    TransferFunction { x: DVector<f64> },
+    /** Number of time steps committed, which selects the sample of a noise source. */
+    Noise { step: u64 },
+}
 
 pub struct Simulator {
     circuit: Circuit,
+    /** Number of components before the composite ones are expanded. */
+    component_count: usize,
+    /** For each part of a composite component, the component it was expanded from. */
+    parents: HashMap<ComponentID, ComponentID>,
     n: usize,
-    component_id_to_vgenerator_id: HashMap<ComponentID, usize>,
+    component_id_to_branch_id: HashMap<ComponentID, usize>,
     nodes: Vec<Vec<TerminalID>>,
     terminal_to_node: HashMap<TerminalID, usize>,
 
-    vgenerators: Vec<ComponentID>,
-}
+    branches: Vec<ComponentID>,
+
+    /** None for a DC solve, where capacitors are open circuits. */
+    time_step: Option<TimeStep>,
+
+    /** The unknowns (X) around which nonlinear components are linearized. */
+    guess: DVector<f64>,
+
+    /** State of the components with a memory, updated by `commit`. */
+    states: HashMap<ComponentID, ComponentState>,
+
+    /** Temperature of the circuit, in °C. */
+    temperature: f64,
+    /** Temperature of the components with a thermal model, in °C, updated by `commit`. */
+    component_temperatures: HashMap<ComponentID, f64>,
+    /** Levels of the signals of the digital engine during a mixed-signal transient analysis. */
+    digital_signals: Vec<bool>,
+
+    integration: Integration,
+    /**
+    The unknowns before the previous ones and the duration of the previous time step, set when a
+    time step is committed, for the second order methods.
+    */
+    older: Option<(DVector<f64>, f64)>,
+
+    newton: NewtonOptions,
+    /** Conductance between every node and the ground, only nonzero during Gmin stepping. */
+    gmin: f64,
+    /** Factor applied to the independent generators, only below 1 during source stepping. */
+    source_scale: f64,
+    /** How the last solve converged. */
+    convergence: Convergence,
+
+    /** Whether transient analyses start from the initial conditions rather than the DC operating point. */
+    use_initial_conditions: bool,
+    /**
+    Set while the initial point is solved from the initial conditions: capacitors are then voltage
+    sources and inductors current sources, of their initial condition or 0.
+    */
+    applying_initial_conditions: bool,
+}
+
+impl Simulator {
+    /**
+    Creates a new simulator.
+
+    The simulator tries to solve M * X = Y, where M is the matrix of the circuit, X is the unknowns
+    and Y is the result.
+
+    ## Result (Y):
+    transpose(Y) = [I_1, I_2, ..., I_n, V_b0, V_b1, ..., V_bm]
+
+    Where:
+    - Where I_i is the intensity that goes through the node i. I_i = 0 as per the Kirchhoff's law.
+    - V_bj is the voltage across the branch j.
+
+    ## Unknowns (X):
+    transpose(X) = [V_1, V_2, ..., V_n, I_b0, I_b1, ..., I_bm]
+
+    Where:
+    - V_i is the voltage of the node i. NOTE: By convention, V_0 = 0, the node 0 being the one of the
+      ground components if there are any.
+    - I_bj is the intensity that goes through the branch j.
+
+    A branch is a component whose intensity cannot be deduced from the node voltages, and thus is an
+    unknown of its own: voltage generators, batteries, inductors, relay coils, motors, supercapacitors,
+    op-amp and regulator outputs, transformers, transmission lines and voltage sources controlled by
+    a voltage or an intensity. A component
+    may have several branches, in which case they follow each other.
+
+    Composite components (capacitors with parasitics, ...) are first replaced by the primitive
+    components they are made of, which are appended to the circuit.
+
+    Nonlinear components (diodes, transistors) are replaced by their tangent around a guess of X, and the
+    equation is solved repeatedly until the guess does not move anymore (Newton–Raphson).
+    */
+    pub fn new(circuit: Circuit) -> Self {
+        Self::check_terminals(&circuit);
+
+        let component_count = circuit.components.len();
+        let Expansion { circuit, terminals, parents } = expansion::expand(circuit);
+
+        // Both terminals of a wire are the same node, so a wire is merged like an edge.
+        let wires: Vec<(TerminalID, TerminalID)> = circuit.components
+            .iter()
+            .enumerate()
+            .filter(|(_, component)| matches!(component, Components::Wire))
+            .map(|(component_id, _)| (TerminalID::new(component_id, 0), TerminalID::new(component_id, 1)))
+            .collect();
+
+        let grounds: Vec<TerminalID> = circuit.components
+            .iter()
+            .enumerate()
+            .filter(|(_, component)| matches!(component, Components::Ground))
+            .map(|(component_id, _)| TerminalID::new(component_id, 0))
+            .collect();
+
+        let terminal_ids: Vec<TerminalID> = circuit.terminal_edges
+            .iter()
+            .chain(&wires)
+            .flat_map(|(left, right)| [*left, *right])
+            .chain(grounds.iter().copied())
+            .collect();
+
+        let nodes = {
+            let mut terminal_disjoint_set = DisjointSet::new(terminal_ids);
+
+            for (left, right) in circuit.terminal_edges.iter().chain(&wires) {
+                terminal_disjoint_set.merge(*left, *right);
+            }
+            for ground in grounds.iter().skip(1) {
+                terminal_disjoint_set.merge(grounds[0], *ground);
+            }
+            let mut nodes = terminal_disjoint_set.into_terminal_groups();
+
+            // The node 0 is the reference, it must be the ground.
+            if let Some(ground) = grounds.first() {
+                let ground_node_id = nodes.iter().position(|node| node.contains(ground)).unwrap();
+                nodes.swap(0, ground_node_id);
+            }
+            nodes
+        };
+
+        let branches: Vec<ComponentID> = circuit.components
+            .iter()
+            .enumerate()
+            .flat_map(|(component_id, component)| {
+                vec![ComponentID(component_id); Self::get_branch_count(component)]
+            })
+            .collect();
+
+
+
+        let terminal_id_to_node_id = {
+            let mut m = HashMap::new();
+            for (node_id, node) in nodes.iter().enumerate() {
+                for terminal_id in node {
+                    m.insert(*terminal_id, node_id);
+                }
+            }
+
+            // The terminals of composite components are connected to the node of their part.
+            for (terminal_id, moved) in &terminals {
+                if let Some(node_id) = m.get(moved) {
+                    m.insert(*terminal_id, *node_id);
+                }
+            }
+            m
+        };
+
+        let component_id_to_branch_id = {
+            let mut m = HashMap::new();
+            for (branch_id, component_id) in branches.iter().enumerate().rev() {
+                // A component with several branches is mapped to its first one.
+                m.insert(*component_id, branch_id);
+            }
+            m
+        };
+
+        for component in &circuit.components {
+            if let Components::Ccvs { control, .. } | Components::Cccs { control, .. } = component {
+                if !component_id_to_branch_id.contains_key(control) {
+                    panic!("The control component does not have a branch intensity");
+                }
+            }
+        }
+
+        for component in &circuit.components {
+            if let Components::BehavioralVoltage(expression) | Components::BehavioralCurrent(expression) = component {
+                for variable in expression.variables() {
+                    match variable {
+                        Variable::Voltage(terminal_id) => {
+                            if !terminal_id_to_node_id.contains_key(&terminal_id) {
+                                panic!("The expression refers to a terminal that does not exist");
+                            }
+                        }
+                        Variable::Intensity(component_id) => {
+                            if !component_id_to_branch_id.contains_key(&component_id) {
+                                panic!("The expression refers to a component that does not have a branch intensity");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for component in &circuit.components {
+            if let Components::TransferFunction { numerator, denominator } = component {
+                if denominator.last().is_none_or(|leading| *leading == 0.0) || numerator.len() > denominator.len() {
+                    panic!("The transfer function must be proper, with a nonzero leading denominator coefficient");
+                }
+            }
+        }
+
+        for component in &circuit.components {
+            if let Components::PwlVoltage { points } | Components::PwlCurrent { points } = component {
+                if points.is_empty() || points.windows(2).any(|points| points[0].0 > points[1].0) {
+                    panic!("The PWL points must not be empty and must be sorted by time");
+                }
+            }
+        }
+
+        for component in &circuit.components {
+            if let Components::TableResistor { table } = component {
+                if table.len() < 2 || table.windows(2).any(|points| points[0].0 >= points[1].0) {
+                    panic!("The I-V table must have at least two points sorted by voltage");
+                }
+            }
+            if let Components::Battery { ocv, .. } = component {
+                if ocv.len() < 2 || ocv.windows(2).any(|points| points[0].0 >= points[1].0) {
+                    panic!("The OCV table must have at least two points sorted by state of charge");
+                }
+            }
+        }
+
+        for component in &circuit.components {
+            if let Components::BuckConverter { duty, .. } | Components::BoostConverter { duty, .. } = component {
+                if *duty <= 0.0 || *duty >= 1.0 {
+                    panic!("The duty cycle must be between 0 and 1 excluded");
+                }
+            }
+        }
+
+        for coupling in &circuit.couplings {
+            for component_id in [coupling.inductors.0, coupling.inductors.1] {
+                if !matches!(circuit.components[component_id.0], Components::Inductor(_)) {
+                    panic!("Only inductors can be coupled");
+                }
+            }
+        }
+
+        for tolerance in &circuit.tolerances {
+            if circuit.components.get(tolerance.component.0).and_then(Components::value).is_none() {
+                panic!("A tolerance must be given to a component with a single value");
+            }
+            if !(0.0..1.0).contains(&tolerance.relative) {
+                panic!("The relative tolerance must be between 0 and 1");
+            }
+        }
+
+        for coefficient in &circuit.temperature_coefficients {
+            if !matches!(circuit.components.get(coefficient.component.0), Some(Components::Resistor(_))) {
+                panic!("Only resistors have a temperature coefficient");
+            }
+        }
+
+        for thermal_model in &circuit.thermal_models {
+            if !(thermal_model.thermal_resistance > 0.0 && thermal_model.thermal_capacitance > 0.0) {
+                panic!("The thermal resistance and capacitance must be positive");
+            }
+        }
+
+        for rating in &circuit.ratings {
+            if rating.component.0 >= component_count {
+                panic!("The component {} does not exist", rating.component.0);
+            }
+            if ![rating.max_power, rating.max_voltage, rating.max_current].iter().flatten().all(|max| *max > 0.0) {
+                panic!("The ratings must be positive");
+            }
+        }
+
+        for initial_condition in &circuit.initial_conditions {
+            if !matches!(circuit.components.get(initial_condition.component.0), Some(Components::Capacitor(_) | Components::Inductor(_))) {
+                panic!("Only capacitors and inductors have an initial condition");
+            }
+        }
+
+        let n = nodes.len() - 1 + branches.len();
+
+        let component_temperatures = circuit.thermal_models.iter().map(|thermal_model| (thermal_model.component, NOMINAL_TEMPERATURE)).collect();
+        let states = Self::get_initial_states(&circuit.components);
+
+        Self { circuit, component_count, parents, component_id_to_branch_id, nodes, terminal_to_node: terminal_id_to_node_id, n, branches, time_step: None, guess: DVector::zeros(n), states, temperature: NOMINAL_TEMPERATURE, component_temperatures, digital_signals: Vec::new(), integration: Integration::default(), older: None, newton: NewtonOptions::default(), gmin: 0.0, source_scale: 1.0, convergence: Convergence::Newton, use_initial_conditions: false, applying_initial_conditions: false }
+    }
+
+    /**
+    Checks that the edges only refer to existing terminals, and that every terminal is connected. An
+    oriented component must have two terminals.
+    */
+    fn check_terminals(circuit: &Circuit) {
+        let connected: HashSet<TerminalID> = circuit.terminal_edges
+            .iter()
+            .flat_map(|(left, right)| [*left, *right])
+            .collect();
+
+        for terminal_id in &connected {
+            let Some(component) = circuit.components.get(terminal_id.component_id.0) else {
+                panic!("The component {} does not exist", terminal_id.component_id.0);
+            };
+            if terminal_id.idx >= component.terminal_count() {
+                panic!("The component {} does not have a terminal {}", terminal_id.component_id.0, terminal_id.idx);
+            }
+        }
+
+        for component in &circuit.components {
+            if let Components::Oriented { component, .. } = component {
+                if component.terminal_count() != 2 {
+                    panic!("Only a two-terminal component can be oriented");
+                }
+            }
+        }
+
+        for (component_id, component) in circuit.components.iter().enumerate() {
+            // A wire merges its terminals by itself, one of them may be left unconnected.
+            if matches!(component, Components::Wire) {
+                continue;
+            }
+            for idx in 0..component.terminal_count() {
+                if !connected.contains(&TerminalID::new(component_id, idx)) {
+                    panic!("The terminal {} of the component {} is not connected", idx, component_id);
+                }
+            }
+        }
+    }
+
+    /** Returns the state of a component before any solve, if it has one. */
+    /** Returns the initial state of each component with a memory. */
+    fn get_initial_states(components: &[Components]) -> HashMap<ComponentID, ComponentState> {
+        components
+            .iter()
+            .enumerate()
+            .filter_map(|(component_id, component)| {
+                Self::get_initial_state(component).map(|state| (ComponentID(component_id), state))
+            })
+            .collect()
+    }
+
+    fn get_initial_state(component: &Components) -> Option<ComponentState> {
+        match component {
+            Components::Relay { .. } => Some(ComponentState::Relay { closed: false }),
+            Components::SrLatch { .. } => Some(ComponentState::Latch { q: false }),
+            Components::DFlipFlop { .. } => Some(ComponentState::FlipFlop { q: false, clock: false }),
+            Components::SparkGap { .. } => Some(ComponentState::SparkGap { struck: false }),
+            Components::Fuse { .. } => Some(ComponentState::Fuse { blown: false, i2t: 0.0 }),
+            Components::Memristor { x, .. } => Some(ComponentState::Memristor { x: *x }),
+            Components::Capacitor(_) => Some(ComponentState::Capacitor { current: 0.0 }),
+            Components::Lamp { .. } => Some(ComponentState::Lamp { temperature: 0.0 }),
+            Components::DcMotor { .. } => Some(ComponentState::DcMotor { speed: 0.0 }),
+            Components::Battery { soc, .. } => Some(ComponentState::Battery { soc: *soc }),
+            Components::TransmissionLine { .. } => Some(ComponentState::TransmissionLine { history: VecDeque::new() }),
+            Components::NoiseVoltage { .. } | Components::NoiseCurrent { .. } => Some(ComponentState::Noise { step: 0 }),
+            Components::TransferFunction { denominator, .. } => {
+                Some(ComponentState::TransferFunction { x: DVector::zeros(denominator.len() - 1) })
+            }
+            _ => None,
+        }
+    }
+
+    /**
+    Updates the state of the components with a memory (relays, fuses, batteries, ...) from an
+    accepted solution. It must be called once per time point, the state is then used by the
+    following solves.
+    */
+    pub fn commit(&mut self, unknowns: &DVector<f64>) {
+        let mut states = std::mem::take(&mut self.states);
+
+        for (component_id, state) in states.iter_mut() {
+            match (&self.circuit.components[component_id.0], state) {
+                (Components::Relay { pull_in_current, drop_out_current, .. }, ComponentState::Relay { closed }) => {
+                    let branch_id = self.get_branch_id_from_component_id(component_id);
+                    let intensity = unknowns[self.nodes.len() - 1 + branch_id].abs();
+
+                    if intensity > *pull_in_current {
+                        *closed = true;
+                    } else if intensity < *drop_out_current {
+                        *closed = false;
+                    }
+                }
+                (Components::SrLatch { .. }, ComponentState::Latch { q }) => {
+                    let v = [0, 1, 4, 5].map(|idx| {
+                        let node_id = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx));
+                        self.node_voltage(unknowns, node_id)
+                    });
+                    let [v_set, v_reset, v_ground, v_supply] = v;
+                    *q = logic::latch(*q, logic::is_high(v_set, v_ground, v_supply), logic::is_high(v_reset, v_ground, v_supply));
+                }
+                (Components::DFlipFlop { .. }, ComponentState::FlipFlop { q, clock }) => {
+                    let v = [0, 1, 4, 5].map(|idx| {
+                        let node_id = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx));
+                        self.node_voltage(unknowns, node_id)
+                    });
+                    let [v_d, v_clock, v_ground, v_supply] = v;
+                    let clock_now = logic::is_high(v_clock, v_ground, v_supply);
+                    *q = logic::flip_flop(*q, logic::is_high(v_d, v_ground, v_supply), *clock, clock_now);
+                    *clock = clock_now;
+                }
+                (Components::SparkGap { strike_voltage, holding_voltage, arc_resistance, extinction_current }, ComponentState::SparkGap { struck }) => {
+                    let v = self.bipole_voltage(unknowns, &TerminalID::new(component_id.0, 0));
+                    let linearized = spark_gap::linearize(*struck, *holding_voltage, *arc_resistance, v);
+                    let intensity = linearized.conductance * v + linearized.current;
+                    *struck = spark_gap::is_struck(*struck, *strike_voltage, *extinction_current, v, intensity);
+                }
+                (Components::Fuse { rated_current, melting_i2t, .. }, ComponentState::Fuse { blown, i2t }) => {
+                    if *blown {
+                        continue;
+                    }
+
+                    let resistance = self.get_fuse_resistance(*component_id, &ComponentState::Fuse { blown: false, i2t: *i2t });
+                    let intensity = self.bipole_voltage(unknowns, &TerminalID::new(component_id.0, 0)) / resistance;
+                    let dt = self.time_step.as_ref().map(|time_step| time_step.dt);
+                    (*i2t, *blown) = fuse::melt(*rated_current, *melting_i2t, *i2t, intensity, dt);
+                }
+                (Components::Capacitor(capacitance), ComponentState::Capacitor { current }) => {
+                    let Some(time_step) = &self.time_step else {
+                        *current = 0.0;
+                        continue;
+                    };
+
+                    let terminal_id = TerminalID::new(component_id.0, 0);
+                    let derivative = self.derivative(time_step);
+                    let history = self.derivative_history(&derivative, |unknowns| self.bipole_voltage(unknowns, &terminal_id));
+                    *current = capacitance * (derivative.alpha * self.bipole_voltage(unknowns, &terminal_id) + history) - derivative.gamma * *current;
+                }
+                (Components::Memristor { r_on, r_off, drift, .. }, ComponentState::Memristor { x }) => {
+                    // The state only moves during a time step.
+                    let Some(time_step) = &self.time_step else {
+                        continue;
+                    };
+
+                    let intensity = self.bipole_voltage(unknowns, &TerminalID::new(component_id.0, 0)) / memristor::resistance(*r_on, *r_off, *x);
+                    *x = memristor::integrate(*drift, *x, intensity, time_step.dt);
+                }
+                (Components::Lamp { cold_resistance, temperature_coefficient, thermal_resistance, thermal_capacitance }, ComponentState::Lamp { temperature }) => {
+                    // The filament only heats up during a time step.
+                    let Some(time_step) = &self.time_step else {
+                        continue;
+                    };
+
+                    let v = self.bipole_voltage(unknowns, &TerminalID::new(component_id.0, 0));
+                    let power = v * v / lamp::resistance(*cold_resistance, *temperature_coefficient, *temperature);
+                    *temperature = lamp::heat(*temperature, power, *thermal_resistance, *thermal_capacitance, time_step.dt);
+                }
+                (Components::Battery { capacity, .. }, ComponentState::Battery { soc }) => {
+                    let Some(time_step) = &self.time_step else {
+                        continue;
+                    };
+
+                    // The branch intensity enters the positive terminal, so it charges the battery.
+                    let branch_id = self.get_branch_id_from_component_id(component_id);
+                    let intensity = unknowns[self.nodes.len() - 1 + branch_id];
+                    *soc = (*soc + intensity * time_step.dt / (capacity * 3600.0)).clamp(0.0, 1.0);
+                }
+                (Components::DcMotor { back_emf_constant, inertia, load_torque, .. }, ComponentState::DcMotor { speed }) => {
+                    let Some(time_step) = &self.time_step else {
+                        continue;
+                    };
+
+                    let branch_id = self.get_branch_id_from_component_id(component_id);
+                    let intensity = unknowns[self.nodes.len() - 1 + branch_id];
+                    *speed = motor::accelerate(*back_emf_constant, *inertia, *load_torque, *speed, intensity, time_step.dt);
+                }
+                (Components::TransmissionLine { delay, .. }, ComponentState::TransmissionLine { history }) => {
+                    let branch_id = self.get_branch_id_from_component_id(component_id);
+                    let v = [0, 1, 2, 3].map(|idx| {
+                        let node_id = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx));
+                        self.node_voltage(unknowns, node_id)
+                    });
+                    let sample = transmission_line::Sample {
+                        time: self.time(),
+                        v1: v[1] - v[0],
+                        i1: unknowns[self.nodes.len() - 1 + branch_id],
+                        v2: v[3] - v[2],
+                        i2: unknowns[self.nodes.len() + branch_id],
+                    };
+                    transmission_line::record(history, sample, *delay);
+                }
+                (Components::NoiseVoltage { .. } | Components::NoiseCurrent { .. }, ComponentState::Noise { step }) => {
+                    if self.time_step.is_some() {
+                        *step += 1;
+                    }
+                }
+                (Components::TransferFunction { numerator, denominator }, ComponentState::TransferFunction { x }) => {
+                    let realization = transfer_function::realize(numerator, denominator);
+                    let v = [2, 3].map(|idx| {
+                        let node_id = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx));
+                        self.node_voltage(unknowns, node_id)
+                    });
+                    let u = v[1] - v[0];
+
+                    match &self.time_step {
+                        Some(time_step) => *x = realization.advance(x, u, time_step.dt),
+                        None => {
+                            if let Some(steady_state) = realization.steady_state(u) {
+                                *x = steady_state;
+                            }
+                        }
+                    }
+                }
+                _ => panic!("Unexpected component state"),
+            }
+        }
+
+        self.states = states;
+
+        // C dT/dt = P - (T - T_ambient) / R integrated by backward Euler, a DC solve setting the
+        // components back to the ambient.
+        for thermal_model in &self.circuit.thermal_models {
+            let temperature = match &self.time_step {
+                None => self.temperature,
+                Some(time_step) => {
+                    let power = self.power(unknowns, thermal_model.component).unwrap_or(0.0);
+                    let capacitance = thermal_model.thermal_capacitance / time_step.dt;
+                    let conductance = 1.0 / thermal_model.thermal_resistance;
+                    (capacitance * self.component_temperatures[&thermal_model.component] + power + conductance * self.temperature) / (capacitance + conductance)
+                }
+            };
+            self.component_temperatures.insert(thermal_model.component, temperature);
+        }
+
+        self.older = self.time_step.as_ref().map(|time_step| (time_step.previous.clone(), time_step.dt));
+
+        for component_id in 0..self.circuit.components.len() {
+            let Components::Custom(custom) = &self.circuit.components[component_id] else {
+                continue;
+            };
+            let (terminal_rows, branch_rows) = self.stamp_rows(ComponentID(component_id), custom.terminals(), custom.branches());
+
+            // The context only borrows the time step, so the component can be updated through it.
+            let ctx = StampContext { terminal_rows, branch_rows, unknowns, time_step: self.time_step.as_ref() };
+            if let Components::Custom(custom) = &mut self.circuit.components[component_id] {
+                custom.post_solve(&ctx);
+            }
+        }
+    }
+
+    /** Returns how many branch intensities a component adds to the unknowns. */
+    fn get_branch_count(component: &Components) -> usize {
+        match component {
+            Components::VoltageGenerator(_) | Components::SineGenerator { .. } | Components::CurrentProbe { .. } | Components::Inductor(_)
+            | Components::PulseGenerator { .. } | Components::PwlVoltage { .. } | Components::ExpGenerator { .. }
+            | Components::FmGenerator { .. } | Components::PrbsGenerator { .. }
+            | Components::NoiseVoltage { .. }
+            | Components::OpAmp { .. } | Components::Transformer { .. } | Components::Vcvs { .. } | Components::Ccvs { .. } | Components::Relay { .. }
+            | Components::Battery { .. } | Components::Supercapacitor { .. } | Components::DcMotor { .. }
+            | Components::Regulator { .. } | Components::BehavioralVoltage(_) | Components::TransferFunction { .. } => 1,
+            // The ideal transformer and the magnetizing inductance.
+            Components::SaturatingTransformer { .. } => 2,
+            // One intensity per port.
+            Components::TransmissionLine { .. } => 2,
+            Components::Custom(component) => component.branches(),
+            _ => 0,
+        }
+    }
+
+    /** Sets the temperature of the circuit in °C, which adjusts the resistors with a temperature coefficient. */
+    pub fn set_temperature(&mut self, temperature: f64) {
+        self.temperature = temperature;
+    }
+
+    /**
+    Returns the temperature of a component in °C: the one of its thermal model if it has one (see
+    `ThermalModel`), the temperature of the circuit otherwise.
+    */
+    pub fn component_temperature(&self, component_id: ComponentID) -> f64 {
+        self.component_temperatures.get(&component_id).copied().unwrap_or(self.temperature)
+    }
+
+    /** Sets the iteration limit and the damping of the Newton–Raphson iteration. */
+    pub fn set_newton_options(&mut self, newton: NewtonOptions) {
+        if newton.max_iterations == 0 {
+            panic!("The Newton–Raphson iteration needs at least one iteration");
+        }
+        if !(newton.damping > 0.0 && newton.damping <= 1.0) {
+            panic!("The damping must be between 0 excluded and 1");
+        }
+        if newton.max_step <= 0.0 || newton.max_step.is_nan() {
+            panic!("The maximum step must be positive");
+        }
+        self.newton = newton;
+    }
+
+    /** Sets the integration method of the companion models of capacitors and inductors. */
+    pub fn set_integration(&mut self, integration: Integration) {
+        self.integration = integration;
+    }
+
+    /**
+    Sets whether transient analyses start from the initial conditions of the capacitors and
+    inductors (0 when not given) instead of the DC operating point, like the UIC option of SPICE.
+    */
+    pub fn set_use_initial_conditions(&mut self, use_initial_conditions: bool) {
+        self.use_initial_conditions = use_initial_conditions;
+    }
+
+    /**
+    Sets the time step used for the companion models, or None to go back to a DC solve. Panics if
+    the step is longer than the delay of a transmission line.
+    */
+    pub fn set_time_step(&mut self, time_step: Option<TimeStep>) {
+        if let Some(time_step) = &time_step {
+            self.check_time_step(time_step.dt);
+        }
+        self.time_step = time_step;
+    }
+
+    /**
+    Returns the longest time step the circuit allows, the smallest delay of its transmission
+    lines, which must not be stepped over: a line reads its other port one delay earlier.
+    */
+    fn longest_time_step(&self) -> f64 {
+        self.circuit.components
+            .iter()
+            .filter_map(|component| match component {
+                Components::TransmissionLine { delay, .. } => Some(*delay),
+                _ => None,
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /** Panics if the time step is longer than the circuit allows, see `longest_time_step`. */
+    fn check_time_step(&self, dt: f64) {
+        if dt > self.longest_time_step() {
+            panic!("The time step must not be longer than the delay of a transmission line");
+        }
+    }
+
+    /**
+    Runs a transient analysis from the initial point at t = 0 to tstop, with time steps of dt
+    (the last one may be shorter). The states of the components are committed at every step, and
+    the simulator is left in DC mode. Panics if dt is not positive, or longer than the delay of a
+    transmission line.
+    */
+    pub fn transient(&mut self, tstop: f64, dt: f64) -> Transient {
+        assert!(dt.is_finite() && dt > 0.0, "The time step must be positive");
+        let (initial_point, initial_powers) = self.initial_point();
+        let mut times = vec![0.0];
+        let mut unknowns = vec![initial_point];
+        let mut powers = vec![initial_powers];
+        let mut step = 0;
+        while times[step] < tstop {
+            // The times are computed from the step count so that rounding errors do not add up.
+            let time = ((step + 1) as f64 * dt).min(tstop);
+            self.set_time_step(Some(TimeStep { time, dt: time - times[step], previous: unknowns[step].clone() }));
+            let solution = self.solve();
+            powers.push(self.powers(&solution));
+            self.commit(&solution);
+
+            times.push(time);
+            unknowns.push(solution);
+            step += 1;
+        }
+
+        self.set_time_step(None);
+        Transient { times, unknowns, powers, events: Vec::new() }
+    }
+
+    /**
+    Runs a transient analysis like `transient`, but lands on the events: the time steps are
+    shortened to end on the breakpoints and just before the toggle times of the switches, and a step over
+    which a threshold is crossed is retried up to the crossing, found by the secant method, until
+    it ends within a millionth of dt of it. The discontinuities are then taken into account when
+    they happen rather than up to a time step later.
+
+    The listener is called with each event and the unknowns when the analysis lands on it. The
+    events are also recorded in the result.
+    */
+    pub fn transient_with_events(&mut self, tstop: f64, dt: f64, events: &[Event], mut listener: impl FnMut(&EventOccurrence, &DVector<f64>)) -> Transient {
+        self.transient_events(tstop, dt, events, |_, unknowns, occurrences| {
+            for occurrence in occurrences {
+                listener(occurrence, unknowns.last().unwrap());
+            }
+            false
+        })
+    }
+
+    /**
+    Runs a transient analysis like `transient_with_events`, but stops as soon as one of the
+    conditions is met, and returns why and when it stopped.
+    */
+    pub fn transient_until(&mut self, tstop: f64, dt: f64, conditions: &[StopCondition]) -> (Transient, Stop) {
+        // The events of the conditions, with the index of their condition.
+        let (events, event_conditions): (Vec<Event>, Vec<usize>) = conditions
+            .iter()
+            .enumerate()
+            .filter_map(|(k, condition)| match condition {
+                StopCondition::Event(event) => Some((event.clone(), k)),
+                StopCondition::SteadyState { .. } => None,
+            })
+            .unzip();
+
+        let mut reason = StopReason::Completed;
+        let transient = self.transient_events(tstop, dt, &events, |times, unknowns, occurrences| {
+            if let Some(occurrence) = occurrences.first() {
+                reason = StopReason::Condition(event_conditions[occurrence.event]);
+                return true;
+            }
+            let steady = conditions.iter().position(|condition| match condition {
+                StopCondition::SteadyState { duration, relative_tolerance, absolute_tolerance } => {
+                    let time = *times.last().unwrap();
+                    let current = unknowns.last().unwrap();
+                    times[0] <= time - duration
+                        && times.iter().zip(unknowns).rev().take_while(|(t, _)| **t >= time - duration).all(|(_, x)| {
+                            x.iter().zip(current.iter()).all(|(x, c)| (x - c).abs() <= absolute_tolerance + relative_tolerance * c.abs())
+                        })
+                }
+                StopCondition::Event(_) => false,
+            });
+            if let Some(k) = steady {
+                reason = StopReason::Condition(k);
+            }
+            steady.is_some()
+        });
+
+        let stop = Stop { reason, time: *transient.times.last().unwrap() };
+        (transient, stop)
+    }
+
+    /**
+    Runs a transient analysis landing on the events, see `transient_with_events`. After each step,
+    after_step is called with the times and unknowns so far and the events of the step, and the
+    analysis stops if it returns true.
+    */
+    fn transient_events(
+        &mut self,
+        tstop: f64,
+        dt: f64,
+        events: &[Event],
+        mut after_step: impl FnMut(&[f64], &[DVector<f64>], &[EventOccurrence]) -> bool,
+    ) -> Transient {
+        assert!(dt.is_finite() && dt > 0.0, "The time step must be positive");
+        let resolution = dt * EVENT_RESOLUTION;
+        let mut breakpoints: Vec<f64> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Breakpoint(time) => Some(*time),
+                Event::Threshold { .. } => None,
+            })
+            // A switch has toggled at the end of a step ending on its toggle time, the step ends just before.
+            .chain(self.circuit.components.iter().flat_map(|component| match component {
+                Components::Switch { toggle_times, .. } => toggle_times.iter().map(|time| time - resolution).collect(),
+                _ => Vec::new(),
+            }))
+            .filter(|time| *time > 0.0 && *time < tstop)
+            .collect();
+        breakpoints.sort_by(f64::total_cmp);
+
+        let (initial_point, initial_powers) = self.initial_point();
+        let mut times = vec![0.0];
+        let mut unknowns = vec![initial_point];
+        let mut powers = vec![initial_powers];
+        let mut occurrences = Vec::new();
+        while *times.last().unwrap() < tstop {
+            let step = times.len() - 1;
+            let start = times[step];
+            let grid = Self::next_grid_time(start, dt, resolution);
+            let breakpoint = breakpoints.iter().copied().find(|time| *time > start + resolution);
+            let mut time = breakpoint.map_or(grid, |breakpoint| breakpoint.min(grid)).min(tstop);
+
+            let (solution, crossed) = loop {
+                self.set_time_step(Some(TimeStep { time, dt: time - start, previous: unknowns[step].clone() }));
+                let solution = self.solve();
+
+                // The crossings over the step, their times being interpolated linearly.
+                let crossings: Vec<(usize, f64)> = events
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, event)| {
+                        let Event::Threshold { expression, level, direction } = event else {
+                            return None;
+                        };
+                        let before = self.evaluate_expression(&unknowns[step], expression, start) - level;
+                        let after = self.evaluate_expression(&solution, expression, time) - level;
+                        direction.crosses(before, after).then(|| (k, start + (time - start) * before / (before - after)))
+                    })
+                    .collect();
+
+                let earliest = crossings.iter().map(|(_, crossing_time)| *crossing_time).fold(f64::INFINITY, f64::min);
+                if time - earliest > resolution && earliest - start > resolution {
+                    time = earliest;
+                    continue;
+                }
+
+                let landed = events.iter().enumerate().filter_map(|(k, event)| match event {
+                    Event::Breakpoint(breakpoint) if (breakpoint - time).abs() <= resolution => Some(k),
+                    _ => None,
+                });
+                break (solution, crossings.iter().map(|(k, _)| *k).chain(landed).collect::<Vec<usize>>());
+            };
+
+            powers.push(self.powers(&solution));
+            self.commit(&solution);
+            times.push(time);
+            unknowns.push(solution);
+            let step_occurrences: Vec<EventOccurrence> = crossed.into_iter().map(|event| EventOccurrence { event, time }).collect();
+            occurrences.extend(&step_occurrences);
+            if after_step(&times, &unknowns, &step_occurrences) {
+                break;
+            }
+        }
+
+        self.set_time_step(None);
+        Transient { times, unknowns, powers, events: occurrences }
+    }
+
+    /**
+    Runs a mixed-signal transient analysis: the digital engine runs alongside the analog solver,
+    from the initial point at t = 0 to tstop with time steps of dt, which are shortened to land on
+    the changes of the digital signals.
+
+    The `DigitalOutput` components drive the analog circuit with the levels of their signals, a
+    change applying from the step that follows it. After each step, the signals of the
+    `DigitalInput` components are set from their voltages at its end, and the engine is advanced
+    to that time. The digital waveforms are in the history of the engine.
+    */
+    pub fn transient_mixed(&mut self, tstop: f64, dt: f64, engine: &mut DigitalEngine) -> Transient {
+        assert!(dt.is_finite() && dt > 0.0, "The time step must be positive");
+        self.digital_signals = engine.signals().to_vec();
+        let (initial_point, initial_powers) = self.initial_point();
+        self.exchange_digital_signals(&initial_point, 0.0, engine);
+
+        let mut times = vec![0.0];
+        let mut unknowns = vec![initial_point];
+        let mut powers = vec![initial_powers];
+        while *times.last().unwrap() < tstop {
+            let step = times.len() - 1;
+            let grid = Self::next_grid_time(times[step], dt, dt * EVENT_RESOLUTION);
+            let time = engine.next_time().map_or(grid, |next| next.min(grid)).min(tstop);
+
+            self.set_time_step(Some(TimeStep { time, dt: time - times[step], previous: unknowns[step].clone() }));
+            let solution = self.solve();
+            powers.push(self.powers(&solution));
+            self.commit(&solution);
+            self.exchange_digital_signals(&solution, time, engine);
+
+            times.push(time);
+            unknowns.push(solution);
+        }
+
+        self.set_time_step(None);
+        self.digital_signals.clear();
+        Transient { times, unknowns, powers, events: Vec::new() }
+    }
+
+    /**
+    Sets the signals of the digital inputs from the unknowns at a time, advances the engine to
+    that time, and takes the new levels of the signals for the digital outputs.
+    */
+    fn exchange_digital_signals(&mut self, unknowns: &DVector<f64>, time: f64, engine: &mut DigitalEngine) {
+        for (component_id, component) in self.circuit.components.iter().enumerate() {
+            if let Components::DigitalInput { signal, threshold } = component {
+                let v = self.terminal_voltage(unknowns, TerminalID::new(component_id, 1)) - self.terminal_voltage(unknowns, TerminalID::new(component_id, 0));
+                let value = v > *threshold;
+                if engine.signals()[*signal] != value {
+                    engine.schedule(Change { time, signal: *signal, value });
+                }
+            }
+        }
+
+        engine.advance(time);
+        self.digital_signals = engine.signals().to_vec();
+    }
+
+    /** Returns the level of a signal of the digital engine, low outside of a mixed-signal transient analysis. */
+    fn digital_signal(&self, signal: usize) -> bool {
+        self.digital_signals.get(signal).copied().unwrap_or(false)
+    }
+
+    /** Returns the next multiple of dt after a time, skipping the one within the resolution of it. */
+    fn next_grid_time(start: f64, dt: f64, resolution: f64) -> f64 {
+        let grid = ((start / dt).floor() + 1.0) * dt;
+        if grid - start < resolution {
+            grid + dt
+        } else {
+            grid
+        }
+    }
+
+    /**
+    Runs a transient analysis from the initial point at t = 0 to tstop, adapting the time
+    step to the local truncation error: the steps are long while the circuit changes slowly and
+    short around its fast edges.
+
+    The error of a step is estimated from the difference between the solution and its linear
+    extrapolation from the two previous points. A step whose error is above the tolerances is
+    rejected and retried with a shorter one, unless it is already min_step. The first step, which
+    has no extrapolation, is always accepted. The steps are not longer than the delay of the
+    transmission lines, even if max_step is.
+    */
+    pub fn transient_adaptive(&mut self, tstop: f64, control: &StepControl) -> Transient {
+        assert!(
+            0.0 < control.min_step && control.min_step <= control.max_step,
+            "The time step bounds must satisfy 0 < min_step <= max_step"
+        );
+        self.check_time_step(control.min_step);
+        let max_step = control.max_step.min(self.longest_time_step());
+
+        let (initial_point, initial_powers) = self.initial_point();
+        let mut times = vec![0.0];
+        let mut unknowns = vec![initial_point];
+        let mut powers = vec![initial_powers];
+        let mut dt = control.initial_step.clamp(control.min_step, max_step);
+        while *times.last().unwrap() < tstop {
+            let step = times.len() - 1;
+            // A step that would end just before tstop is stretched to avoid a tiny last step, but
+            // not beyond max_step.
+            let remaining = tstop - times[step];
+            let dt_try = if dt >= remaining || remaining - dt < control.min_step { remaining.min(max_step) } else { dt };
+
+            let time = times[step] + dt_try;
+            self.set_time_step(Some(TimeStep { time, dt: dt_try, previous: unknowns[step].clone() }));
+            let solution = self.solve();
+
+            // Ratio of the estimated error to the tolerance, a step being accepted below 1.
+            let ratio = if step == 0 {
+                0.0
+            } else {
+                let dt_previous = times[step] - times[step - 1];
+                // The difference with the extrapolation is x'' * dt * (dt + dt_previous) / 2, the
+                // error of a backward Euler step is x'' * dt^2 / 2.
+                let scale = dt_try / (dt_try + dt_previous);
+                solution.iter()
+                    .zip(unknowns[step].iter().zip(unknowns[step - 1].iter()))
+                    .map(|(x, (x1, x2))| {
+                        let predicted = x1 + (x1 - x2) * (dt_try / dt_previous);
+                        (x - predicted).abs() * scale / (control.absolute_tolerance + control.relative_tolerance * x.abs())
+                    })
+                    .fold(0.0, f64::max)
+            };
+
+            // The error varies as dt^2, the factors are bounded to keep the control stable.
+            let factor = (0.9 / ratio.sqrt()).clamp(0.2, 2.0);
+            if ratio > 1.0 && dt_try > control.min_step {
+                dt = (dt_try * factor).max(control.min_step);
+                continue;
+            }
+
+            powers.push(self.powers(&solution));
+            self.commit(&solution);
+            times.push(time);
+            unknowns.push(solution);
+            dt = (dt_try * factor).clamp(control.min_step, max_step);
+        }
+
+        self.set_time_step(None);
+        Transient { times, unknowns, powers, events: Vec::new() }
+    }
+
+    /**
+    Finds the periodic steady state of a circuit driven by generators of the given period
+    (rectifiers, switching converters...) by the shooting method, and returns one period of it,
+    from t = 0 to the period.
+
+    Starting from the initial point of a transient analysis, a period is integrated from the
+    unknowns at its beginning, which are corrected by Newton iterations until they are found again
+    at its end. The Jacobian of a period is estimated by finite differences, integrating one more
+    period per unknown, so it suits small circuits whose slowest time constant is much longer than
+    the period. If that Jacobian is singular (e.g. an undamped resonator), the unknowns at the end
+    of the period are used as the next guess instead.
+
+    The states of the components with a memory (relays, batteries...) follow the integrated
+    periods rather than being solved for. Panics if the iterations do not converge.
+    */
+    pub fn periodic_steady_state(&mut self, shooting: &Shooting) -> Transient {
+        assert!(shooting.period > 0.0 && shooting.steps > 0, "The period and the steps must be positive");
+
+        let (mut start, _) = self.initial_point();
+        for _ in 0..shooting.max_iterations {
+            let states = self.states.clone();
+            let period = self.shoot(&start, shooting);
+            let end = period.unknowns.last().unwrap().clone();
+
+            let converged = end.iter()
+                .zip(start.iter())
+                .all(|(x1, x0)| (x1 - x0).abs() <= shooting.absolute_tolerance + shooting.relative_tolerance * x1.abs());
+            if converged {
+                self.set_time_step(None);
+                return period;
+            }
+            let period_states = std::mem::replace(&mut self.states, states.clone());
+
+            // Newton step on F(x0) = end(x0) - x0, the Jacobian of the end being found by perturbing
+            // each unknown at the beginning of the period in turn.
+            let mut jacobian = DMatrix::<f64>::zeros(start.len(), start.len());
+            for j in 0..start.len() {
+                let delta = 1e-6 * (1.0 + start[j].abs());
+                let mut perturbed = start.clone();
+                perturbed[j] += delta;
+                let perturbed_end = self.shoot(&perturbed, shooting).unknowns.pop().unwrap();
+                self.states = states.clone();
+                jacobian.set_column(j, &((perturbed_end - &end) / delta));
+                jacobian[(j, j)] -= 1.0;
+            }
+
+            start = match jacobian.lu().solve(&(&end - &start)) {
+                Some(correction) if correction.iter().all(|x| x.is_finite()) => &start - correction,
+                _ => end,
+            };
+            self.states = period_states;
+        }
+
+        panic!("The shooting method did not converge after {} iterations", shooting.max_iterations)
+    }
+
+    /**
+    Integrates one period from the given unknowns at t = 0 with fixed time steps, committing the
+    states of the components at every step.
+    */
+    fn shoot(&mut self, start: &DVector<f64>, shooting: &Shooting) -> Transient {
+        self.older = None;
+        self.guess = start.clone();
+        let mut times = vec![0.0];
+        let mut unknowns = vec![start.clone()];
+        let mut powers = vec![self.powers(start)];
+        for step in 0..shooting.steps {
+            let time = (step + 1) as f64 * shooting.period / shooting.steps as f64;
+            self.set_time_step(Some(TimeStep { time, dt: time - times[step], previous: unknowns[step].clone() }));
+            let solution = self.solve();
+            powers.push(self.powers(&solution));
+            self.commit(&solution);
+
+            times.push(time);
+            unknowns.push(solution);
+        }
+        Transient { times, unknowns, powers, events: Vec::new() }
+    }
+
+    /**
+    Solves and commits the point a transient analysis starts from: the DC operating point, or the
+    point given by the initial conditions if they are used. Returns it with the power of each
+    component. The components start from their initial state, whatever an earlier analysis left.
+    */
+    fn initial_point(&mut self) -> (DVector<f64>, Vec<Option<f64>>) {
+        self.reset_states();
+        self.set_time_step(None);
+        self.applying_initial_conditions = self.use_initial_conditions;
+        let initial_point = self.solve();
+        let powers = self.powers(&initial_point);
+        self.applying_initial_conditions = false;
+
+        self.commit(&initial_point);
+        (initial_point, powers)
+    }
+
+    /**
+    Sets the components with a memory (relays, fuses, lamps, transmission lines...) and the
+    components with a thermal model back to their state before any analysis.
+    */
+    fn reset_states(&mut self) {
+        self.states = Self::get_initial_states(&self.circuit.components);
+        self.older = None;
+        let ambient = self.temperature;
+        self.component_temperatures.values_mut().for_each(|temperature| *temperature = ambient);
+    }
+
+    /** Returns the initial condition of a capacitor or an inductor, 0 if it does not have any. */
+    fn initial_condition(&self, component_id: ComponentID) -> f64 {
+        self.circuit.initial_conditions
+            .iter()
+            .find(|initial_condition| initial_condition.component == component_id)
+            .map_or(0.0, |initial_condition| initial_condition.value)
+    }
+
+    /**
+    Solves the DC operating point and returns the circuit linearized there: the nonlinear
+    components are replaced by their tangent, the small-signal conductances and
+    transconductances, which is the circuit the AC analyses (`ac`, `s_parameters`, ...) solve.
+    */
+    pub fn linearize(&mut self) -> Linearization {
+        self.set_time_step(None);
+        let unknowns = self.solve();
+
+        let devices = (0..self.circuit.components.len())
+            .map(ComponentID)
+            .filter_map(|component_id| match &self.circuit.components[component_id.0] {
+                Components::Diode { .. } | Components::Zener { .. } | Components::Led { .. }
+                | Components::TableResistor { .. } | Components::Tvs { .. } | Components::IdealDiode { .. }
+                | Components::SparkGap { .. } => {
+                    Some((component_id, SmallSignal::Bipole { conductance: self.linearize_bipole(component_id).conductance }))
+                }
+                Components::Bjt { .. } | Components::Mosfet { .. } | Components::Jfet { .. } | Components::Triode(_)
+                | Components::Optocoupler { .. } => {
+                    Some((component_id, SmallSignal::Terminals { conductances: self.linearize_terminals(component_id).conductances }))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Linearization { unknowns, matrix: self.get_matrix(), devices }
+    }
+
+    /**
+    Runs a small-signal AC analysis at the given frequency in Hz, around the DC operating point, and
+    returns the complex unknowns (X), readable with `terminal_voltage` and `branch_intensity`.
+
+    The system is the DC one linearized at the operating point, G (see `linearize`), plus the reactive parts:
+    M = G + jwC for capacitors, jwL and jwM for inductors and H(jw) - H(0) for transfer functions.
+    The op-amps have their single pole, gain / (1 + jw / wp), unless their output is clipped, the
+    relay coils and motor armatures are R + jwL, the supercapacitors ESR + 1 / jwC beside their
+    leakage, the magnetizing inductance of the saturating transformers is its value at the
+    operating point and the transmission lines delay their waves by e^(-jw * delay).
+    The sine generators are the AC sources, with the phasor amplitude * e^(j * phase) (the sine
+    being the reference), the other independent generators being set to 0.
+    */
+    pub fn ac(&mut self, frequency: f64) -> DVector<Complex<f64>> {
+        self.set_time_step(None);
+        self.solve();
+        self.ac_at_operating_point(frequency)
+    }
+
+    /**
+    Runs a small-signal AC analysis at every frequency of the sweep, see `ac`. The operating point
+    is only solved once.
+    */
+    pub fn ac_sweep(&mut self, sweep: &FrequencySweep) -> AcSweep {
+        self.set_time_step(None);
+        self.solve();
+
+        let frequencies = sweep.frequencies();
+        let unknowns = frequencies.iter().map(|frequency| self.ac_at_operating_point(*frequency)).collect();
+        AcSweep { frequencies, unknowns }
+    }
+
+    /**
+    Returns the Bode plot of the transfer function from the voltage of the node of the input
+    terminal to the voltage of the node of the output terminal, over an AC sweep.
+    */
+    pub fn bode(&self, sweep: &AcSweep, output: TerminalID, input: TerminalID) -> Bode {
+        let response = sweep.response(|unknowns| self.terminal_voltage(unknowns, output) / self.terminal_voltage(unknowns, input));
+        Bode::new(sweep.frequencies.clone(), &response)
+    }
+
+    /**
+    Returns the small-signal impedance between the nodes of two terminals over a frequency sweep,
+    around the DC operating point like `ac`: a 1 A AC probe is injected into the node of a and out
+    of the node of b, the independent generators being set to 0, and Z is the voltage across them.
+    */
+    pub fn impedance(&mut self, a: TerminalID, b: TerminalID, sweep: &FrequencySweep) -> ImpedanceSweep {
+        self.set_time_step(None);
+        self.solve();
+
+        let mut probe = DVector::zeros(self.n);
+        if let Some(row) = self.get_node_id_from_terminal_id(&a).checked_sub(1) {
+            probe[row] += Complex::new(1.0, 0.0);
+        }
+        if let Some(row) = self.get_node_id_from_terminal_id(&b).checked_sub(1) {
+            probe[row] -= Complex::new(1.0, 0.0);
+        }
+
+        let frequencies = sweep.frequencies();
+        let impedances = frequencies
+            .iter()
+            .map(|frequency| {
+                let (matrix, _) = self.ac_system(*frequency);
+                let unknowns = matrix.lu().solve(&probe).expect("The AC system is singular");
+                self.terminal_voltage(&unknowns, a) - self.terminal_voltage(&unknowns, b)
+            })
+            .collect();
+
+        ImpedanceSweep { frequencies, impedances }
+    }
+
+    /**
+    Returns the loop gain of a feedback loop over a frequency sweep, around the DC operating point
+    like `ac`, by the double injection of Middlebrook (the Tian method of SPICE simulators gives
+    the same result): it accounts for the loading of the loop on both sides of the break.
+
+    The loop is broken at a probe, a voltage generator (usually of 0 V) inserted in the loop with
+    its terminal 1 on the side of the forward path and its terminal 0 on the side of the return.
+    An AC voltage is injected by the probe, giving T_v = -v_0 / v_1, then an AC current into the
+    node of its terminal 0, giving T_i = i_0 / i_1 from the current entering each side. The loop
+    gain is T = (T_v * T_i - 1) / (T_v + T_i + 2).
+    */
+    pub fn loop_gain(&mut self, probe: ComponentID, sweep: &FrequencySweep) -> LoopGain {
+        if !matches!(self.circuit.components[probe.0], Components::VoltageGenerator(_)) {
+            panic!("The probe of a loop gain analysis must be a voltage generator");
+        }
+        self.set_time_step(None);
+        self.solve();
+
+        let (forward, returned) = (TerminalID::new(probe.0, 1), TerminalID::new(probe.0, 0));
+        let mut voltage_injection = DVector::zeros(self.n);
+        voltage_injection[self.nodes.len() - 1 + self.get_branch_id_from_component_id(&probe)] = Complex::new(1.0, 0.0);
+        let mut current_injection = DVector::zeros(self.n);
+        if let Some(row) = self.get_node_id_from_terminal_id(&returned).checked_sub(1) {
+            current_injection[row] = Complex::new(1.0, 0.0);
+        }
+
+        let frequencies = sweep.frequencies();
+        let gains = frequencies
+            .iter()
+            .map(|frequency| {
+                let (matrix, _) = self.ac_system(*frequency);
+                let lu = matrix.lu();
+
+                let unknowns = lu.solve(&voltage_injection).expect("The AC system is singular");
+                let t_v = -self.terminal_voltage(&unknowns, returned) / self.terminal_voltage(&unknowns, forward);
+
+                // The intensity of the probe enters its terminal 1, so it leaves the forward side.
+                let unknowns = lu.solve(&current_injection).expect("The AC system is singular");
+                let i_1 = -self.branch_intensity(&unknowns, probe).unwrap();
+                let i_0 = Complex::new(1.0, 0.0) - i_1;
+
+                // The formula multiplied by i_1, which is 0 when the forward path does not load the loop.
+                (t_v * i_0 - i_1) / (t_v * i_1 + i_0 + i_1 * 2.0)
+            })
+            .collect();
+
+        LoopGain { frequencies, gains }
+    }
+
+    /**
+    Runs an S-parameter analysis of the ports over a frequency sweep, around the DC operating point
+    like `ac`, the independent generators being set to 0.
+
+    Every port is terminated by its reference impedance, and each port in turn is driven by a
+    generator of 1 V behind its reference impedance: the incident wave is then a_j = 1 / (2 sqrt(Z_j))
+    and the wave leaving the port k is b_k = (2 V_k - delta_kj) / (2 sqrt(Z_k)).
+    */
+    pub fn s_parameters(&mut self, ports: &[Port], sweep: &FrequencySweep) -> SParameters {
+        if ports.iter().any(|port| port.impedance <= 0.0 || port.impedance.is_nan()) {
+            panic!("The reference impedance of a port must be positive");
+        }
+        self.set_time_step(None);
+        self.solve();
+
+        let node_column = |terminal_id: &TerminalID| self.get_node_id_from_terminal_id(terminal_id).checked_sub(1);
+        let frequencies = sweep.frequencies();
+        let matrices = frequencies
+            .iter()
+            .map(|frequency| {
+                let (mut matrix, _) = self.ac_system(*frequency);
+                for port in ports {
+                    let admittance = Complex::new(1.0 / port.impedance, 0.0);
+                    let (positive, negative) = (node_column(&port.positive), node_column(&port.negative));
+                    for (row, sign) in [(positive, 1.0), (negative, -1.0)] {
+                        let Some(row) = row else { continue };
+                        for (column, side) in [(positive, 1.0), (negative, -1.0)] {
+                            if let Some(column) = column {
+                                matrix[(row, column)] += admittance * sign * side;
+                            }
+                        }
+                    }
+                }
+
+                let lu = matrix.lu();
+                let solutions: Vec<DVector<Complex<f64>>> = ports
+                    .iter()
+                    .map(|port| {
+                        // The generator of 1 V behind Z_j is a current source of 1 / Z_j.
+                        let mut result = DVector::zeros(self.n);
+                        let source = Complex::new(1.0 / port.impedance, 0.0);
+                        if let Some(row) = node_column(&port.positive) {
+                            result[row] += source;
+                        }
+                        if let Some(row) = node_column(&port.negative) {
+                            result[row] -= source;
+                        }
+                        lu.solve(&result).expect("The AC system is singular")
+                    })
+                    .collect();
+
+                DMatrix::from_fn(ports.len(), ports.len(), |k, j| {
+                    let v = self.terminal_voltage(&solutions[j], ports[k].positive) - self.terminal_voltage(&solutions[j], ports[k].negative);
+                    let delta = if k == j { 1.0 } else { 0.0 };
+                    (v * 2.0 - delta) * (ports[j].impedance / ports[k].impedance).sqrt()
+                })
+            })
+            .collect();
+
+        SParameters { frequencies, matrices }
+    }
+
+    /** Solves the AC system at a frequency, the guess being the operating point. */
+    fn ac_at_operating_point(&self, frequency: f64) -> DVector<Complex<f64>> {
+        let (matrix, result) = self.ac_system(frequency);
+        matrix.lu().solve(&result).expect("The AC system is singular")
+    }
+
+    /** Returns the matrix and the result of the AC system at a frequency, see `ac`. */
+    fn ac_system(&self, frequency: f64) -> (DMatrix<Complex<f64>>, DVector<Complex<f64>>) {
+        let ac = AngularFrequency { w: 2.0 * std::f64::consts::PI * frequency, j: Complex::new(0.0, 1.0) };
+        // The custom components only stamp real equations.
+        let mut custom = DMatrix::zeros(self.n, self.n);
+        self.stamp_custom_components(&mut custom, &mut DVector::zeros(self.n));
+        let matrix = self.assemble_matrix(Some(&ac)) + custom.map(Complex::from);
+        let mut result = DVector::zeros(self.n);
+
+        for (component_id, component) in self.circuit.components.iter().enumerate() {
+            if let Components::SineGenerator { amplitude, phase, .. } = component {
+                let row = self.nodes.len() - 1 + self.get_branch_id_from_component_id(&ComponentID(component_id));
+                result[row] = Complex::from_polar(*amplitude, *phase);
+            }
+        }
+
+        (matrix, result)
+    }
+
+    /** Reads the voltage of the node a terminal is connected to from the unknowns (X). */
+    pub fn terminal_voltage<T: ComplexField>(&self, unknowns: &DVector<T>, terminal_id: TerminalID) -> T {
+        self.node_voltage(unknowns, self.get_node_id_from_terminal_id(&terminal_id))
+    }
+
+    /**
+    Reads the branch intensity of a component from the unknowns (X), the first one if it has
+    several, or None if it does not have any.
+    */
+    pub fn branch_intensity<T: ComplexField>(&self, unknowns: &DVector<T>, component_id: ComponentID) -> Option<T> {
+        let branch_id = self.component_id_to_branch_id.get(&component_id)?;
+        Some(unknowns[self.nodes.len() - 1 + branch_id].clone())
+    }
+
+    /**
+    Solves the circuit for each of the given wiper positions of a potentiometer, and returns the
+    unknowns (X) of every solve. The potentiometer is left at the last position.
+    */
+    pub fn sweep_potentiometer(&mut self, component_id: ComponentID, positions: &[f64]) -> Vec<DVector<f64>> {
+        positions
+            .iter()
+            .map(|new_position| {
+                match &mut self.circuit.components[component_id.0] {
+                    Components::Potentiometer { position, .. } => *position = *new_position,
+                    _ => panic!("Potentiometer expected"),
+                }
+                self.solve()
+            })
+            .collect()
+    }
+
+    /**
+    Runs a small-signal DC transfer function analysis from a voltage or current generator to the
+    voltage of the node of the output terminal, around the DC operating point: the gain, the
+    resistance seen by the source and the resistance seen from the output node to the ground, the
+    independent generators being set to 0.
+    */
+    pub fn small_signal_transfer(&mut self, source: ComponentID, output: TerminalID) -> SmallSignalTransfer {
+        let output_node_id = self.get_node_id_from_terminal_id(&output);
+        if output_node_id == 0 {
+            panic!("The output must not be the ground");
+        }
+
+        self.set_time_step(None);
+        self.solve();
+        let lu = self.get_matrix().lu();
+        let node_row = |terminal_id: TerminalID| self.get_node_id_from_terminal_id(&terminal_id).checked_sub(1);
+
+        // Response to a unit source, the other generators being 0.
+        let mut excitation = DVector::zeros(self.n);
+        match &self.circuit.components[source.0] {
+            Components::VoltageGenerator(_) => excitation[self.nodes.len() - 1 + self.get_branch_id_from_component_id(&source)] = 1.0,
+            Components::CurrentGenerator(_) => {
+                // The intensity leaves the generator by the terminal 1.
+                if let Some(row) = node_row(TerminalID::new(source.0, 1)) {
+                    excitation[row] += 1.0;
+                }
+                if let Some(row) = node_row(TerminalID::new(source.0, 0)) {
+                    excitation[row] -= 1.0;
+                }
+            }
+            _ => panic!("The source must be a voltage or current generator"),
+        }
+        let response = lu.solve(&excitation).expect("The circuit is singular");
+        let gain = self.node_voltage(&response, output_node_id);
+        let input_resistance = match &self.circuit.components[source.0] {
+            // The branch intensity enters the terminal 1, the source delivers its opposite.
+            Components::VoltageGenerator(_) => -1.0 / self.branch_intensity(&response, source).unwrap(),
+            _ => self.bipole_voltage(&response, &TerminalID::new(source.0, 1)),
+        };
+
+        let output_resistance = self.resistance_between(&lu, output_node_id, 0);
+
+        SmallSignalTransfer { gain, input_resistance, output_resistance }
+    }
+
+    /**
+    Derives the transfer function H(s) from a source (a voltage, sine or current generator) to the
+    voltage of the node of the output terminal symbolically, as a ratio of polynomials of s whose
+    coefficients are products of the component values: R1, C2, L3, E4 (gain of a VCVS) and G5
+    (transconductance of a VCCS), the number being the component ID. The other independent
+    generators are set to 0.
+
+    The MNA equations are solved by Cramer's rule with determinants expanded symbolically, so it is
+    meant for small linear circuits, e.g. to check a filter design. Panics on other components.
+    */
+    pub fn symbolic_transfer(&self, source: ComponentID, output: TerminalID) -> SymbolicTransfer {
+        let output_row = self.get_node_id_from_terminal_id(&output).checked_sub(1).expect("The output must not be the ground");
+        if !matches!(self.circuit.components[source.0], Components::VoltageGenerator(_) | Components::SineGenerator { .. } | Components::CurrentGenerator(_)) {
+            panic!("The source must be a voltage, sine or current generator");
+        }
+
+        // The symbol 0 is s, the resistors being conductances until the end.
+        let mut symbols = Vec::new();
+        let mut values = Vec::new();
+        let mut conductances = Vec::new();
+        let mut symbol = |name: String, value: f64| {
+            symbols.push(name);
+            values.push(value);
+            symbols.len()
+        };
+        let s = Polynomial::symbol(0);
+        let one = Polynomial::constant(1.0);
+
+        let mut matrix = vec![vec![Polynomial::zero(); self.n]; self.n];
+        let mut result = vec![0.0; self.n];
+        let node_row = |component_id: ComponentID, idx: usize| self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx)).checked_sub(1);
+        for (component_id, component) in self.circuit.components.iter().enumerate() {
+            let component_id = ComponentID(component_id);
+            let (n0, n1) = (node_row(component_id, 0), if component.terminal_count() > 1 { node_row(component_id, 1) } else { None });
+            let branch_row = || Some(self.nodes.len() - 1 + self.get_branch_id_from_component_id(&component_id));
+
+            match component {
+                Components::Ground | Components::Wire => {}
+                Components::Resistor(resistance) => {
+                    let conductance = symbol(format!("R{}", component_id.0), *resistance);
+                    conductances.push(conductance);
+                    symbolic::stamp_admittance(&mut matrix, n0, n1, &Polynomial::symbol(conductance));
+                }
+                Components::Capacitor(capacitance) => {
+                    let capacitance = Polynomial::symbol(symbol(format!("C{}", component_id.0), *capacitance));
+                    symbolic::stamp_admittance(&mut matrix, n0, n1, &capacitance.multiply(&s));
+                }
+                Components::Inductor(inductance) => {
+                    // The intensity enters the terminal 0: V0 - V1 - sL * i = 0.
+                    let inductance = Polynomial::symbol(symbol(format!("L{}", component_id.0), *inductance));
+                    let row = branch_row();
+                    symbolic::stamp(&mut matrix, n0, row, &one, 1.0);
+                    symbolic::stamp(&mut matrix, n1, row, &one, -1.0);
+                    symbolic::stamp(&mut matrix, row, n0, &one, 1.0);
+                    symbolic::stamp(&mut matrix, row, n1, &one, -1.0);
+                    symbolic::stamp(&mut matrix, row, row, &inductance.multiply(&s), -1.0);
+                }
+                Components::VoltageGenerator(_) | Components::SineGenerator { .. } | Components::Vcvs { .. } => {
+                    // The intensity enters the terminal 1: V1 - V0 = value.
+                    let row = branch_row();
+                    symbolic::stamp(&mut matrix, n1, row, &one, 1.0);
+                    symbolic::stamp(&mut matrix, n0, row, &one, -1.0);
+                    symbolic::stamp(&mut matrix, row, n1, &one, 1.0);
+                    symbolic::stamp(&mut matrix, row, n0, &one, -1.0);
+                    if let Components::Vcvs { gain } = component {
+                        let gain = Polynomial::symbol(symbol(format!("E{}", component_id.0), *gain));
+                        symbolic::stamp(&mut matrix, row, node_row(component_id, 3), &gain, -1.0);
+                        symbolic::stamp(&mut matrix, row, node_row(component_id, 2), &gain, 1.0);
+                    }
+                    if component_id == source {
+                        result[row.unwrap()] = 1.0;
+                    }
+                }
+                Components::CurrentGenerator(_) => {
+                    // The intensity leaves the node of the terminal 0 into the node of the terminal 1.
+                    if component_id == source {
+                        if let Some(row) = n1 {
+                            result[row] += 1.0;
+                        }
+                        if let Some(row) = n0 {
+                            result[row] -= 1.0;
+                        }
+                    }
+                }
+                Components::Vccs { transconductance } => {
+                    // gm * (V3 - V2) leaves the node of the terminal 1 into the node of the terminal 0.
+                    let transconductance = Polynomial::symbol(symbol(format!("G{}", component_id.0), *transconductance));
+                    let (n2, n3) = (node_row(component_id, 2), node_row(component_id, 3));
+                    symbolic::stamp(&mut matrix, n1, n3, &transconductance, 1.0);
+                    symbolic::stamp(&mut matrix, n1, n2, &transconductance, -1.0);
+                    symbolic::stamp(&mut matrix, n0, n3, &transconductance, -1.0);
+                    symbolic::stamp(&mut matrix, n0, n2, &transconductance, 1.0);
+                }
+                _ => panic!("The component {} is not supported by the symbolic analysis", component_id.0),
+            }
+        }
+
+        // Cramer's rule: X_j = det(M_j) / det(M), M_j being M with its column j replaced by Y.
+        let all: Vec<usize> = (0..self.n).collect();
+        let without = |k: usize| -> Vec<usize> { all.iter().copied().filter(|i| *i != k).collect() };
+        let numerator = result
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0.0)
+            .fold(Polynomial::zero(), |sum, (row, value)| {
+                let sign = if (row + output_row).is_multiple_of(2) { 1.0 } else { -1.0 };
+                sum.add(&symbolic::determinant(&matrix, &without(row), &without(output_row)).scale(sign * value))
+            });
+        let denominator = symbolic::determinant(&matrix, &all, &all);
+        if denominator.is_zero() {
+            panic!("The circuit is singular");
+        }
+
+        SymbolicTransfer::new(symbols, values, &conductances, numerator, denominator)
+    }
+
+    /**
+    Returns the Thévenin equivalent of the circuit seen between the nodes of two terminals: the
+    open-circuit voltage V(a) - V(b) at the DC operating point, and the resistance between the
+    nodes around it, the independent generators being set to 0.
+    */
+    pub fn thevenin(&mut self, a: TerminalID, b: TerminalID) -> Thevenin {
+        let (node_a, node_b) = (self.get_node_id_from_terminal_id(&a), self.get_node_id_from_terminal_id(&b));
+        if node_a == node_b {
+            panic!("The terminals must be on different nodes");
+        }
+
+        self.set_time_step(None);
+        let unknowns = self.solve();
+        let voltage = self.node_voltage(&unknowns, node_a) - self.node_voltage(&unknowns, node_b);
+        Thevenin { voltage, resistance: self.equivalent_resistance(a, b) }
+    }
+
+    /**
+    Returns the load between the nodes of two terminals that draws the most power from the
+    circuit, from its Thévenin equivalent around the DC operating point: at DC (a frequency of 0)
+    a resistance equal to the Thévenin resistance, otherwise the complex conjugate of the
+    impedance seen between the nodes, the AC sources being the sine generators like in `ac`.
+    */
+    pub fn max_power_transfer(&mut self, a: TerminalID, b: TerminalID, frequency: f64) -> PowerTransfer {
+        let (voltage, source_impedance) = if frequency == 0.0 {
+            let thevenin = self.thevenin(a, b);
+            (Complex::new(thevenin.voltage, 0.0), Complex::new(thevenin.resistance, 0.0))
+        } else {
+            let unknowns = self.ac(frequency);
+            let voltage = self.terminal_voltage(&unknowns, a) - self.terminal_voltage(&unknowns, b);
+            let sweep = FrequencySweep::Linear { start: frequency, stop: frequency, points: 1 };
+            (voltage, self.impedance(a, b, &sweep).impedances[0])
+        };
+        if source_impedance.re <= 0.0 {
+            panic!("The source must have a positive resistance to transfer a finite power");
+        }
+
+        let mut transfer = PowerTransfer { frequency, voltage, source_impedance, load_impedance: source_impedance.conj(), power: 0.0 };
+        transfer.power = transfer.load_power(transfer.load_impedance);
+        transfer
+    }
+
+    /**
+    Returns the equivalent resistance between the nodes of two terminals, the independent
+    generators being set to 0: voltage generators are short circuits and current generators open
+    circuits. Nonlinear components are linearized around the DC operating point.
+    */
+    pub fn equivalent_resistance(&mut self, a: TerminalID, b: TerminalID) -> f64 {
+        let (node_a, node_b) = (self.get_node_id_from_terminal_id(&a), self.get_node_id_from_terminal_id(&b));
+        if node_a == node_b {
+            return 0.0;
+        }
+
+        self.set_time_step(None);
+        self.solve();
+        self.resistance_between(&self.get_matrix().lu(), node_a, node_b)
+    }
+
+    /**
+    Returns the voltage between two nodes for a unit intensity injected into a and drawn from b,
+    with the linearized matrix of the circuit, whose generators are then set to 0.
+    */
+    fn resistance_between(&self, lu: &LU<f64, Dyn, Dyn>, node_a: usize, node_b: usize) -> f64 {
+        // The reference node (0) has no row, the row of the node i is i - 1.
+        let mut injection = DVector::zeros(self.n);
+        if let Some(row) = node_a.checked_sub(1) {
+            injection[row] += 1.0;
+        }
+        if let Some(row) = node_b.checked_sub(1) {
+            injection[row] -= 1.0;
+        }
+
+        let response = lu.solve(&injection).expect("The circuit is singular");
+        self.node_voltage(&response, node_a) - self.node_voltage(&response, node_b)
+    }
+
+    /**
+    Solves the DC operating point for each value of a voltage or current generator, like the `.dc`
+    analysis of SPICE. Each solve starts from the previous one, and the generator is set back to
+    its value afterwards.
+    */
+    pub fn dc_sweep(&mut self, component_id: ComponentID, range: &SweepRange) -> DcSweep {
+        if !matches!(self.circuit.components[component_id.0], Components::VoltageGenerator(_) | Components::CurrentGenerator(_)) {
+            panic!("Only voltage and current generators can be swept");
+        }
+
+        self.set_time_step(None);
+        let nominal = self.circuit.components[component_id.0].value().unwrap();
+        let values = range.values();
+        let unknowns = values
+            .iter()
+            .map(|value| {
+                *self.circuit.components[component_id.0].value_mut().unwrap() = *value;
+                self.solve()
+            })
+            .collect();
+
+        *self.circuit.components[component_id.0].value_mut().unwrap() = nominal;
+        DcSweep { values, unknowns }
+    }
+
+    /**
+    Traces the I–V characteristic of a two-terminal component (diode, varistor, lamp, custom
+    model...) like a curve tracer: the component alone is connected to a voltage generator swept
+    over the range, V0 - V1 being the swept value, and the intensity through it is read at each
+    voltage. Each solve starts from the previous one.
+    */
+    pub fn curve_trace(component: Components, range: &SweepRange) -> CurveTrace {
+        if component.terminal_count() != 2 {
+            panic!("Only two-terminal components can be traced");
+        }
+
+        // The generator drives the terminal 0 of the component, the terminal 1 being grounded.
+        let terminal = TerminalID::new;
+        let mut simulator = Simulator::new(Circuit::new(
+            vec![Components::Ground, Components::VoltageGenerator(0.0), component],
+            vec![(terminal(1, 0), terminal(0, 0)), (terminal(1, 1), terminal(2, 0)), (terminal(2, 1), terminal(0, 0))],
+        ));
+        let sweep = simulator.dc_sweep(ComponentID(1), range);
+
+        CurveTrace {
+            voltages: sweep.values,
+            // The intensity of the generator enters its terminal 1, so it leaves the component.
+            currents: sweep.unknowns.iter().map(|unknowns| -simulator.branch_intensity(unknowns, ComponentID(1)).unwrap()).collect(),
+        }
+    }
+
+    /**
+    Runs a fault-injection analysis: the circuit given by build is faulted with each fault in turn
+    and its DC operating point solved, and the outputs are measured and compared to their nominal
+    value, to find which faults they detect. A faulty circuit that does not converge is reported
+    rather than aborting the analysis.
+    */
+    pub fn fault_analysis(build: impl Fn() -> Circuit, injection: &FaultInjection) -> FaultReport {
+        let measure = |circuit: Circuit| {
+            let mut simulator = Simulator::new(circuit);
+            let unknowns = simulator.try_solve()?;
+            Some(injection.outputs.iter().map(|output| simulator.evaluate_expression(&unknowns, output, 0.0)).collect::<Vec<f64>>())
+        };
+
+        let Some(nominal) = measure(build()) else {
+            panic!("The circuit without any fault does not converge");
+        };
+
+        let results = injection.faults
+            .iter()
+            .map(|fault| {
+                let mut circuit = build();
+                fault.apply(&mut circuit);
+                let values = measure(circuit);
+                let deviations = match &values {
+                    Some(values) => (0..values.len()).filter(|k| injection.deviates(nominal[*k], values[*k])).collect(),
+                    None => (0..nominal.len()).collect(),
+                };
+                FaultResult { fault: *fault, values, deviations }
+            })
+            .collect();
+
+        FaultReport { nominal, results }
+    }
+
+    /**
+    Solves the DC operating point at each temperature of the range in °C, which adjusts the
+    resistors with a temperature coefficient, to measure the drift of a bias point. Each solve
+    starts from the previous one, and the temperature is set back afterwards.
+    */
+    pub fn temperature_sweep(&mut self, range: &SweepRange) -> DcSweep {
+        self.set_time_step(None);
+        let nominal = self.temperature;
+        let values = range.values();
+        let unknowns = values
+            .iter()
+            .map(|temperature| {
+                self.set_temperature(*temperature);
+                self.solve()
+            })
+            .collect();
+
+        self.set_temperature(nominal);
+        DcSweep { values, unknowns }
+    }
+
+    /**
+    Runs a DC sweep of a generator for each value of an outer generator, for families of
+    characteristics (e.g. sweeping Vce for each step of Ib). Both generators are set back to their
+    value afterwards.
+    */
+    pub fn dc_sweep_nested(&mut self, component_id: ComponentID, range: &SweepRange, outer_component_id: ComponentID, outer_range: &SweepRange) -> NestedDcSweep {
+        if component_id == outer_component_id {
+            panic!("The inner and outer swept generators must be different");
+        }
+        if !matches!(self.circuit.components[outer_component_id.0], Components::VoltageGenerator(_) | Components::CurrentGenerator(_)) {
+            panic!("Only voltage and current generators can be swept");
+        }
+
+        let nominal = self.circuit.components[outer_component_id.0].value().unwrap();
+        let outer_values = outer_range.values();
+        let sweeps = outer_values
+            .iter()
+            .map(|value| {
+                *self.circuit.components[outer_component_id.0].value_mut().unwrap() = *value;
+                self.dc_sweep(component_id, range)
+            })
+            .collect();
+
+        *self.circuit.components[outer_component_id.0].value_mut().unwrap() = nominal;
+        NestedDcSweep { outer_values, sweeps }
+    }
+
+    /**
+    Reruns an analysis for each value of a component described by a single value (see
+    `Components::value`), like the `.step` command of SPICE, and returns the result of each run.
+    The circuit is only set up once, each run starts from the initial state of the components, and
+    the component is set back to its value afterwards.
+    */
+    pub fn step<T>(&mut self, component_id: ComponentID, values: &[f64], mut analysis: impl FnMut(&mut Simulator) -> T) -> Vec<T> {
+        let Some(nominal) = self.circuit.components[component_id.0].value() else {
+            panic!("Only a component with a single value can be stepped");
+        };
+
+        let results = values
+            .iter()
+            .map(|value| {
+                *self.circuit.components[component_id.0].value_mut().unwrap() = *value;
+                self.reset_states();
+                analysis(self)
+            })
+            .collect();
+
+        *self.circuit.components[component_id.0].value_mut().unwrap() = nominal;
+        results
+    }
+
+    /**
+    Runs an analysis for each trial of a Monte Carlo analysis, the values of the components with a
+    tolerance being drawn from their tolerance range for each trial. Each trial starts from the
+    initial state of the components, and they are set back to their nominal value afterwards.
+    */
+    pub fn monte_carlo<T>(&mut self, monte_carlo: &MonteCarlo, mut analysis: impl FnMut(&mut Simulator) -> T) -> MonteCarloResult<T> {
+        if let ToleranceDistribution::Gaussian { sigmas } = monte_carlo.distribution {
+            if sigmas <= 0.0 {
+                panic!("The tolerance must be a positive number of standard deviations");
+            }
+        }
+
+        let tolerances: Vec<(ComponentID, f64)> = self.circuit.tolerances
+            .iter()
+            .map(|tolerance| (tolerance.component, tolerance.relative))
+            .collect();
+        let nominals: Vec<f64> = tolerances
+            .iter()
+            .map(|(component_id, _)| self.circuit.components[component_id.0].value().unwrap())
+            .collect();
+
+        let trials = (0..monte_carlo.trials)
+            .map(|trial| {
+                for (k, ((component_id, relative), nominal)) in tolerances.iter().zip(&nominals).enumerate() {
+                    let deviation = monte_carlo.deviation(trial, k, tolerances.len(), *relative);
+                    *self.circuit.components[component_id.0].value_mut().unwrap() = nominal * (1.0 + deviation);
+                }
+                self.reset_states();
+                analysis(self)
+            })
+            .collect();
+
+        for ((component_id, _), nominal) in tolerances.iter().zip(&nominals) {
+            *self.circuit.components[component_id.0].value_mut().unwrap() = *nominal;
+        }
+        MonteCarloResult { trials }
+    }
+
+    /**
+    Adjusts the values of components within their bounds to minimize an error computed by an
+    analysis of the circuit, e.g. the distance of a Bode plot to a target response or of a DC
+    voltage to a setpoint, see `optimization::rms_error`. The search starts from the current
+    values, each evaluation starts from the initial state of the components, and the components
+    are left at the best values found.
+    */
+    pub fn optimize(&mut self, optimization: &Optimization, mut error: impl FnMut(&mut Simulator) -> f64) -> OptimizationResult {
+        for parameter in &optimization.parameters {
+            if self.circuit.components[parameter.component.0].value().is_none() {
+                panic!("Only a component with a single value can be optimized");
+            }
+            if parameter.min >= parameter.max {
+                panic!("The minimum of a parameter must be below its maximum");
+            }
+        }
+
+        let start = optimization.parameters
+            .iter()
+            .map(|parameter| parameter.position(self.circuit.components[parameter.component.0].value().unwrap()))
+            .collect();
+        let set = |simulator: &mut Simulator, point: &[f64]| {
+            for (parameter, u) in optimization.parameters.iter().zip(point) {
+                *simulator.circuit.components[parameter.component.0].value_mut().unwrap() = parameter.value(*u);
+            }
+        };
+
+        let (best, error, iterations, converged) = optimization::nelder_mead(optimization, start, |point| {
+            set(self, point);
+            self.reset_states();
+            error(self)
+        });
+        set(self, &best);
+
+        let values = optimization.parameters.iter().zip(&best).map(|(parameter, u)| parameter.value(*u)).collect();
+        OptimizationResult { values, error, iterations, converged }
+    }
+
+    /** Returns the intensity through the current probe with the given label, if there is one. */
+    pub fn probe_current(&self, unknowns: &DVector<f64>, label: &str) -> Option<f64> {
+        let component_id = self.circuit.components
+            .iter()
+            .position(|component| matches!(component, Components::CurrentProbe { label: probe_label } if probe_label == label))?;
+
+        let branch_id = self.get_branch_id_from_component_id(&ComponentID(component_id));
+        Some(unknowns[self.nodes.len() - 1 + branch_id])
+    }
+
+    /**
+    Returns the reading of the voltmeter or ammeter with the given label, if there is one: V1 - V0
+    for a voltmeter and the intensity entering terminal 1 for an ammeter.
+    */
+    pub fn meter_reading(&self, unknowns: &DVector<f64>, label: &str) -> Option<f64> {
+        let component_id = self.circuit.components
+            .iter()
+            .position(|component| match component {
+                Components::Voltmeter { label: meter_label, .. } | Components::Ammeter { label: meter_label, .. } => meter_label == label,
+                _ => false,
+            })?;
+
+        // The bipole voltage seen from the terminal 1 is V1 - V0.
+        let v = self.bipole_voltage(unknowns, &TerminalID::new(component_id, 1));
+        match &self.circuit.components[component_id] {
+            Components::Ammeter { burden_resistance, .. } => Some(v / burden_resistance),
+            _ => Some(v),
+        }
+    }
+
+    /** Returns the time the circuit is solved at, a DC solve happening at t = 0. */
+    fn time(&self) -> f64 {
+        self.time_step.as_ref().map_or(0.0, |time_step| time_step.time)
+    }
+
+    /**
+    Solves the circuit with the Newton–Raphson method and returns the unknowns (X).
+
+    Each iteration linearizes the nonlinear components around the previous solution. A linear
+    circuit converges on the second iteration. The step towards the solution of the linearized
+    circuit is damped and limited by the `NewtonOptions`, the iteration being converged once that
+    step is within the tolerances.
+
+    If the iteration does not converge, it is retried with Gmin stepping, then with source
+    stepping, see `Convergence`. The strategy that succeeded is given by `convergence`.
+    */
+    pub fn solve(&mut self) -> DVector<f64> {
+        match self.try_solve() {
+            Some(unknowns) => unknowns,
+            None => panic!("Newton–Raphson did not converge after {} iterations, even with Gmin and source stepping", self.newton.max_iterations),
+        }
+    }
+
+    /** Solves the circuit like `solve`, None if it does not converge even with Gmin and source stepping. */
+    fn try_solve(&mut self) -> Option<DVector<f64>> {
+        let guess = self.guess.clone();
+        if let Some(unknowns) = self.newton() {
+            self.convergence = Convergence::Newton;
+            return Some(unknowns);
+        }
+
+        self.guess = guess.clone();
+        if let Some(unknowns) = self.gmin_stepping() {
+            self.convergence = Convergence::GminStepping;
+            return Some(unknowns);
+        }
+
+        self.guess = guess;
+        let unknowns = self.source_stepping()?;
+        self.convergence = Convergence::SourceStepping;
+        Some(unknowns)
+    }
+
+    /** Returns how the last solve converged. */
+    pub fn convergence(&self) -> Convergence {
+        self.convergence
+    }
+
+    /** Runs the Newton–Raphson iteration from the guess, None if it does not converge. */
+    fn newton(&mut self) -> Option<DVector<f64>> {
+        for _ in 0..self.newton.max_iterations {
+            let (mat, result) = self.get_system();
+
+            let unknowns = mat.lu().solve(&result)?;
+            if unknowns.iter().any(|x| !x.is_finite()) {
+                return None;
+            }
+            let converged = unknowns.iter()
+                .zip(self.guess.iter())
+                .all(|(new, old)| (new - old).abs() <= ABSOLUTE_TOLERANCE + RELATIVE_TOLERANCE * new.abs());
+
+            if converged {
+                self.guess = unknowns;
+                return Some(self.guess.clone());
+            }
+
+            let NewtonOptions { damping, max_step, .. } = self.newton;
+            self.guess = self.guess.zip_map(&unknowns, |old, new| old + ((new - old) * damping).clamp(-max_step, max_step));
+        }
+
+        None
+    }
+
+    /**
+    Solves the circuit with a large conductance from every node to the ground, which makes it
+    nearly linear, then divides it step by step down to 0, each solve starting from the previous
+    solution. A solve that does not converge is retried with a smaller division from the last
+    converged one. Returns None if the first solve does not converge or the division gets too small.
+    */
+    fn gmin_stepping(&mut self) -> Option<DVector<f64>> {
+        let next = |gmin: f64, factor: f64| if gmin / factor < GMIN_SMALLEST { 0.0 } else { gmin / factor };
+
+        let mut gmin = GMIN_START;
+        let mut factor = GMIN_FACTOR;
+        let mut converged: Option<(f64, DVector<f64>)> = None;
+        let unknowns = loop {
+            self.gmin = gmin;
+            match (self.newton(), &converged) {
+                (Some(unknowns), _) if gmin == 0.0 => break Some(unknowns),
+                (Some(unknowns), _) => {
+                    converged = Some((gmin, unknowns));
+                    gmin = next(gmin, factor);
+                }
+                (None, Some((last_gmin, last_unknowns))) if factor > GMIN_SMALLEST_FACTOR => {
+                    factor = factor.sqrt();
+                    self.guess = last_unknowns.clone();
+                    gmin = next(*last_gmin, factor);
+                }
+                (None, _) => break None,
+            }
+        };
+
+        self.gmin = 0.0;
+        unknowns
+    }
+
+    /**
+    Solves the circuit with the independent generators at 0, then ramps them up to their actual
+    value, each solve starting from the previous solution. A solve that does not converge is
+    retried with a smaller increment from the last converged one. Returns None if the first solve
+    does not converge or the increment gets too small.
+    */
+    fn source_stepping(&mut self) -> Option<DVector<f64>> {
+        let mut scale = 0.0;
+        let mut step = SOURCE_STEP;
+        let mut converged: Option<(f64, DVector<f64>)> = None;
+        let unknowns = loop {
+            self.source_scale = scale;
+            match (self.newton(), &converged) {
+                (Some(unknowns), _) if scale == 1.0 => break Some(unknowns),
+                (Some(unknowns), _) => {
+                    converged = Some((scale, unknowns));
+                    scale = (scale + step).min(1.0);
+                }
+                (None, Some((last_scale, last_unknowns))) if step > SOURCE_SMALLEST_STEP => {
+                    step /= 2.0;
+                    self.guess = last_unknowns.clone();
+                    scale = (last_scale + step).min(1.0);
+                }
+                (None, _) => break None,
+            }
+        };
+
+        self.source_scale = 1.0;
+        unknowns
+    }
+
+    /**
+    Solves the DC operating point (or the current time step) and returns it as data: the node
+    voltages, the branch intensities and a reading of each component. See the `report` module to
+    print it.
+    */
+    pub fn operating_point(&mut self) -> OperatingPoint {
+        let unknowns = self.solve();
+
+        let node_voltages = (0..self.nodes.len()).map(|node_id| self.node_voltage(&unknowns, node_id)).collect();
+        let branch_intensities = self.branches
+            .iter()
+            .enumerate()
+            .map(|(branch_id, component_id)| (*component_id, unknowns[self.nodes.len() - 1 + branch_id]))
+            .collect();
+
+        // The parts of composite components are not reported, only the composite ones.
+        let readings = (0..self.component_count)
+            .map(|component_id| self.component_reading(&unknowns, component_id))
+            .collect();
+
+        OperatingPoint { unknowns, nodes: self.nodes.clone(), node_voltages, branch_intensities, readings }
+    }
+
+    /**
+    Checks the ratings of the components against the solved unknowns (X), of the DC operating
+    point or of any time step, and returns the violations, from the smallest margin. The current
+    of a composite or custom component is not checked, it is not known at its terminals.
+    */
+    pub fn design_rule_check(&self, unknowns: &DVector<f64>) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for rating in &self.circuit.ratings {
+            let component_id = rating.component;
+            let component = &self.circuit.components[component_id.0];
+            let terminals = (0..component.terminal_count()).map(|idx| TerminalID::new(component_id.0, idx));
+
+            let voltages: Vec<f64> = terminals.clone().map(|terminal_id| self.terminal_voltage(unknowns, terminal_id)).collect();
+            let voltage = voltages.iter().copied().fold(f64::NEG_INFINITY, f64::max) - voltages.iter().copied().fold(f64::INFINITY, f64::min);
+            let primitive = !matches!(component, Components::Custom(_)) && !self.parents.values().any(|parent| *parent == component_id);
+            let current = primitive.then(|| {
+                terminals
+                    .map(|terminal_id| (self.get_component_intensity_vector(terminal_id, None).dot(unknowns) + self.get_component_intensity_constant(terminal_id)).abs())
+                    .fold(0.0, f64::max)
+            });
+
+            let checks = [
+                (RatedQuantity::Power, rating.max_power, self.power(unknowns, component_id).map(f64::abs)),
+                (RatedQuantity::Voltage, rating.max_voltage, Some(voltage)),
+                (RatedQuantity::Current, rating.max_current, current),
+            ];
+            for (quantity, max, value) in checks {
+                if let (Some(rating), Some(value)) = (max, value) {
+                    if value > rating {
+                        let kind = self.component_reading(unknowns, component_id.0).kind;
+                        violations.push(Violation { component_id, kind, quantity, value, rating });
+                    }
+                }
+            }
+        }
+
+        violations.sort_by(|left, right| left.margin().total_cmp(&right.margin()));
+        violations
+    }
+
+    /** Returns the power absorbed by each component of the circuit, see `power`. */
+    fn powers(&self, unknowns: &DVector<f64>) -> Vec<Option<f64>> {
+        (0..self.component_count).map(|component_id| self.power(unknowns, ComponentID(component_id))).collect()
+    }
+
+    /**
+    Returns the power absorbed by a component from the solved unknowns (X), the sum of the voltage
+    of each terminal times the intensity entering it, or of its parts for a composite component.
+    None if it is or contains a custom component.
+    */
+    fn power(&self, unknowns: &DVector<f64>, component_id: ComponentID) -> Option<f64> {
+        let parts: Vec<ComponentID> = self.parents
+            .keys()
+            .filter(|part_id| self.parents[part_id] == component_id)
+            .copied()
+            .collect();
+        if !parts.is_empty() {
+            return parts.into_iter().map(|part_id| self.power(unknowns, part_id)).sum();
+        }
+
+        let component = &self.circuit.components[component_id.0];
+        if let Components::Custom(_) = component {
+            return None;
+        }
+
+        let power = (0..component.terminal_count())
+            .map(|idx| {
+                let terminal_id = TerminalID::new(component_id.0, idx);
+                let intensity = self.get_component_intensity_vector(terminal_id, None).dot(unknowns) + self.get_component_intensity_constant(terminal_id);
+                self.node_voltage(unknowns, self.get_node_id_from_terminal_id(&terminal_id)) * intensity
+            })
+            .sum();
+        Some(power)
+    }
+
+    /** Returns the reading of a component from the solved unknowns (X). */
+    fn component_reading(&self, unknowns: &DVector<f64>, component_id: usize) -> ComponentReading {
+        let q = |prefix: &'static str, value: f64, unit: &'static str| Quantity { prefix, value, unit };
+        let power = self.power(unknowns, ComponentID(component_id));
+        let reading = |kind: &'static str, detail: Option<String>, quantities: Vec<Quantity>| ComponentReading {
+            component_id: ComponentID(component_id),
+            kind,
+            detail,
+            quantities,
+            power,
+        };
+
+        let component = &self.circuit.components[component_id];
+        if let Components::Custom(custom) = component {
+            return reading("Custom component", Some(format!("{:?}", custom)), vec![]);
+        }
+        if component.terminal_count() < 2 {
+            // There is no voltage across a single terminal.
+            return reading("Ground", None, vec![]);
+        }
+
+        let input_terminal_id = TerminalID::new(component_id, 0);
+        let output_terminal_id = TerminalID::new(component_id, 1);
+
+        let node_input = self.get_node_id_from_terminal_id(&input_terminal_id);
+        let node_output = self.get_node_id_from_terminal_id(&output_terminal_id);
+
+        let v = self.node_voltage(unknowns, node_output) - self.node_voltage(unknowns, node_input);
+
+        match component {
+            Components::Resistor(_) => reading("Resistor", None, vec![q("", v, "V")]),
+            Components::DigitalOutput { signal, .. } => {
+                let level = if self.digital_signal(*signal) { "high" } else { "low" };
+                reading("Digital output", Some(format!("signal {} {}", signal, level)), vec![q("", v, "V")])
+            }
+            Components::DigitalInput { signal, .. } => reading("Digital input", Some(format!("signal {}", signal)), vec![q("", v, "V")]),
+            Components::Relay { .. } => {
+                let state = match self.states[&ComponentID(component_id)] {
+                    ComponentState::Relay { closed: true } => "closed",
+                    _ => "open",
+                };
+                let branch_id = self.get_branch_id_from_component_id(&ComponentID(component_id));
+                reading("Relay", Some(state.to_string()), vec![q("", v, "V"), q("", unknowns[self.nodes.len() - 1 + branch_id], "A")])
+            }
+            Components::Potentiometer { position, .. } => {
+                let node_wiper = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let v_wiper = self.node_voltage(unknowns, node_wiper) - self.node_voltage(unknowns, node_input);
+                reading("Potentiometer", Some(format!("at {}", position)), vec![q("", v, "V"), q("wiper at ", v_wiper, "V")])
+            }
+            Components::DcMotor { .. } => {
+                let ComponentState::DcMotor { speed } = self.states[&ComponentID(component_id)] else {
+                    panic!("DC motor state expected");
+                };
+                reading("DC motor", Some(format!("{}rad/s", speed)), vec![q("", v, "V")])
+            }
+            Components::Lamp { .. } => {
+                let ComponentState::Lamp { temperature } = self.states[&ComponentID(component_id)] else {
+                    panic!("Lamp state expected");
+                };
+                reading("Lamp", Some(format!("{}K above ambient", temperature)), vec![q("", v, "V")])
+            }
+            Components::Memristor { r_on, r_off, .. } => {
+                let ComponentState::Memristor { x } = self.states[&ComponentID(component_id)] else {
+                    panic!("Memristor state expected");
+                };
+                reading("Memristor", Some(format!("{}Ω", memristor::resistance(*r_on, *r_off, x))), vec![q("", v, "V")])
+            }
+            Components::Fuse { .. } => {
+                let state = match self.states[&ComponentID(component_id)] {
+                    ComponentState::Fuse { blown: true, .. } => "blown",
+                    _ => "intact",
+                };
+                reading("Fuse", Some(state.to_string()), vec![q("", v, "V")])
+            }
+            Components::ControlledSwitch { threshold, .. } => {
+                let node_control_input = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let node_control_output = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 3));
+                let v_control = self.node_voltage(unknowns, node_control_output) - self.node_voltage(unknowns, node_control_input);
+                let state = if v_control > *threshold { "closed" } else { "open" };
+                reading("Switch", Some(state.to_string()), vec![q("", v, "V")])
+            }
+            Components::Switch { closed, toggle_times, .. } => {
+                let state = if switch::is_closed(*closed, toggle_times, self.time()) { "closed" } else { "open" };
+                reading("Switch", Some(state.to_string()), vec![q("", v, "V")])
+            }
+            Components::VoltageGenerator(_) => reading("Voltage Generator", None, vec![q("", v, "V")]),
+            Components::SineGenerator { .. } => reading("Sine Generator", None, vec![q("", v, "V")]),
+            Components::PulseGenerator { .. } => reading("Pulse Generator", None, vec![q("", v, "V")]),
+            Components::PwlVoltage { .. } | Components::PwlCurrent { .. } => reading("PWL Generator", None, vec![q("", v, "V")]),
+            Components::ExpGenerator { .. } => reading("Exponential Generator", None, vec![q("", v, "V")]),
+            Components::FmGenerator { .. } => reading("FM Generator", None, vec![q("", v, "V")]),
+            Components::PrbsGenerator { .. } => reading("PRBS Generator", None, vec![q("", v, "V")]),
+            Components::NoiseVoltage { .. } | Components::NoiseCurrent { .. } => reading("Noise source", None, vec![q("", v, "V")]),
+            Components::ThreePhaseSource { .. } => {
+                let node_neutral = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 3));
+                let v_phases = [0, 1, 2].map(|idx| {
+                    let node_phase = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, idx));
+                    self.node_voltage(unknowns, node_phase) - self.node_voltage(unknowns, node_neutral)
+                });
+                reading("Three-phase source", None, vec![q("Va=", v_phases[0], "V"), q("Vb=", v_phases[1], "V"), q("Vc=", v_phases[2], "V")])
+            }
+            Components::Voltmeter { label, .. } | Components::Ammeter { label, .. } => {
+                let reading_value = self.meter_reading(unknowns, label).unwrap();
+                match component {
+                    Components::Voltmeter { .. } => reading("Voltmeter", Some(label.clone()), vec![q("", reading_value, "V")]),
+                    _ => reading("Ammeter", Some(label.clone()), vec![q("", reading_value, "A")]),
+                }
+            }
+            Components::Wire => reading("Wire", None, vec![]),
+            Components::Ground | Components::Custom(_) => unreachable!("Read above"),
+            Components::CurrentProbe { label } => {
+                let branch_id = self.get_branch_id_from_component_id(&ComponentID(component_id));
+                reading("Current probe", Some(label.clone()), vec![q("", unknowns[self.nodes.len() - 1 + branch_id], "A")])
+            }
+            Components::CurrentGenerator(_) => reading("Current Generator", None, vec![q("", v, "V")]),
+            Components::NortonSource { .. } => reading("Norton source", None, vec![q("", v, "V")]),
+            Components::Oriented { .. } => reading("Oriented component", None, vec![q("", v, "V")]),
+            Components::SolarCell { .. } => reading("Solar cell", None, vec![q("", v, "V")]),
+            Components::Loudspeaker { .. } => reading("Loudspeaker", None, vec![q("", v, "V")]),
+            Components::Capacitor(_) | Components::RealCapacitor { .. } => reading("Capacitor", None, vec![q("", v, "V")]),
+            Components::Supercapacitor { .. } => reading("Supercapacitor", None, vec![q("", v, "V")]),
+            Components::Battery { .. } => {
+                let ComponentState::Battery { soc } = self.states[&ComponentID(component_id)] else {
+                    panic!("Battery state expected");
+                };
+                reading("Battery", Some(format!("{}%", soc * 100.0)), vec![q("", v, "V")])
+            }
+            Components::Vcvs { .. } => reading("VCVS", None, vec![q("", v, "V")]),
+            Components::Vccs { .. } => reading("VCCS", None, vec![q("", v, "V")]),
+            Components::Ccvs { .. } => reading("CCVS", None, vec![q("", v, "V")]),
+            Components::BehavioralVoltage(_) | Components::BehavioralCurrent(_) => reading("Behavioral source", None, vec![q("", v, "V")]),
+            Components::TransferFunction { .. } => reading("Transfer function", None, vec![q("", v, "V")]),
+            Components::Cccs { .. } => reading("CCCS", None, vec![q("", v, "V")]),
+            Components::Inductor(_) | Components::RealInductor { .. } => reading("Inductor", None, vec![q("", v, "V")]),
+            Components::Diode { .. } => reading("Diode", None, vec![q("", v, "V")]),
+            Components::Zener { .. } => reading("Zener", None, vec![q("", v, "V")]),
+            Components::TableResistor { table } => {
+                let (intensity, _) = table::interpolate(table, -v);
+                reading("Table resistor", None, vec![q("", v, "V"), q("", intensity, "A")])
+            }
+            Components::Bjt { .. } => {
+                let node_emitter = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let v_emitter = self.node_voltage(unknowns, node_emitter);
+                let v_collector = self.node_voltage(unknowns, node_input);
+                let v_base = self.node_voltage(unknowns, node_output);
+                reading("BJT", None, vec![q("Vce=", v_collector - v_emitter, "V"), q("Vbe=", v_base - v_emitter, "V")])
+            }
+            Components::Mosfet { .. } | Components::Jfet { .. } => {
+                let node_source = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let v_source = self.node_voltage(unknowns, node_source);
+                let v_drain = self.node_voltage(unknowns, node_input);
+                let v_gate = self.node_voltage(unknowns, node_output);
+                let kind = match component {
+                    Components::Mosfet { .. } => "MOSFET",
+                    _ => "JFET",
+                };
+                reading(kind, None, vec![q("Vds=", v_drain - v_source, "V"), q("Vgs=", v_gate - v_source, "V")])
+            }
+            Components::Triode(_) => {
+                let node_cathode = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let v_cathode = self.node_voltage(unknowns, node_cathode);
+                let v_plate = self.node_voltage(unknowns, node_input);
+                let v_grid = self.node_voltage(unknowns, node_output);
+                reading("Triode", None, vec![q("Vpk=", v_plate - v_cathode, "V"), q("Vgk=", v_grid - v_cathode, "V")])
+            }
+            Components::Comparator { .. } | Components::SrLatch { .. } | Components::Timer555
+            | Components::BuckConverter { .. } | Components::BoostConverter { .. } | Components::LogicGate { .. }
+            | Components::DFlipFlop { .. } => {
+                let (kind, output_idx, ground_idx) = match component {
+                    Components::Comparator { .. } => ("Comparator", 2, 3),
+                    Components::SrLatch { .. } => ("Latch", 2, 4),
+                    Components::DFlipFlop { .. } => ("Flip-flop", 2, 4),
+                    Components::LogicGate { function, .. } => ("Logic gate", function.input_count(), function.input_count() + 1),
+                    Components::BuckConverter { .. } => ("Buck converter", 1, 2),
+                    Components::BoostConverter { .. } => ("Boost converter", 1, 2),
+                    _ => ("555 timer", 2, 0),
+                };
+                let node_output = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, output_idx));
+                let node_ground = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, ground_idx));
+                let v_out = self.node_voltage(unknowns, node_output) - self.node_voltage(unknowns, node_ground);
+                reading(kind, None, vec![q("Vout=", v_out, "V")])
+            }
+            Components::Regulator { .. } => {
+                let node_ground = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let v_out = self.node_voltage(unknowns, node_output) - self.node_voltage(unknowns, node_ground);
+                let region = match self.regulator_region(ComponentID(component_id)) {
+                    regulator::Region::Regulation => "regulating",
+                    regulator::Region::Dropout => "dropout",
+                    regulator::Region::CurrentLimit => "current limit",
+                };
+                reading("Regulator", Some(region.to_string()), vec![q("Vout=", v_out, "V")])
+            }
+            Components::OpAmp { .. } => {
+                let node_amplifier = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let v_amplifier = self.node_voltage(unknowns, node_amplifier);
+                reading("Op-amp", None, vec![q("Vout=", v_amplifier, "V"), q("Vd=", -v, "V")])
+            }
+            Components::TransmissionLine { .. } | Components::Gyrator { .. } => {
+                let node_port_2_input = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let node_port_2_output = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 3));
+                let v_port_2 = self.node_voltage(unknowns, node_port_2_output) - self.node_voltage(unknowns, node_port_2_input);
+                let kind = match component {
+                    Components::TransmissionLine { .. } => "Transmission line",
+                    _ => "Gyrator",
+                };
+                reading(kind, None, vec![q("V1=", v, "V"), q("V2=", v_port_2, "V")])
+            }
+            Components::Transformer { .. } | Components::SaturatingTransformer { .. } => {
+                let node_secondary_input = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let node_secondary_output = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 3));
+                let v_secondary = self.node_voltage(unknowns, node_secondary_output) - self.node_voltage(unknowns, node_secondary_input);
+                reading("Transformer", None, vec![q("Vp=", v, "V"), q("Vs=", v_secondary, "V")])
+            }
+            Components::SparkGap { .. } => {
+                let state = match self.states[&ComponentID(component_id)] {
+                    ComponentState::SparkGap { struck: true } => "struck",
+                    _ => "open",
+                };
+                reading("Spark gap", Some(state.to_string()), vec![q("", v, "V")])
+            }
+            Components::IdealDiode { forward_voltage } => {
+                // The anode is the terminal 0, so the forward voltage is -v.
+                let state = if -v > *forward_voltage { "conducting" } else { "blocking" };
+                reading("Ideal diode", Some(state.to_string()), vec![q("", v, "V")])
+            }
+            Components::Tvs { breakdown_voltage, clamping_resistance } => {
+                let (intensity, _) = tvs::intensity(*breakdown_voltage, *clamping_resistance, -v);
+                reading("TVS", None, vec![q("", v, "V"), q("", intensity, "A")])
+            }
+            Components::Optocoupler { vf, rs, .. } => {
+                let node_collector = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 2));
+                let node_emitter = self.get_node_id_from_terminal_id(&TerminalID::new(component_id, 3));
+                let v_ce = self.node_voltage(unknowns, node_collector) - self.node_voltage(unknowns, node_emitter);
+                let (intensity, _) = led::intensity(*vf, *rs, -v);
+                reading("Optocoupler", None, vec![q("If=", intensity, "A"), q("Vce=", v_ce, "V")])
+            }
+            Components::Led { vf, rs, max_current } => {
+                // The LED conducts from the anode (0) to the cathode (1).
+                let (intensity, _) = led::intensity(*vf, *rs, -v);
+                let detail = (intensity > *max_current).then(|| format!("exceeds the maximum of {}A", max_current));
+                reading("LED", detail, vec![q("", v, "V"), q("", intensity, "A")])
+            }
+        }
+    }
+
+    /** Solves the circuit and returns the system and the operating point, see `report` to print them. */
+    pub fn simulate(&mut self) -> Simulation {
+        let operating_point = self.operating_point();
+        let (matrix, result) = self.get_system();
+        Simulation { matrix, result, operating_point }
+    }
+
+    /** Returns the matrix (M) of the equation (M * X = Y). */
+    pub fn get_matrix(&self) -> DMatrix<f64> {
+        self.get_system().0
+    }
+
+    /**
+    Returns the matrix (M) and the result (Y) of the equation (M * X = Y), the custom components
+    stamping both at once.
+    */
+    fn get_system(&self) -> (DMatrix<f64>, DVector<f64>) {
+        let mut matrix = self.assemble_matrix(None);
+        let mut result = self.get_result_vector();
+        self.stamp_custom_components(&mut matrix, &mut result);
+        (matrix, result)
+    }
+
+    /**
+    Returns the matrix of the equation over real scalars, or over complex ones with the reactive
+    components at the angular frequency of an AC analysis. The custom components are not stamped.
+    */
+    fn assemble_matrix<T: ComplexField<RealField = f64>>(&self, ac: Option<&AngularFrequency<T>>) -> DMatrix<T> {
+        let mut rows = Vec::with_capacity(self.n);
+        for node_id in 1..self.nodes.len() {
+            let node_intensity = self.get_node_intensity(node_id, ac);
+            rows.push(node_intensity.transpose());
+        }
+
+        for branch_id in 0..self.branches.len() {
+            let branch_voltage = self.get_branch_voltage(branch_id, ac);
+            rows.push(branch_voltage.transpose());
+        }
+
+        let mut matrix = DMatrix::from_rows(&rows);
+        // The rows and columns of the nodes come first, without the reference node.
+        for node_row in 0..self.nodes.len() - 1 {
+            matrix[(node_row, node_row)] += T::from_real(self.gmin);
+        }
+        matrix
+    }
+
+    /** Lets the custom components add their equations, linearized around the guess. */
+    fn stamp_custom_components(&self, matrix: &mut DMatrix<f64>, rhs: &mut DVector<f64>) {
+        for (component_id, component) in self.circuit.components.iter().enumerate() {
+            if let Components::Custom(component) = component {
+                let (terminal_rows, branch_rows) = self.stamp_rows(ComponentID(component_id), component.terminals(), component.branches());
+                let ctx = StampContext { terminal_rows, branch_rows, unknowns: &self.guess, time_step: self.time_step.as_ref() };
+                component.stamp(matrix, rhs, &ctx);
+            }
+        }
+    }
+
+    /** Returns the rows of the terminals and branches of a custom component in the matrix equation. */
+    fn stamp_rows(&self, component_id: ComponentID, terminals: usize, branches: usize) -> (Vec<Option<usize>>, Vec<usize>) {
+        // The reference node (0) has no row, the row of the node i is i - 1.
+        let terminal_rows = (0..terminals)
+            .map(|idx| self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx)).checked_sub(1))
+            .collect();
+        let branch_rows = match self.component_id_to_branch_id.get(&component_id) {
+            Some(branch_id) => (0..branches).map(|k| self.nodes.len() - 1 + branch_id + k).collect(),
+            None => Vec::new(),
+        };
+
+        (terminal_rows, branch_rows)
+    }
+
+
+    /** Returns the intensity that goes through a certain node as a vector of the dimensions. */
+    fn get_node_intensity<T: ComplexField<RealField = f64>>(&self, node_id: usize, ac: Option<&AngularFrequency<T>>) -> DVector<T> {
+        let mut result = DVector::zeros(self.n);
+
+        for terminal_id in &self.nodes[node_id] {
+            let intensity = self.get_component_intensity_vector(*terminal_id, ac);
+            result += intensity;
+        }
+
+        result
+    }
+
+    /** Returns the intensity that goes through a certain component as a vector of the dimensions. */
+    fn get_component_intensity_vector<T: ComplexField<RealField = f64>>(&self, output_terminal_id: TerminalID, ac: Option<&AngularFrequency<T>>) -> DVector<T> {
+        let component = &self.circuit.components[output_terminal_id.component_id.0];
+
+        match component {
+            Components::RealCapacitor { .. } | Components::RealInductor { .. } | Components::SolarCell { .. }
+            | Components::ThreePhaseSource { .. } | Components::Timer555 | Components::BuckConverter { .. }
+            | Components::BoostConverter { .. } | Components::Loudspeaker { .. } | Components::NortonSource { .. }
+            | Components::Oriented { .. } => {
+                unreachable!("Composite components are expanded")
+            }
+            Components::Resistor(resistance) => {
+                self.unknown_bipole_voltage(&output_terminal_id).unscale(self.get_resistance(output_terminal_id.component_id, *resistance))
+            }
+            Components::Voltmeter { input_resistance: resistance, .. } | Components::Ammeter { burden_resistance: resistance, .. }
+            | Components::DigitalOutput { output_resistance: resistance, .. } => {
+                self.unknown_bipole_voltage(&output_terminal_id).unscale(*resistance)
+            }
+            Components::DigitalInput { .. } => DVector::zeros(self.n),
+            Components::Potentiometer { resistance, position } => {
+                let (resistance_0, resistance_1) = potentiometer::resistances(*resistance, *position);
+                let component_id = output_terminal_id.component_id;
+                let v = [0, 1, 2].map(|idx| self.unknown_terminal_voltage(component_id, idx));
+
+                match output_terminal_id.idx {
+                    0 => (&v[0] - &v[2]).unscale(resistance_0),
+                    1 => (&v[1] - &v[2]).unscale(resistance_1),
+                    2 => (&v[2] - &v[0]).unscale(resistance_0) + (&v[2] - &v[1]).unscale(resistance_1),
+                    _ => panic!("Invalid terminal index"),
+                }
+            }
+            Components::Memristor { r_on, r_off, .. } => {
+                let ComponentState::Memristor { x } = self.states[&output_terminal_id.component_id] else {
+                    panic!("Memristor state expected");
+                };
+                self.unknown_bipole_voltage(&output_terminal_id).unscale(memristor::resistance(*r_on, *r_off, x))
+            }
+            Components::Lamp { cold_resistance, temperature_coefficient, .. } => {
+                let ComponentState::Lamp { temperature } = self.states[&output_terminal_id.component_id] else {
+                    panic!("Lamp state expected");
+                };
+                self.unknown_bipole_voltage(&output_terminal_id).unscale(lamp::resistance(*cold_resistance, *temperature_coefficient, temperature))
+            }
+            Components::Fuse { .. } => {
+                let component_id = output_terminal_id.component_id;
+                self.unknown_bipole_voltage(&output_terminal_id).unscale(self.get_fuse_resistance(component_id, &self.states[&component_id]))
+            }
+            Components::Switch { on_resistance, off_resistance, closed, toggle_times } => {
+                // The matrix is built again for every solve, so the switch is re-stamped with its
+                // state at the current time.
+                let resistance = if switch::is_closed(*closed, toggle_times, self.time()) {
+                    on_resistance
+                } else {
+                    off_resistance
+                };
+                self.unknown_bipole_voltage(&output_terminal_id).unscale(*resistance)
+            }
+            Components::VoltageGenerator(_) | Components::SineGenerator { .. } | Components::CurrentProbe { .. }
+            | Components::Inductor(_) | Components::NoiseVoltage { .. } | Components::PulseGenerator { .. }
+            | Components::PwlVoltage { .. } | Components::ExpGenerator { .. } | Components::FmGenerator { .. }
+            | Components::PrbsGenerator { .. } => {
+                let branch_id = self.get_branch_id_from_component_id(&output_terminal_id.component_id);
+                let intensity = self.unknown_branch_intensity(branch_id);
+
+                // This intensity is directed from 0->1. So if we want the intensity on the other
+                // terminal, we need to invert it.
+                if output_terminal_id.idx == 1 {
+                    intensity
+                } else {
+                    -intensity
+                }
+            }
+            Components::Capacitor(_) if self.applying_initial_conditions => {
+                self.unknown_bipole_voltage(&output_terminal_id).scale(INITIAL_CONDITION_CONDUCTANCE)
+            }
+            Components::Capacitor(capacitance) => {
+                // i = C * v', a capacitor does not let any DC current through.
+                let derivative = self.derivative_factor(&self.regime(ac));
+                self.unknown_bipole_voltage(&output_terminal_id) * derivative.scale(*capacitance)
+            }
+            Components::Diode { .. } | Components::Zener { .. } | Components::Led { .. }
+            | Components::TableResistor { .. } | Components::Tvs { .. } | Components::IdealDiode { .. }
+            | Components::SparkGap { .. } => {
+                let conductance = self.linearize_bipole(output_terminal_id.component_id).conductance;
+                self.unknown_bipole_voltage(&output_terminal_id).scale(conductance)
+            }
+            Components::Relay { on_resistance, off_resistance, .. } if output_terminal_id.idx >= 2 => {
+                let resistance = match self.states[&output_terminal_id.component_id] {
+                    ComponentState::Relay { closed: true } => on_resistance,
+                    _ => off_resistance,
+                };
+
+                let node_output = self.get_node_id_from_terminal_id(&output_terminal_id);
+                let node_input = self.get_node_id_from_terminal_id(&TerminalID::new(output_terminal_id.component_id.0, 5 - output_terminal_id.idx));
+                (self.unknown_node_voltage(node_output) - self.unknown_node_voltage(node_input)).unscale(*resistance)
+            }
+            Components::Supercapacitor { leakage_resistance, .. } => {
+                // The capacitive branch intensity, plus the leakage one.
+                let branch_id = self.get_branch_id_from_component_id(&output_terminal_id.component_id);
+                let intensity = self.unknown_branch_intensity(branch_id);
+                let leakage = self.unknown_bipole_voltage(&output_terminal_id).unscale(*leakage_resistance);
+
+                if output_terminal_id.idx == 1 {
+                    intensity + leakage
+                } else {
+                    -intensity + leakage
+                }
+            }
+            Components::Vcvs { .. } | Components::Ccvs { .. } | Components::Relay { .. } | Components::Battery { .. }
+            | Components::DcMotor { .. } | Components::Regulator { .. } | Components::BehavioralVoltage(_)
+            | Components::TransferFunction { .. } => {
+                let branch_id = self.get_branch_id_from_component_id(&output_terminal_id.component_id);
+                let intensity = self.unknown_branch_intensity(branch_id);
+
+                // Like a voltage generator on the output, the sense terminals do not draw any intensity.
+                match output_terminal_id.idx {
+                    0 => -intensity,
+                    1 => intensity,
+                    _ => DVector::zeros(self.n),
+                }
+            }
+            Components::TransmissionLine { .. } => {
+                // The first branch intensity enters the port 1 on the terminal 1, the second one
+                // enters the port 2 on the terminal 3.
+                let branch_id = self.get_branch_id_from_component_id(&output_terminal_id.component_id);
+                match output_terminal_id.idx {
+                    0 => -self.unknown_branch_intensity(branch_id),
+                    1 => self.unknown_branch_intensity(branch_id),
+                    2 => -self.unknown_branch_intensity(branch_id + 1),
+                    3 => self.unknown_branch_intensity(branch_id + 1),
+                    _ => panic!("Invalid terminal index"),
+                }
+            }
+            Components::Gyrator { conductance } => {
+                let component_id = output_terminal_id.component_id;
+                let v_port_1 = self.unknown_terminal_voltage(component_id, 1) - self.unknown_terminal_voltage(component_id, 0);
+                let v_port_2 = self.unknown_terminal_voltage(component_id, 3) - self.unknown_terminal_voltage(component_id, 2);
+
+                match output_terminal_id.idx {
+                    0 => -v_port_2.scale(*conductance),
+                    1 => v_port_2.scale(*conductance),
+                    2 => v_port_1.scale(*conductance),
+                    3 => -v_port_1.scale(*conductance),
+                    _ => panic!("Invalid terminal index"),
+                }
+            }
+            Components::CurrentGenerator(_) | Components::NoiseCurrent { .. } | Components::PwlCurrent { .. } => DVector::zeros(self.n),
+            // Both terminals are in the same node, the intensity through the wire cancels out.
+            Components::Wire | Components::Ground => DVector::zeros(self.n),
+            // Custom components stamp the matrix themselves.
+            Components::Custom(_) => DVector::zeros(self.n),
+            Components::Comparator { output_resistance } => {
+                let component_id = output_terminal_id.component_id;
+                let v_plus = self.node_voltage(&self.guess, self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, 0)));
+                let v_minus = self.node_voltage(&self.guess, self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, 1)));
+
+                // The output is selected around the guess, like the region of an ideal diode.
+                let rail = if v_plus > v_minus { 4 } else { 3 };
+                self.unknown_rail_output(output_terminal_id, 2, rail, *output_resistance)
+            }
+            Components::ControlledSwitch { on_resistance, off_resistance, threshold } => {
+                let component_id = output_terminal_id.component_id;
+                if output_terminal_id.idx >= 2 {
+                    return DVector::zeros(self.n);
+                }
+
+                // Like the comparator, the state is selected around the guess.
+                let v_control = self.node_voltage(&self.guess, self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, 3)))
+                    - self.node_voltage(&self.guess, self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, 2)));
+                let resistance = if v_control > *threshold { on_resistance } else { off_resistance };
+                self.unknown_bipole_voltage(&output_terminal_id).unscale(*resistance)
+            }
+            Components::LogicGate { function, output_resistance } => {
+                let component_id = output_terminal_id.component_id;
+                let output_idx = function.input_count();
+                let v = |idx: usize| self.node_voltage(&self.guess, self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx)));
+                let inputs: Vec<bool> = (0..output_idx)
+                    .map(|idx| logic::is_high(v(idx), v(output_idx + 1), v(output_idx + 2)))
+                    .collect();
+
+                // Like the comparator, the output is selected around the guess.
+                let rail = if logic::evaluate(*function, &inputs) { output_idx + 2 } else { output_idx + 1 };
+                self.unknown_rail_output(output_terminal_id, output_idx, rail, *output_resistance)
+            }
+            Components::SrLatch { output_resistance } | Components::DFlipFlop { output_resistance } => {
+                let (ComponentState::Latch { q } | ComponentState::FlipFlop { q, .. }) = self.states[&output_terminal_id.component_id] else {
+                    panic!("Latch state expected");
+                };
+
+                let (rail_q, rail_not_q) = if q { (5, 4) } else { (4, 5) };
+                self.unknown_rail_output(output_terminal_id, 2, rail_q, *output_resistance)
+                    + self.unknown_rail_output(output_terminal_id, 3, rail_not_q, *output_resistance)
+            }
+            Components::Vccs { transconductance } => {
+                let component_id = output_terminal_id.component_id;
+                let v_sense = self.unknown_terminal_voltage::<T>(component_id, 3) - self.unknown_terminal_voltage(component_id, 2);
+                let intensity = v_sense.scale(*transconductance);
+
+                match output_terminal_id.idx {
+                    0 => -intensity,
+                    1 => intensity,
+                    _ => DVector::zeros(self.n),
+                }
+            }
+            Components::BehavioralCurrent(expression) => {
+                let intensity = self.linearize_expression(expression).0.map(T::from_real);
+
+                match output_terminal_id.idx {
+                    0 => -intensity,
+                    1 => intensity,
+                    _ => panic!("Invalid terminal index"),
+                }
+            }
+            Components::Cccs { control, gain } => {
+                let control_branch_id = self.get_branch_id_from_component_id(control);
+                let intensity = self.unknown_branch_intensity::<T>(control_branch_id).scale(*gain);
+
+                match output_terminal_id.idx {
+                    0 => -intensity,
+                    1 => intensity,
+                    _ => panic!("Invalid terminal index"),
+                }
+            }
+            Components::OpAmp { .. } => {
+                // The inputs do not draw any intensity, the output one is directed into the op-amp.
+                if output_terminal_id.idx == 2 {
+                    let branch_id = self.get_branch_id_from_component_id(&output_terminal_id.component_id);
+                    self.unknown_branch_intensity(branch_id)
+                } else {
+                    DVector::zeros(self.n)
+                }
+            }
+            Components::Transformer { ratio } | Components::SaturatingTransformer { ratio, .. } => {
+                let branch_id = self.get_branch_id_from_component_id(&output_terminal_id.component_id);
+                let intensity = self.unknown_branch_intensity(branch_id);
+
+                // The first branch intensity is the primary one. The power being conserved, the
+                // secondary intensity is -ratio times the primary one.
+                let primary = match component {
+                    // The magnetizing intensity also goes through the primary winding.
+                    Components::SaturatingTransformer { .. } => &intensity + self.unknown_branch_intensity(branch_id + 1),
+                    _ => intensity.clone(),
+                };
+                match output_terminal_id.idx {
+                    0 => -primary,
+                    1 => primary,
+                    2 => intensity.scale(*ratio),
+                    3 => -intensity.scale(*ratio),
+                    _ => panic!("Invalid terminal index"),
+                }
+            }
+            Components::Bjt { .. } | Components::Mosfet { .. } | Components::Jfet { .. } | Components::Triode(_)
+            | Components::Optocoupler { .. } => {
+                let linearized = self.linearize_terminals(output_terminal_id.component_id);
+
+                let mut result = DVector::zeros(self.n);
+                for (idx, conductance) in linearized.conductances[output_terminal_id.idx].iter().enumerate() {
+                    let node_id = self.get_node_id_from_terminal_id(&TerminalID::new(output_terminal_id.component_id.0, idx));
+                    result += self.unknown_node_voltage::<T>(node_id).scale(*conductance);
+                }
+                result
+            }
+        }
+    }
+
+    /**
+    Returns the constant part of the intensity that goes through a certain component, i.e. the part
+    that does not depend on the unknowns.
+    */
+    fn get_component_intensity_constant(&self, output_terminal_id: TerminalID) -> f64 {
+        let component = &self.circuit.components[output_terminal_id.component_id.0];
+
+        match component {
+            Components::RealCapacitor { .. } | Components::RealInductor { .. } | Components::SolarCell { .. }
+            | Components::ThreePhaseSource { .. } | Components::Timer555 | Components::BuckConverter { .. }
+            | Components::BoostConverter { .. } | Components::Loudspeaker { .. } | Components::NortonSource { .. }
+            | Components::Oriented { .. } => {
+                unreachable!("Composite components are expanded")
+            }
+            Components::CurrentGenerator(intensity) => {
+                // The intensity enters the generator by the terminal 0.
+                let intensity = *intensity * self.source_scale;
+                if output_terminal_id.idx == 0 {
+                    intensity
+                } else {
+                    -intensity
+                }
+            }
+            Components::NoiseCurrent { .. } | Components::PwlCurrent { .. } => {
+                let intensity = match component {
+                    Components::PwlCurrent { points } => waveform::piecewise_linear(points, self.time()),
+                    _ => self.noise_sample(output_terminal_id.component_id),
+                } * self.source_scale;
+                if output_terminal_id.idx == 0 {
+                    intensity
+                } else {
+                    -intensity
+                }
+            }
+            Components::Resistor(_) | Components::Voltmeter { .. } | Components::Ammeter { .. } | Components::Potentiometer { .. }
+            | Components::Switch { .. } | Components::Fuse { .. } | Components::Wire | Components::Ground | Components::Custom(_)
+            | Components::Memristor { .. } | Components::Lamp { .. } | Components::VoltageGenerator(_) | Components::SineGenerator { .. } | Components::NoiseVoltage { .. } | Components::CurrentProbe { .. }
+            | Components::PulseGenerator { .. } | Components::PwlVoltage { .. } | Components::ExpGenerator { .. } | Components::FmGenerator { .. } | Components::PrbsGenerator { .. } | Components::Inductor(_) | Components::OpAmp { .. } | Components::Transformer { .. }
+            | Components::SaturatingTransformer { .. } | Components::Vcvs { .. } | Components::Vccs { .. } | Components::Gyrator { .. }
+            | Components::Ccvs { .. } | Components::Cccs { .. } | Components::Relay { .. }
+            | Components::Battery { .. } | Components::Supercapacitor { .. } | Components::TransmissionLine { .. }
+            | Components::DcMotor { .. } | Components::Regulator { .. } | Components::Comparator { .. }
+            | Components::SrLatch { .. } | Components::ControlledSwitch { .. } | Components::BehavioralVoltage(_)
+            | Components::LogicGate { .. } | Components::DFlipFlop { .. } | Components::DigitalInput { .. }
+            | Components::TransferFunction { .. } => 0.0,
+            Components::DigitalOutput { signal, low, high, output_resistance } => {
+                // (V1 - V0 - V) / R enters by the terminal 1.
+                let voltage = if self.digital_signal(*signal) { *high } else { *low } * self.source_scale;
+                if output_terminal_id.idx == 1 {
+                    -voltage / output_resistance
+                } else {
+                    voltage / output_resistance
+                }
+            }
+            Components::BehavioralCurrent(expression) => {
+                // The intensity enters by the terminal 1, like for a VCCS.
+                let (_, current) = self.linearize_expression(expression);
+                if output_terminal_id.idx == 1 {
+                    current
+                } else {
+                    -current
+                }
+            }
+            Components::Capacitor(_) if self.applying_initial_conditions => {
+                // The bipole voltage seen from the terminal 1 is V1 - V0, the initial condition.
+                let v = self.initial_condition(output_terminal_id.component_id);
+                let v = if output_terminal_id.idx == 1 { v } else { -v };
+                -v * INITIAL_CONDITION_CONDUCTANCE
+            }
+            Components::Capacitor(capacitance) => {
+                let Some(time_step) = &self.time_step else {
+                    return 0.0;
+                };
+
+                // Companion model: i = C * v', the previous voltages and intensity act as a current
+                // source in parallel with the C * alpha conductance.
+                let derivative = self.derivative(time_step);
+                let history = self.derivative_history(&derivative, |unknowns| self.bipole_voltage(unknowns, &output_terminal_id));
+                let ComponentState::Capacitor { current } = self.states[&output_terminal_id.component_id] else {
+                    panic!("Capacitor state expected");
+                };
+                let current_previous = if output_terminal_id.idx == 0 { current } else { -current };
+                *capacitance * history - derivative.gamma * current_previous
+            }
+            Components::Diode { .. } | Components::Zener { .. } | Components::Led { .. }
+            | Components::TableResistor { .. } | Components::Tvs { .. } | Components::IdealDiode { .. }
+            | Components::SparkGap { .. } => {
+                // The linearized intensity goes from the anode (0) to the cathode (1).
+                let current = self.linearize_bipole(output_terminal_id.component_id).current;
+                if output_terminal_id.idx == 0 {
+                    current
+                } else {
+                    -current
+                }
+            }
+            Components::Bjt { .. } | Components::Mosfet { .. } | Components::Jfet { .. } | Components::Triode(_)
+            | Components::Optocoupler { .. } => {
+                self.linearize_terminals(output_terminal_id.component_id).currents[output_terminal_id.idx]
+            }
+        }
+    }
+
+    /**
+    Linearizes a nonlinear bipole (diode, ...) around the guess, as seen from the terminal 0 (the
+    anode) to the terminal 1 (the cathode).
+    */
+    fn linearize_bipole(&self, component_id: ComponentID) -> Linearized {
+        let node_anode = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, 0));
+        let node_cathode = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, 1));
+        let v = self.node_voltage(&self.guess, node_anode) - self.node_voltage(&self.guess, node_cathode);
+
+        match &self.circuit.components[component_id.0] {
+            Components::Diode { is, n } => diode::linearize(*is, *n, v),
+            Components::Zener { is, n, vz } => zener::linearize(*is, *n, *vz, v),
+            Components::Led { vf, rs, .. } => led::linearize(*vf, *rs, v),
+            Components::Tvs { breakdown_voltage, clamping_resistance } => tvs::linearize(*breakdown_voltage, *clamping_resistance, v),
+            Components::IdealDiode { forward_voltage } => ideal_diode::linearize(*forward_voltage, v),
+            Components::SparkGap { holding_voltage, arc_resistance, .. } => {
+                let ComponentState::SparkGap { struck } = self.states[&component_id] else {
+                    panic!("Spark gap state expected");
+                };
+                spark_gap::linearize(struck, *holding_voltage, *arc_resistance, v)
+            }
+            Components::TableResistor { table } => {
+                let (intensity, conductance) = table::interpolate(table, v);
+                Linearized::tangent(v, intensity, conductance)
+            }
+            _ => panic!("Nonlinear bipole expected"),
+        }
+    }
+
+    /** Linearizes a component with more than two terminals (transistor, optocoupler) around the guess. */
+    fn linearize_terminals(&self, component_id: ComponentID) -> LinearizedTerminals {
+        let voltage = |idx| {
+            let node_id = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx));
+            self.node_voltage(&self.guess, node_id)
+        };
+        let v = [0, 1, 2].map(voltage);
+
+        match &self.circuit.components[component_id.0] {
+            Components::Bjt { polarity, beta, is } => bjt::linearize(*polarity, *beta, *is, v),
+            Components::Mosfet { channel, vth, kp, lambda } => mosfet::linearize(*channel, *vth, *kp, *lambda, v),
+            Components::Jfet { channel, idss, vp } => jfet::linearize(*channel, *idss, *vp, v),
+            Components::Triode(model) => triode::linearize(model, v),
+            Components::Optocoupler { vf, rs, ctr } => optocoupler::linearize(*vf, *rs, *ctr, [0, 1, 2, 3].map(voltage)),
+            _ => panic!("Transistor expected"),
+        }
+    }
+
+    fn get_branch_voltage<T: ComplexField<RealField = f64>>(&self, branch_id: usize, ac: Option<&AngularFrequency<T>>) -> DVector<T> {
+        let component_id = self.get_component_id_from_branch_id(branch_id);
+        if let Components::Custom(_) = &self.circuit.components[component_id.0] {
+            // Custom components stamp their branch rows themselves.
+            return DVector::zeros(self.n);
+        }
+
+        let terminal_input = TerminalID::new(component_id.0, 0);
+        let terminal_output = TerminalID::new(component_id.0, 1);
+
+        let node_input = self.get_node_id_from_terminal_id(&terminal_input);
+        let node_output = self.get_node_id_from_terminal_id(&terminal_output);
+
+        let v_input = self.unknown_node_voltage(node_input);
+        let v_output = self.unknown_node_voltage(node_output);
+
+        let regime = self.regime(ac);
+        match (&self.circuit.components[component_id.0], &regime) {
+            (Components::Inductor(_), _) if self.applying_initial_conditions => self.unknown_branch_intensity(branch_id),
+            (Components::Inductor(inductance), regime) => {
+                // v = L * i' + M * i_k' for every inductor k coupled to this one, an inductor is a
+                // short circuit at DC.
+                let derivative = self.derivative_factor(regime);
+                let intensity = self.unknown_branch_intensity(branch_id);
+                let mut result = v_output - v_input - intensity * derivative.clone().scale(*inductance);
+                for (other_branch_id, mutual) in self.get_mutual_inductances(component_id) {
+                    result -= self.unknown_branch_intensity::<T>(other_branch_id) * derivative.clone().scale(mutual);
+                }
+                result
+            }
+            (Components::SaturatingTransformer { magnetizing_inductance, saturation_flux, .. }, Regime::Transient(time_step))
+                if branch_id != self.get_branch_id_from_component_id(&component_id) => {
+                // Companion model of the magnetizing inductance, linearized around the guess:
+                // v = (flux(i) - flux(i_previous)) / dt ~= (flux(i_guess) + L(i_guess) * (i - i_guess) - flux(i_previous)) / dt
+                let (_, inductance) = magnetic_core::flux(*magnetizing_inductance, *saturation_flux, self.guess[self.nodes.len() - 1 + branch_id]);
+                let intensity = self.unknown_branch_intensity::<T>(branch_id);
+                v_output - v_input - intensity.scale(inductance / time_step.dt)
+            }
+            (Components::SaturatingTransformer { magnetizing_inductance, saturation_flux, .. }, Regime::Ac(ac))
+                if branch_id != self.get_branch_id_from_component_id(&component_id) => {
+                // The magnetizing inductance at the operating point.
+                let (_, inductance) = magnetic_core::flux(*magnetizing_inductance, *saturation_flux, self.guess[self.nodes.len() - 1 + branch_id]);
+                let intensity = self.unknown_branch_intensity(branch_id);
+                v_output - v_input - intensity * ac.jw().scale(inductance)
+            }
+            (Components::SaturatingTransformer { .. }, Regime::Dc) if branch_id != self.get_branch_id_from_component_id(&component_id) => {
+                // The magnetizing inductance is a short circuit at DC.
+                v_output - v_input
+            }
+            (Components::Transformer { ratio } | Components::SaturatingTransformer { ratio, .. }, _) => {
+                let v_secondary = self.unknown_terminal_voltage::<T>(component_id, 3) - self.unknown_terminal_voltage(component_id, 2);
+                v_output - v_input - v_secondary.scale(*ratio)
+            }
+            (Components::TransmissionLine { impedance, delay }, regime) => {
+                let first_branch_id = self.get_branch_id_from_component_id(&component_id);
+                let v_port_1 = self.unknown_terminal_voltage::<T>(component_id, 1) - self.unknown_terminal_voltage(component_id, 0);
+                let v_port_2 = self.unknown_terminal_voltage::<T>(component_id, 3) - self.unknown_terminal_voltage(component_id, 2);
+                let port = |idx: usize| self.unknown_branch_intensity::<T>(first_branch_id + idx);
+
+                match (regime, branch_id - first_branch_id) {
+                    // At DC, both ports have the same voltage and the intensity goes through.
+                    (Regime::Dc, 0) => v_port_1 - v_port_2,
+                    (Regime::Dc, _) => port(0) + port(1),
+                    // Each port is the characteristic impedance in series with a delayed source:
+                    // v - Z0 * i = E(t - delay).
+                    (Regime::Transient(_), 0) => v_port_1 - port(0).scale(*impedance),
+                    (Regime::Transient(_), _) => v_port_2 - port(1).scale(*impedance),
+                    // The wave arriving at a port left the other one a delay earlier:
+                    // v_1 - Z0 * i_1 = e^(-jw * delay) * (v_2 + Z0 * i_2), and the other way around.
+                    (Regime::Ac(ac), idx) => {
+                        let delayed = ac.jw().scale(-delay).exp();
+                        let (v, v_other) = if idx == 0 { (v_port_1, v_port_2) } else { (v_port_2, v_port_1) };
+                        v - port(idx).scale(*impedance) - (v_other + port(1 - idx).scale(*impedance)) * delayed
+                    }
+                }
+            }
+            (Components::Vcvs { gain }, _) => {
+                let v_sense = self.unknown_terminal_voltage::<T>(component_id, 3) - self.unknown_terminal_voltage(component_id, 2);
+                v_output - v_input - v_sense.scale(*gain)
+            }
+            (Components::Relay { coil_resistance: resistance, coil_inductance: inductance, .. }
+            | Components::DcMotor { armature_resistance: resistance, armature_inductance: inductance, .. }, regime) => {
+                // The coil is an inductor in series with a resistor: v = R * i + L * di/dt, the
+                // back-EMF of a motor being a constant.
+                let intensity = self.unknown_branch_intensity(branch_id);
+                let impedance = match regime {
+                    Regime::Dc => T::from_real(*resistance),
+                    Regime::Transient(time_step) => T::from_real(resistance + inductance / time_step.dt),
+                    Regime::Ac(ac) => T::from_real(*resistance) + ac.jw().scale(*inductance),
+                };
+                v_output - v_input - intensity * impedance
+            }
+            (Components::Supercapacitor { .. }, Regime::Dc) => {
+                // The capacitive branch does not let any DC current through.
+                self.unknown_branch_intensity(branch_id)
+            }
+            (Components::Supercapacitor { capacitance, esr, .. }, Regime::Transient(time_step)) => {
+                // Backward Euler: V1 - V0 = ESR * I + v_c, where v_c = v_c_previous + I * dt / C.
+                let intensity = self.unknown_branch_intensity::<T>(branch_id);
+                v_output - v_input - intensity.scale(esr + time_step.dt / capacitance)
+            }
+            (Components::Supercapacitor { capacitance, esr, .. }, Regime::Ac(ac)) => {
+                // jwC * (V1 - V0) - (1 + jwC * ESR) * I = 0.
+                let admittance = ac.jw().scale(*capacitance);
+                let intensity = self.unknown_branch_intensity(branch_id);
+                (v_output - v_input) * admittance.clone() - intensity * (T::one() + admittance.scale(*esr))
+            }
+            (Components::Battery { internal_resistance, .. }, _) => {
+                // V1 - V0 = OCV(soc) + R * I, the intensity entering the positive terminal.
+                v_output - v_input - self.unknown_branch_intensity::<T>(branch_id).scale(*internal_resistance)
+            }
+            (Components::Ccvs { control, transresistance }, _) => {
+                let control_branch_id = self.get_branch_id_from_component_id(control);
+                v_output - v_input - self.unknown_branch_intensity::<T>(control_branch_id).scale(*transresistance)
+            }
+            (Components::TransferFunction { numerator, denominator }, regime) => {
+                // The gain used at DC and in transient, H(jw) in AC.
+                let gain = match regime {
+                    Regime::Ac(ac) => {
+                        let (re, im) = transfer_function::frequency_response(numerator, denominator, ac.w);
+                        ac.complex(re, im)
+                    }
+                    _ => T::from_real(self.transfer_function_output(component_id).0),
+                };
+                let v_sense = self.unknown_terminal_voltage(component_id, 3) - self.unknown_terminal_voltage(component_id, 2);
+                v_output - v_input - v_sense * gain
+            }
+            (Components::BehavioralVoltage(expression), _) => {
+                let (voltage, _) = self.linearize_expression(expression);
+                v_output - v_input - voltage.map(T::from_real)
+            }
+            (Components::Regulator { output_impedance, .. }, _) => {
+                // The branch intensity enters the output, so the output intensity is its opposite.
+                let intensity = self.unknown_branch_intensity::<T>(branch_id);
+                match self.regulator_region(component_id) {
+                    regulator::Region::Regulation => {
+                        v_output - self.unknown_terminal_voltage(component_id, 2) - intensity.scale(*output_impedance)
+                    }
+                    regulator::Region::Dropout => v_output - v_input - intensity.scale(*output_impedance),
+                    regulator::Region::CurrentLimit => intensity,
+                }
+            }
+            (Components::OpAmp { output_resistance, bandwidth, .. }, regime) => {
+                // The internal source E is behind the output resistance: V_out - R_out * I = E.
+                let node_amplifier = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, 2));
+                let intensity = self.unknown_branch_intensity::<T>(branch_id);
+                let v_amplifier = self.unknown_node_voltage(node_amplifier) - intensity.scale(*output_resistance);
+
+                match self.opamp_output(component_id) {
+                    // The non-inverting input is the terminal 0, so V_input - V_output is vd. In AC,
+                    // the gain has a single pole: gain / (1 + jw / wp). A clipped output does not
+                    // depend on the input.
+                    opamp::Output::Linear { gain, .. } => {
+                        let gain = match regime {
+                            Regime::Ac(ac) => T::from_real(gain) / (T::one() + ac.jw().unscale(2.0 * std::f64::consts::PI * bandwidth)),
+                            _ => T::from_real(gain),
+                        };
+                        v_amplifier - (v_input - v_output) * gain
+                    }
+                    opamp::Output::Clipped(_) => v_amplifier,
+                }
+            }
+            _ => v_output - v_input,
+        }
+    }
+
+    /** Tells how the reactive components are stamped, see `Regime`. */
+    fn regime<'a, T>(&'a self, ac: Option<&'a AngularFrequency<T>>) -> Regime<'a, T> {
+        match (ac, &self.time_step) {
+            (Some(ac), _) => Regime::Ac(ac),
+            (None, Some(time_step)) => Regime::Transient(time_step),
+            (None, None) => Regime::Dc,
+        }
+    }
+
+    /**
+    Returns the factor of x in the derivative x' of a quantity: 0 at DC, alpha of the integration
+    method over a time step (see `Derivative`) and jw in AC.
+    */
+    fn derivative_factor<T: ComplexField<RealField = f64>>(&self, regime: &Regime<T>) -> T {
+        match regime {
+            Regime::Dc => T::zero(),
+            Regime::Transient(time_step) => T::from_real(self.derivative(time_step).alpha),
+            Regime::Ac(ac) => ac.jw(),
+        }
+    }
+
+    /** Returns the constant part of the branch equation, i.e. the right hand side of the row. */
+    fn get_branch_constant(&self, branch_id: usize) -> f64 {
+        let component_id = self.get_component_id_from_branch_id(branch_id);
+
+        match &self.circuit.components[component_id.0] {
+            Components::VoltageGenerator(voltage) => *voltage * self.source_scale,
+            Components::Custom(_) => 0.0,
+            Components::SineGenerator { amplitude, frequency, phase, dc_offset } => {
+                let voltage = dc_offset + amplitude * (2.0 * std::f64::consts::PI * frequency * self.time() + phase).sin();
+                voltage * self.source_scale
+            }
+            Components::PulseGenerator { v1, v2, delay, rise, fall, width, period } => {
+                waveform::pulse([*v1, *v2], *delay, *rise, *fall, *width, *period, self.time()) * self.source_scale
+            }
+            Components::PwlVoltage { points } => waveform::piecewise_linear(points, self.time()) * self.source_scale,
+            Components::ExpGenerator { v1, v2, rise_delay, rise_tau, fall_delay, fall_tau } => {
+                waveform::exponential([*v1, *v2], *rise_delay, *rise_tau, *fall_delay, *fall_tau, self.time()) * self.source_scale
+            }
+            Components::FmGenerator { offset, amplitude, carrier_frequency, modulation_index, signal_frequency } => {
+                let voltage = waveform::frequency_modulated(*offset, *amplitude, *carrier_frequency, *modulation_index, *signal_frequency, self.time());
+                voltage * self.source_scale
+            }
+            Components::PrbsGenerator { low, high, bit_period, seed } => {
+                let index = (self.time() / bit_period).floor() as u64;
+                let voltage = if noise::bit(*seed, index) { *high } else { *low };
+                voltage * self.source_scale
+            }
+            Components::Inductor(_) if self.applying_initial_conditions => self.initial_condition(component_id),
+            Components::Inductor(inductance) => {
+                let Some(time_step) = &self.time_step else {
+                    // An inductor is a short circuit at DC.
+                    return 0.0;
+                };
+
+                // The previous voltage of the inductor is the previous derivative of its flux.
+                let derivative = self.derivative(time_step);
+                let history = |branch_id: usize| {
+                    let idx = self.nodes.len() - 1 + branch_id;
+                    self.derivative_history(&derivative, |unknowns| unknowns[idx])
+                };
+                let v_previous = self.bipole_voltage(&time_step.previous, &TerminalID::new(component_id.0, 1));
+
+                let mut result = *inductance * history(branch_id) - derivative.gamma * v_previous;
+                for (other_branch_id, mutual) in self.get_mutual_inductances(component_id) {
+                    result += mutual * history(other_branch_id);
+                }
+                result
+            }
+            Components::Transformer { .. } | Components::Vcvs { .. } | Components::Ccvs { .. }
+            | Components::CurrentProbe { .. } => 0.0,
+            Components::BehavioralVoltage(expression) => self.linearize_expression(expression).1,
+            Components::NoiseVoltage { .. } => self.noise_sample(component_id) * self.source_scale,
+            Components::TransferFunction { .. } => self.transfer_function_output(component_id).1,
+            Components::TransmissionLine { impedance, delay } => {
+                let Some(time_step) = &self.time_step else {
+                    return 0.0;
+                };
+                let ComponentState::TransmissionLine { history } = &self.states[&component_id] else {
+                    panic!("Transmission line state expected");
+                };
+
+                // The wave that arrives at a port left the other port one delay earlier.
+                let sample = transmission_line::delayed(history, time_step.time - delay);
+                if branch_id == self.get_branch_id_from_component_id(&component_id) {
+                    sample.v2 + sample.i2 * *impedance
+                } else {
+                    sample.v1 + sample.i1 * *impedance
+                }
+            }
+            Components::Supercapacitor { esr, .. } => {
+                let Some(time_step) = &self.time_step else {
+                    return 0.0;
+                };
+
+                // The voltage of the capacitance is not an unknown, but it is the voltage of the
+                // supercapacitor minus the ESR drop.
+                let i_previous = time_step.previous[self.nodes.len() - 1 + branch_id];
+                let v_previous = self.bipole_voltage(&time_step.previous, &TerminalID::new(component_id.0, 1));
+                v_previous - esr * i_previous
+            }
+            Components::Battery { ocv, .. } => {
+                let ComponentState::Battery { soc } = self.states[&component_id] else {
+                    panic!("Battery state expected");
+                };
+                table::interpolate(ocv, soc).0
+            }
+            Components::DcMotor { armature_inductance, back_emf_constant, .. } => {
+                let ComponentState::DcMotor { speed } = self.states[&component_id] else {
+                    panic!("DC motor state expected");
+                };
+                let back_emf = back_emf_constant * speed;
+
+                let Some(time_step) = &self.time_step else {
+                    return back_emf;
+                };
+
+                let i_previous = time_step.previous[self.nodes.len() - 1 + branch_id];
+                back_emf - i_previous * (*armature_inductance / time_step.dt)
+            }
+            Components::Relay { coil_inductance, .. } => {
+                let Some(time_step) = &self.time_step else {
+                    return 0.0;
+                };
+
+                let i_previous = time_step.previous[self.nodes.len() - 1 + branch_id];
+                -i_previous * (*coil_inductance / time_step.dt)
+            }
+            Components::SaturatingTransformer { magnetizing_inductance, saturation_flux, .. } => {
+                let Some(time_step) = &self.time_step else {
+                    return 0.0;
+                };
+                if branch_id == self.get_branch_id_from_component_id(&component_id) {
+                    // The ideal transformer.
+                    return 0.0;
+                }
+
+                let idx = self.nodes.len() - 1 + branch_id;
+                let (flux, inductance) = magnetic_core::flux(*magnetizing_inductance, *saturation_flux, self.guess[idx]);
+                let (flux_previous, _) = magnetic_core::flux(*magnetizing_inductance, *saturation_flux, time_step.previous[idx]);
+                (flux - inductance * self.guess[idx] - flux_previous) / time_step.dt
+            }
+            Components::Regulator { nominal_voltage, dropout_voltage, current_limit, .. } => {
+                match self.regulator_region(component_id) {
+                    regulator::Region::Regulation => *nominal_voltage,
+                    regulator::Region::Dropout => -dropout_voltage,
+                    regulator::Region::CurrentLimit => -current_limit,
+                }
+            }
+            Components::OpAmp { .. } => {
+                match self.opamp_output(component_id) {
+                    opamp::Output::Linear { offset, .. } => offset,
+                    opamp::Output::Clipped(e) => e,
+                }
+            }
+            _ => panic!("Branch component expected"),
+        }
+    }
+
+    /** Returns the sample of a noise source over the current time step, 0 at DC. */
+    fn noise_sample(&self, component_id: ComponentID) -> f64 {
+        let (Components::NoiseVoltage { distribution, seed } | Components::NoiseCurrent { distribution, seed }) = &self.circuit.components[component_id.0] else {
+            panic!("Noise source expected");
+        };
+        let ComponentState::Noise { step } = self.states[&component_id] else {
+            panic!("Noise state expected");
+        };
+
+        if self.time_step.is_none() {
+            return 0.0;
+        }
+        noise::sample(*distribution, *seed, step)
+    }
+
+    /** Returns the coefficients of the derivative at the end of a time step, for the integration method. */
+    fn derivative(&self, time_step: &TimeStep) -> Derivative {
+        let dt = time_step.dt;
+        match (self.integration, &self.older) {
+            (Integration::Trapezoidal, _) => Derivative { alpha: 2.0 / dt, c1: -2.0 / dt, c2: 0.0, gamma: 1.0 },
+            (Integration::Gear2, Some((_, dt_previous))) => {
+                // Variable step BDF2, omega being the ratio of the step to the previous one.
+                let omega = dt / dt_previous;
+                Derivative {
+                    alpha: (1.0 + 2.0 * omega) / ((1.0 + omega) * dt),
+                    c1: -(1.0 + omega) / dt,
+                    c2: omega * omega / ((1.0 + omega) * dt),
+                    gamma: 0.0,
+                }
+            }
+            (Integration::BackwardEuler | Integration::Gear2, _) => Derivative { alpha: 1.0 / dt, c1: -1.0 / dt, c2: 0.0, gamma: 0.0 },
+        }
+    }
+
+    /** Returns c1 * x_n-1 + c2 * x_n-2 for a quantity x read from the unknowns. */
+    fn derivative_history(&self, derivative: &Derivative, read: impl Fn(&DVector<f64>) -> f64) -> f64 {
+        let Some(time_step) = &self.time_step else {
+            return 0.0;
+        };
+
+        let mut history = derivative.c1 * read(&time_step.previous);
+        if let Some((older, _)) = &self.older {
+            if derivative.c2 != 0.0 {
+                history += derivative.c2 * read(older);
+            }
+        }
+        history
+    }
+
+    /** Returns the resistance of a resistor at the temperature of the circuit. */
+    fn get_resistance(&self, component_id: ComponentID, nominal: f64) -> f64 {
+        self.circuit.temperature_coefficients
+            .iter()
+            .filter(|coefficient| coefficient.component == component_id)
+            .fold(nominal, |resistance, coefficient| resistance * coefficient.factor(self.component_temperature(component_id)))
+    }
+
+    /** Returns the resistance of a fuse in the given state. */
+    fn get_fuse_resistance(&self, component_id: ComponentID, state: &ComponentState) -> f64 {
+        match (&self.circuit.components[component_id.0], state) {
+            (_, ComponentState::Fuse { blown: true, .. }) => fuse::BLOWN_RESISTANCE,
+            (Components::Fuse { resistance, .. }, _) => *resistance,
+            _ => panic!("Fuse expected"),
+        }
+    }
+
+    /** Returns the branch ID and mutual inductance of every inductor coupled to the given one. */
+    fn get_mutual_inductances(&self, component_id: ComponentID) -> Vec<(usize, f64)> {
+        let inductance = |component_id: ComponentID| match self.circuit.components[component_id.0] {
+            Components::Inductor(inductance) => inductance,
+            _ => panic!("Inductor expected"),
+        };
+
+        self.circuit.couplings
+            .iter()
+            .filter_map(|coupling| {
+                let (left, right) = coupling.inductors;
+                let other = if left == component_id {
+                    right
+                } else if right == component_id {
+                    left
+                } else {
+                    return None;
+                };
+
+                let mutual = coupling.k * (inductance(left) * inductance(right)).sqrt();
+                Some((self.get_branch_id_from_component_id(&other), mutual))
+            })
+            .collect()
+    }
+
+    /** Evaluates the internal source of an op-amp around the guess. */
+    fn opamp_output(&self, component_id: ComponentID) -> opamp::Output {
+        let Components::OpAmp { gain, bandwidth, output_resistance, slew_rate, rails } = &self.circuit.components[component_id.0] else {
+            panic!("Op-amp expected");
+        };
+
+        let node_ids = [0, 1, 2].map(|idx| self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx)));
+        let idx = self.nodes.len() - 1 + self.get_branch_id_from_component_id(&component_id);
+
+        let vd = self.node_voltage(&self.guess, node_ids[0]) - self.node_voltage(&self.guess, node_ids[1]);
+        let step = self.time_step.as_ref().map(|time_step| {
+            let e_previous = self.node_voltage(&time_step.previous, node_ids[2]) - time_step.previous[idx] * *output_resistance;
+            (time_step.dt, e_previous)
+        });
+
+        opamp::output(*gain, *bandwidth, *slew_rate, *rails, vd, step)
+    }
+
+    /**
+    Represents the intensity entering a terminal of a component whose output terminal is connected
+    to a rail terminal through a resistance, as a vector of the dimensions. The other terminals do
+    not draw any intensity.
+    */
+    fn unknown_rail_output<T: ComplexField<RealField = f64>>(&self, terminal_id: TerminalID, output_idx: usize, rail_idx: usize, resistance: f64) -> DVector<T> {
+        let component_id = terminal_id.component_id;
+        let v_output = self.unknown_terminal_voltage::<T>(component_id, output_idx);
+        let v_rail = self.unknown_terminal_voltage::<T>(component_id, rail_idx);
+
+        if terminal_id.idx == output_idx {
+            (v_output - v_rail).unscale(resistance)
+        } else if terminal_id.idx == rail_idx {
+            (v_rail - v_output).unscale(resistance)
+        } else {
+            DVector::zeros(self.n)
+        }
+    }
+
+    /** Selects the region of a regulator around the guess. */
+    fn regulator_region(&self, component_id: ComponentID) -> regulator::Region {
+        let Components::Regulator { nominal_voltage, dropout_voltage, output_impedance, current_limit } = &self.circuit.components[component_id.0] else {
+            panic!("Regulator expected");
+        };
+
+        let v = [0, 1, 2].map(|idx| {
+            let node_id = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx));
+            self.node_voltage(&self.guess, node_id)
+        });
+        let i_out = -self.guess[self.nodes.len() - 1 + self.get_branch_id_from_component_id(&component_id)];
+
+        regulator::region(*nominal_voltage, *dropout_voltage, *output_impedance, *current_limit, v, i_out)
+    }
+
+    /**
+    Returns the output of a transfer function block as gain * (V3 - V2) + offset, over the current
+    time step or in DC.
+    */
+    fn transfer_function_output(&self, component_id: ComponentID) -> (f64, f64) {
+        let Components::TransferFunction { numerator, denominator } = &self.circuit.components[component_id.0] else {
+            panic!("Transfer function expected");
+        };
+        let ComponentState::TransferFunction { x } = &self.states[&component_id] else {
+            panic!("Transfer function state expected");
+        };
+
+        let realization = transfer_function::realize(numerator, denominator);
+        match (&self.time_step, realization.steady_state(1.0)) {
+            (Some(time_step), _) => realization.discretize(x, time_step.dt),
+            (None, Some(steady_state)) => (realization.d + realization.c.dot(&steady_state), 0.0),
+            // An integrator holds its output at DC.
+            (None, None) => (realization.d, realization.c.dot(x)),
+        }
+    }
+
+    /** Evaluates an expression (see `Expression`) on the unknowns (X) at a time. */
+    pub fn evaluate_expression(&self, unknowns: &DVector<f64>, expression: &Expression, time: f64) -> f64 {
+        let value = |variable: Variable| match variable {
+            Variable::Voltage(terminal_id) => self.terminal_voltage(unknowns, terminal_id),
+            Variable::Intensity(component_id) => unknowns[self.nodes.len() - 1 + self.get_branch_id_from_component_id(&component_id)],
+        };
+        expression.evaluate(&value, time)
+    }
+
+    /**
+    Linearizes an expression around the guess, as the vector of its derivatives with respect to the
+    unknowns and a constant: f(X) ~ derivatives * X + constant.
+    */
+    fn linearize_expression(&self, expression: &Expression) -> (DVector<f64>, f64) {
+        let value = |variable: Variable| match variable {
+            Variable::Voltage(terminal_id) => self.node_voltage(&self.guess, self.get_node_id_from_terminal_id(&terminal_id)),
+            Variable::Intensity(component_id) => self.guess[self.nodes.len() - 1 + self.get_branch_id_from_component_id(&component_id)],
+        };
+
+        let mut derivatives = DVector::zeros(self.n);
+        let mut constant = expression.evaluate(&value, self.time());
+        for variable in expression.variables() {
+            let (_, derivative) = expression.evaluate_derivative(&value, self.time(), Some(variable));
+            let unknown = match variable {
+                Variable::Voltage(terminal_id) => self.unknown_terminal_voltage(terminal_id.component_id, terminal_id.idx),
+                Variable::Intensity(component_id) => self.unknown_branch_intensity(self.get_branch_id_from_component_id(&component_id)),
+            };
+            constant -= derivative * value(variable);
+            derivatives += unknown * derivative;
+        }
+
+        (derivatives, constant)
+    }
+
+    /** Returns the result (Y) of the matrix equation (M * X = Y), without the custom components. */
+    fn get_result_vector(&self) -> DVector<f64> {
+        let mut result = DVector::zeros(self.n);
+
+        for node_id in 1..self.nodes.len() {
+            // Sum of all currents in the node, the constant parts are moved to this side.
+            let constant: f64 = self.nodes[node_id]
+                .iter()
+                .map(|terminal_id| self.get_component_intensity_constant(*terminal_id))
+                .sum();
+            result[node_id - 1] = -constant;
+        }
+
+        for branch_id in 0..self.branches.len() {
+            result[self.nodes.len() - 1 + branch_id] = self.get_branch_constant(branch_id);
+        }
+
+        result
+    }
+
 
-impl Simulator {
     /**
-    Creates a new simulator.
+    Returns the other terminal of a two-terminal component, or of the first pair of terminals of a
+    component with more terminals. Other terminals are addressed by their index.
+    */
+    fn get_other_terminal(terminal_id: &TerminalID) -> TerminalID {
+        if terminal_id.idx == 0 {
+            TerminalID::new(terminal_id.component_id.0, 1)
+        } else if terminal_id.idx == 1 {
+            TerminalID::new(terminal_id.component_id.0, 0)
+        } else {
+            panic!("Invalid terminal index")
+        }
+    }
 
-    The simulator tries to solve M * X = Y, where M is the matrix of the circuit, X is the unknowns
-    and Y is the result.
+    /** Returns the Node ID from which a terminal is connected. */
+    fn get_node_id_from_terminal_id(&self, terminal_id: &TerminalID) -> usize {
+        self.terminal_to_node[terminal_id]
+    }
 
-    ## Result (Y):
-    transpose(Y) = [I_1, I_2, ..., I_n, V_g0, V_g1, ..., V_gm]
+    /** Return the branch ID from the component ID. */
+    fn get_branch_id_from_component_id(&self, component_id: &ComponentID) -> usize {
+        self.component_id_to_branch_id[component_id]
+    }
 
-    Where:
-    - Where I_i is the intensity that goes through the node i. I_i = 0 as per the Kirchhoff's law.
-    - V_sj is the voltage of the "voltage generator" j.
+    fn get_component_id_from_branch_id(&self, branch_id: usize) -> ComponentID {
+        self.branches[branch_id]
+    }
 
-    ## Unknowns (X):
-    transpose(X) = [V_1, V_2, ..., V_n, I_g0, I_g1, ..., I_gm]
 
-    Where:
-    - V_i is the voltage of the node i. NOTE: By convention, V_0 = 0.
-    - I_sj is the intensity that goes through the voltage generator j.
-    */
-    pub fn new(circuit: Circuit) -> Self {
-        let terminal_ids: Vec<TerminalID> = circuit.terminal_edges
-            .iter()
-            .flat_map(|(left, right)| [*left, *right])
-            .collect();
+    /** Reads the voltage of a node from the solved unknowns (X). */
+    fn node_voltage<T: ComplexField>(&self, unknowns: &DVector<T>, node_id: usize) -> T {
+        if node_id == 0 {
+            // By convention, the node id=0 is the ground node.
+            return T::zero();
+        }
 
-        let nodes = {
-            let mut terminal_disjoint_set = DisjointSet::new(terminal_ids);
+        unknowns[node_id - 1].clone()
+    }
 
-            for (left, right) in &circuit.terminal_edges {
-                terminal_disjoint_set.merge(left.clone(), right.clone());
-            }
-            terminal_disjoint_set.into_terminal_groups()
-        };
+    /** Reads the voltage across a bipole from the solved unknowns (X), as seen from the given terminal. */
+    fn bipole_voltage(&self, unknowns: &DVector<f64>, output_terminal_id: &TerminalID) -> f64 {
+        let input_terminal_id = Self::get_other_terminal(output_terminal_id);
 
-        let vgenerators: Vec<ComponentID> = circuit.components
-            .iter()
-            .enumerate()
-            .filter_map(|(component_id, component)| {
-                match component {
-                    Components::VoltageGenerator(_) => Some(ComponentID(component_id)),
-                    _ => None,
-                }
-            })
-            .collect();
+        let node_output = self.get_node_id_from_terminal_id(output_terminal_id);
+        let node_input = self.get_node_id_from_terminal_id(&input_terminal_id);
 
+        self.node_voltage(unknowns, node_output) - self.node_voltage(unknowns, node_input)
+    }
 
+    /** Represents the voltage across a bipole, as seen from the given terminal, as a vector of the dimensions. */
+    fn unknown_bipole_voltage<T: ComplexField>(&self, output_terminal_id: &TerminalID) -> DVector<T> {
+        let input_terminal_id = Self::get_other_terminal(output_terminal_id);
 
-        let terminal_id_to_node_id = {
-            let mut m = HashMap::new();
-            for (node_id, node) in nodes.iter().enumerate() {
-                for terminal_id in node {
-                    m.insert(terminal_id.clone(), node_id);
-                }
-            }
-            m
+        let node_output = self.get_node_id_from_terminal_id(output_terminal_id);
+        let node_input = self.get_node_id_from_terminal_id(&input_terminal_id);
+
+        self.unknown_node_voltage(node_output) - self.unknown_node_voltage(node_input)
+    }
+
+    /** Represents the voltage of the node a terminal is connected to, as a vector of the dimensions. */
+    fn unknown_terminal_voltage<T: ComplexField>(&self, component_id: ComponentID, idx: usize) -> DVector<T> {
+        let node_id = self.get_node_id_from_terminal_id(&TerminalID::new(component_id.0, idx));
+        self.unknown_node_voltage(node_id)
+    }
+
+    /** Represents the voltage of a node as a unit vector. */
+    fn unknown_node_voltage<T: ComplexField>(&self, node_id: usize) -> DVector<T> {
+        if node_id == 0 {
+            // By convention, the node id=0 is the ground node.
+            return DVector::zeros(self.n);
+        }
+
+        let idx = node_id - 1;
+
+        let mut result = DVector::zeros(self.n);
+        result[idx] = T::one();
+        result
+    }
+
+    /** Represents the intensity that go through a branch as a unit vector. */
+    fn unknown_branch_intensity<T: ComplexField>(&self, branch_id: usize) -> DVector<T> {
+        let idx = self.nodes.len() - 1 + branch_id;
+
+        let mut result = DVector::zeros(self.n);
+        result[idx] = T::one();
+        result
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Component;
+    use crate::event::{Crossing, Stop, StopReason};
+    use crate::fault::Fault;
+    use std::cell::Cell;
+    use std::f64::consts::PI;
+    use std::rc::Rc;
+
+    /** An edge between two terminals, each being (component, idx). */
+    type Edge = ((usize, usize), (usize, usize));
+
+    /** Builds a simulator of the components with the given edges. */
+    fn simulator(components: Vec<Components>, edges: &[Edge]) -> Simulator {
+        Simulator::new(Circuit::new(
+            components,
+            edges.iter().map(|(left, right)| (TerminalID::new(left.0, left.1), TerminalID::new(right.0, right.1))).collect(),
+        ))
+    }
+
+    /** Returns the voltage of the terminal idx of a component from the solved unknowns. */
+    fn voltage(simulator: &Simulator, unknowns: &DVector<f64>, component_id: usize, idx: usize) -> f64 {
+        simulator.node_voltage(unknowns, simulator.get_node_id_from_terminal_id(&TerminalID::new(component_id, idx)))
+    }
+
+    #[test]
+    fn diode_forward_drop() {
+        let (is, n) = (1e-14, 1.0);
+        let mut simulator = simulator(
+            vec![Components::VoltageGenerator(5.0), Components::Resistor(1e3), Components::Diode { is, n }],
+            &[((0, 1), (1, 0)), ((1, 1), (2, 0)), ((2, 1), (0, 0))],
+        );
+        let unknowns = simulator.solve();
+
+        let v = voltage(&simulator, &unknowns, 2, 0) - voltage(&simulator, &unknowns, 2, 1);
+        assert!((0.6..0.75).contains(&v), "forward drop of {}V", v);
+        // The intensity through the resistor goes through the diode.
+        let (intensity, _) = diode::shockley(is, n, v);
+        assert!(((5.0 - v) / 1e3 - intensity).abs() < 1e-6 * intensity);
+    }
+
+    #[test]
+    fn switch_toggles_at_scheduled_times() {
+        let mut simulator = simulator(
+            vec![
+                Components::VoltageGenerator(10.0),
+                Components::Resistor(1e3),
+                Components::Switch { on_resistance: 1e-3, off_resistance: 1e9, closed: true, toggle_times: vec![1e-3, 2e-3] },
+            ],
+            &[((0, 1), (1, 0)), ((1, 1), (2, 0)), ((2, 1), (0, 0))],
+        );
+
+        for (time, closed) in [(0.0, true), (1.5e-3, false), (2.5e-3, true)] {
+            let previous = DVector::zeros(simulator.n);
+            simulator.set_time_step(Some(TimeStep { time, dt: 1e-3, previous }));
+            let unknowns = simulator.solve();
+            let v = voltage(&simulator, &unknowns, 2, 0) - voltage(&simulator, &unknowns, 2, 1);
+            let expected = if closed { 10.0 * 1e-3 / (1e3 + 1e-3) } else { 10.0 * 1e9 / (1e3 + 1e9) };
+            assert!((v - expected).abs() < 1e-9, "V({}) = {} instead of {}", time, v, expected);
+        }
+    }
+
+    #[test]
+    fn relay_contacts_have_hysteresis() {
+        // The coil is driven by the generator 0, the contacts are in series with a 1 kΩ resistor
+        // across the generator 2.
+        let mut simulator = simulator(
+            vec![
+                Components::VoltageGenerator(0.0),
+                Components::Relay {
+                    coil_resistance: 100.0,
+                    coil_inductance: 1e-3,
+                    pull_in_current: 0.05,
+                    drop_out_current: 0.02,
+                    on_resistance: 1e-3,
+                    off_resistance: 1e9,
+                },
+                Components::VoltageGenerator(10.0),
+                Components::Resistor(1e3),
+            ],
+            &[((0, 1), (1, 0)), ((1, 1), (0, 0)), ((2, 1), (3, 0)), ((3, 1), (1, 2)), ((1, 3), (0, 0)), ((2, 0), (0, 0))],
+        );
+
+        // The coil intensity goes up to 60 mA and back down, the contacts stay closed until it
+        // falls below the drop-out current.
+        for (coil_voltage, closed) in [(3.0, false), (6.0, true), (3.0, true), (1.0, false)] {
+            simulator.circuit.components[0] = Components::VoltageGenerator(coil_voltage);
+            let unknowns = simulator.solve();
+            simulator.commit(&unknowns);
+
+            let unknowns = simulator.solve();
+            let v = voltage(&simulator, &unknowns, 1, 2) - voltage(&simulator, &unknowns, 1, 3);
+            assert_eq!(v < 1.0, closed, "contacts at {}V with a {}V coil", v, coil_voltage);
+        }
+    }
+
+    #[test]
+    fn fuse_melts_after_its_i2t() {
+        let mut simulator = simulator(
+            vec![
+                Components::VoltageGenerator(10.0),
+                Components::Resistor(5.0),
+                Components::Fuse { resistance: 0.01, rated_current: 1.0, melting_i2t: Some(0.01) },
+            ],
+            &[((0, 1), (1, 0)), ((1, 1), (2, 0)), ((2, 1), (0, 0))],
+        );
+
+        // About 2A goes through the fuse, which gets 4e-3 A²s per step and melts on the third one.
+        let mut unknowns = DVector::zeros(simulator.n);
+        for step in 1..=3 {
+            assert!(matches!(simulator.states[&ComponentID(2)], ComponentState::Fuse { blown: false, .. }), "blown before step {}", step);
+            simulator.set_time_step(Some(TimeStep { time: step as f64 * 1e-3, dt: 1e-3, previous: unknowns }));
+            unknowns = simulator.solve();
+            simulator.commit(&unknowns);
+        }
+        assert!(matches!(simulator.states[&ComponentID(2)], ComponentState::Fuse { blown: true, .. }));
+
+        let unknowns = simulator.solve();
+        let v = voltage(&simulator, &unknowns, 2, 0) - voltage(&simulator, &unknowns, 2, 1);
+        assert!((v.abs() - 10.0).abs() < 1e-9, "{}V across the blown fuse", v);
+    }
+
+    #[test]
+    fn memristor_state_follows_the_charge() {
+        let (r_on, r_off, drift) = (100.0, 16e3, 1e3);
+        let mut simulator = simulator(
+            vec![Components::VoltageGenerator(1.0), Components::Memristor { r_on, r_off, drift, x: 0.5 }],
+            &[((0, 1), (1, 0)), ((1, 1), (0, 0))],
+        );
+        let state = |simulator: &Simulator| match simulator.states[&ComponentID(1)] {
+            ComponentState::Memristor { x } => x,
+            _ => panic!("Memristor state expected"),
         };
 
-        let component_id_to_vgenerator_id = {
-            let mut m = HashMap::new();
-            for (generator_id, component_id) in vgenerators.iter().enumerate() {
-                m.insert(*component_id, generator_id);
-            }
-            m
+        // A DC solve does not move the state.
+        let unknowns = simulator.solve();
+        simulator.commit(&unknowns);
+        assert_eq!(state(&simulator), 0.5);
+
+        // The intensity enters by terminal 0, so the doped region widens by drift * i * dt.
+        let mut x = 0.5;
+        let mut previous = unknowns;
+        for step in 1..=10 {
+            simulator.set_time_step(Some(TimeStep { time: step as f64 * 1e-3, dt: 1e-3, previous }));
+            let unknowns = simulator.solve();
+            simulator.commit(&unknowns);
+            x += drift * 1e-3 / memristor::resistance(r_on, r_off, x);
+            assert!((state(&simulator) - x).abs() < 1e-12, "x = {} instead of {}", state(&simulator), x);
+            previous = unknowns;
+        }
+    }
+
+    #[test]
+    fn battery_discharges_into_a_load() {
+        // A 1 mAh battery from 3V empty to 4.2V full, with a 0.1 Ω internal resistance.
+        let mut simulator = simulator(
+            vec![
+                Components::Battery { ocv: vec![(0.0, 3.0), (1.0, 4.2)], internal_resistance: 0.1, capacity: 1e-3, soc: 0.5 },
+                Components::Resistor(10.0),
+            ],
+            &[((0, 1), (1, 0)), ((1, 1), (0, 0))],
+        );
+        let soc = |simulator: &Simulator| match simulator.states[&ComponentID(0)] {
+            ComponentState::Battery { soc } => soc,
+            _ => panic!("Battery state expected"),
         };
 
-        let n = nodes.len() - 1 + vgenerators.len();
+        let mut previous = simulator.solve();
+        for step in 1..=5 {
+            simulator.set_time_step(Some(TimeStep { time: step as f64, dt: 1.0, previous }));
+            let unknowns = simulator.solve();
+            let before = soc(&simulator);
+            simulator.commit(&unknowns);
 
-        Self { circuit, component_id_to_vgenerator_id, nodes, terminal_to_node: terminal_id_to_node_id, n, vgenerators }
+            // The voltage follows the OCV of the state of charge during the step.
+            let v = voltage(&simulator, &unknowns, 0, 1) - voltage(&simulator, &unknowns, 0, 0);
+            assert!((v - (3.0 + 1.2 * before) * 10.0 / 10.1).abs() < 1e-9, "{}V at {} of charge", v, before);
+            // The intensity leaves by the positive terminal and discharges the battery.
+            let intensity = unknowns[simulator.nodes.len() - 1];
+            assert!(intensity < 0.0);
+            assert!((soc(&simulator) - (before + intensity / 3.6)).abs() < 1e-12);
+            previous = unknowns;
+        }
     }
 
-    pub fn simulate(&self) {
-        let mat = self.get_matrix();
-        println!("Matrix: {}", mat);
+    #[test]
+    fn transmission_line_delays_a_step() {
+        // A 1V step through a matched 50 Ω source into a 50 Ω line with a 10 ns delay, loaded by 50 Ω.
+        let mut simulator = simulator(
+            vec![
+                Components::VoltageGenerator(0.0),
+                Components::Resistor(50.0),
+                Components::TransmissionLine { impedance: 50.0, delay: 10e-9 },
+                Components::Resistor(50.0),
+            ],
+            &[((0, 1), (1, 0)), ((1, 1), (2, 1)), ((2, 0), (0, 0)), ((2, 3), (3, 0)), ((3, 1), (0, 0)), ((2, 2), (0, 0))],
+        );
+        let mut previous = simulator.solve();
+        simulator.commit(&previous);
 
-        let result = self.get_result_vector();
-        println!("Result: {:?}", result);
+        simulator.circuit.components[0] = Components::VoltageGenerator(1.0);
+        for step in 1..=30 {
+            let time = step as f64 * 1e-9;
+            simulator.set_time_step(Some(TimeStep { time, dt: 1e-9, previous }));
+            let unknowns = simulator.solve();
+            simulator.commit(&unknowns);
 
-        // let cloned_mats = vec![mat.clone(); 10_000];
-        // let start = std::time::Instant::now();
-        // for cloned_mat in cloned_mats {
-        //     cloned_mat.lu().solve(&result).unwrap();
-        // }
-        // println!("Elapsed: {:?}", start.elapsed());
+            // Half of the step enters the line, and reaches the far end one delay later.
+            let near = voltage(&simulator, &unknowns, 2, 1) - voltage(&simulator, &unknowns, 2, 0);
+            let far = voltage(&simulator, &unknowns, 2, 3) - voltage(&simulator, &unknowns, 2, 2);
+            assert!((near - 0.5).abs() < 1e-9, "{}V at the near end at {}s", near, time);
+            let expected = if time < 10e-9 { 0.0 } else if time < 11e-9 { far } else { 0.5 };
+            assert!((far - expected).abs() < 1e-9, "{}V at the far end at {}s", far, time);
+            previous = unknowns;
+        }
+    }
 
-        let unknowns = mat.lu().solve(&result).unwrap();
-        println!("Unknowns: {:?}", unknowns);
+    #[test]
+    #[should_panic(expected = "must not be longer than the delay")]
+    fn transmission_line_rejects_long_time_steps() {
+        let mut simulator = simulator(
+            vec![Components::VoltageGenerator(1.0), Components::TransmissionLine { impedance: 50.0, delay: 10e-9 }, Components::Resistor(50.0)],
+            &[((0, 1), (1, 1)), ((1, 0), (0, 0)), ((1, 3), (2, 0)), ((2, 1), (1, 2)), ((1, 2), (0, 0))],
+        );
+        let previous = simulator.solve();
+        simulator.set_time_step(Some(TimeStep { time: 20e-9, dt: 20e-9, previous }));
+    }
 
-        for component_id in 0..self.circuit.components.len() {
-            let component = &self.circuit.components[component_id];
-            let input_terminal_id = TerminalID::new(component_id, 0);
-            let output_terminal_id = TerminalID::new(component_id, 1);
+    #[test]
+    fn buck_converter_output() {
+        let mut simulator = simulator(
+            vec![Components::VoltageGenerator(12.0), Components::BuckConverter { duty: 0.25, inductance: 1e-3, capacitance: 1e-6 }, Components::Resistor(10.0)],
+            &[((0, 1), (1, 0)), ((1, 2), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (0, 0))],
+        );
+        let unknowns = simulator.solve();
+        let v = voltage(&simulator, &unknowns, 2, 0) - voltage(&simulator, &unknowns, 2, 1);
+        assert!((v - 3.0).abs() < 1e-9, "{}V at the output", v);
+    }
 
-            let node_input = self.get_node_id_from_terminal_id(&input_terminal_id);
-            let node_output = self.get_node_id_from_terminal_id(&output_terminal_id);
+    #[test]
+    fn noise_voltage_is_a_new_sample_at_each_step() {
+        let distribution = crate::model::NoiseDistribution::Gaussian { standard_deviation: 1.0 };
+        let mut simulator = simulator(
+            vec![Components::NoiseVoltage { distribution, seed: 7 }, Components::Resistor(1e3)],
+            &[((0, 1), (1, 0)), ((1, 1), (0, 0))],
+        );
+        let mut previous = simulator.solve();
+        assert!(previous.iter().all(|x| *x == 0.0), "The noise is not 0 at DC");
+        simulator.commit(&previous);
 
-            let v_input = if node_input >= 1  {
-                unknowns[node_input - 1]
-            } else {
-                0.0
-            };
-            let v_output = if node_output >= 1 {
-                unknowns[node_output - 1]
-            } else {
-                0.0
-            };
+        for step in 1..=5 {
+            simulator.set_time_step(Some(TimeStep { time: step as f64 * 1e-3, dt: 1e-3, previous }));
+            let unknowns = simulator.solve();
+            simulator.commit(&unknowns);
+            let v = voltage(&simulator, &unknowns, 0, 1) - voltage(&simulator, &unknowns, 0, 0);
+            assert!((v - noise::sample(distribution, 7, step - 1)).abs() < 1e-12, "{}V at step {}", v, step);
+            previous = unknowns;
+        }
+    }
 
-            let v = v_output - v_input;
+    #[test]
+    fn prbs_generator_holds_each_bit_for_a_period() {
+        let mut simulator = simulator(
+            vec![Components::PrbsGenerator { low: -1.0, high: 2.0, bit_period: 1e-3, seed: 3 }, Components::Resistor(1e3)],
+            &[((0, 1), (1, 0)), ((1, 1), (0, 0))],
+        );
 
-            match component {
-                Components::Resistor(_) => {
-                    println!("Resistor {}: {}V", &component_id, v);
-                }
-                Components::VoltageGenerator(_) => {
-                    println!("Voltage Generator {}: {}V", &component_id, v);
-                }
+        let mut highs = 0;
+        for index in 0..64 {
+            let bit = noise::bit(3, index);
+            highs += bit as usize;
+            // Early and late in the bit period.
+            for time in [(index as f64 + 0.1) * 1e-3, (index as f64 + 0.9) * 1e-3] {
+                let previous = DVector::zeros(simulator.n);
+                simulator.set_time_step(Some(TimeStep { time, dt: 1e-4, previous }));
+                let unknowns = simulator.solve();
+                let v = voltage(&simulator, &unknowns, 0, 1) - voltage(&simulator, &unknowns, 0, 0);
+                let expected = if bit { 2.0 } else { -1.0 };
+                assert!((v - expected).abs() < 1e-12, "{}V for the bit {} at {}s", v, index, time);
             }
         }
+        assert!((16..48).contains(&highs), "{} high bits out of 64", highs);
     }
 
-    /** Returns the matrix (M) of the equation (M * X = Y). */
-    pub fn get_matrix(&self) -> DMatrix<f64> {
-        let mut rows = Vec::with_capacity(self.n);
-        for node_id in 1..self.nodes.len() {
-            let node_intensity = self.get_node_intensity(node_id);
-            rows.push(node_intensity.transpose());
+    /** A generator driving a 1 kΩ resistor into a 1 µF capacitor to the ground, read on the terminal (3, 1). */
+    fn rc(generator: Components) -> Simulator {
+        simulator(
+            vec![Components::Ground, generator, Components::Resistor(1e3), Components::Capacitor(1e-6)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 1)), ((3, 0), (0, 0))],
+        )
+    }
+
+    #[test]
+    fn rc_step_response() {
+        let step = Components::PulseGenerator { v1: 0.0, v2: 1.0, delay: 0.0, rise: 1e-9, fall: 1e-9, width: 1.0, period: 2.0 };
+        let mut simulator = rc(step);
+        let transient = simulator.transient(5e-3, 1e-6);
+
+        let output = transient.waveform(|unknowns| simulator.terminal_voltage(unknowns, TerminalID::new(3, 1)));
+        for (time, v) in transient.times.iter().zip(&output) {
+            let expected = 1.0 - (-time / 1e-3).exp();
+            assert!((v - expected).abs() < 1e-3, "V({}) = {} instead of {}", time, v, expected);
         }
+        assert!(output[0].abs() < 1e-12);
+    }
+
+    #[test]
+    fn rc_starts_from_initial_conditions() {
+        for (initial_condition, v0) in [(None, 0.0), (Some(0.25), 0.25)] {
+            let mut circuit = rc(Components::VoltageGenerator(1.0)).circuit;
+            if let Some(value) = initial_condition {
+                circuit.initial_conditions.push(crate::model::InitialCondition { component: ComponentID(3), value });
+            }
+            let mut simulator = Simulator::new(circuit);
+            simulator.set_use_initial_conditions(true);
+            let transient = simulator.transient(5e-3, 1e-6);
 
-        for vgenerator_id in 0..self.vgenerators.len() {
-            let vgenerator_intensity = self.get_vgenerator_voltage(vgenerator_id);
-            rows.push(vgenerator_intensity.transpose());
+            let output = transient.waveform(|unknowns| simulator.terminal_voltage(unknowns, TerminalID::new(3, 1)));
+            assert!((output[0] - v0).abs() < 1e-6, "starts at {}V instead of {}V", output[0], v0);
+            for (time, v) in transient.times.iter().zip(&output) {
+                let expected = 1.0 - (1.0 - v0) * (-time / 1e-3).exp();
+                assert!((v - expected).abs() < 1e-3, "V({}) = {} instead of {}", time, v, expected);
+            }
         }
+    }
+
+    #[test]
+    fn gmin_stepping_rescues_a_diode() {
+        let diode = || simulator(
+            vec![Components::Ground, Components::VoltageGenerator(5.0), Components::Resistor(1e3), Components::Diode { is: 1e-14, n: 1.0 }],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))],
+        );
+        let mut reference = diode();
+        let unknowns = reference.solve();
+        assert_eq!(reference.convergence(), Convergence::Newton);
+        let expected = voltage(&reference, &unknowns, 3, 0);
 
-        DMatrix::from_rows(&rows)
+        // Ten iterations are not enough from 0V, but are from each solution of the Gmin steps.
+        let mut simulator = diode();
+        simulator.set_newton_options(NewtonOptions { max_iterations: 10, ..NewtonOptions::default() });
+        let unknowns = simulator.solve();
+        assert_eq!(simulator.convergence(), Convergence::GminStepping);
+        let v = voltage(&simulator, &unknowns, 3, 0);
+        assert!((v - expected).abs() < 1e-9, "{}V instead of {}V", v, expected);
     }
 
+    #[test]
+    fn source_stepping_rescues_a_diode() {
+        // 10A through the diode, too far from 0V for Newton–Raphson and Gmin stepping in ten iterations.
+        let diode = || simulator(
+            vec![Components::Ground, Components::VoltageGenerator(10.0), Components::Resistor(1.0), Components::Diode { is: 1e-14, n: 1.0 }],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))],
+        );
+        let mut reference = diode();
+        let unknowns = reference.solve();
+        let expected = voltage(&reference, &unknowns, 3, 0);
 
-    /** Returns the intensity that goes through a certain node as a vector of the dimensions. */
-    fn get_node_intensity(&self, node_id: usize) -> DVector<f64> {
-        let mut result = DVector::zeros(self.n);
+        let mut simulator = diode();
+        simulator.set_newton_options(NewtonOptions { max_iterations: 10, ..NewtonOptions::default() });
+        let unknowns = simulator.solve();
+        assert_eq!(simulator.convergence(), Convergence::SourceStepping);
+        let v = voltage(&simulator, &unknowns, 3, 0);
+        assert!((v - expected).abs() < 1e-9, "{}V instead of {}V", v, expected);
+    }
 
-        for terminal_id in &self.nodes[node_id] {
-            let intensity = self.get_component_intensity_vector(*terminal_id);
-            result += intensity;
+    #[test]
+    fn rc_corner_frequency() {
+        let mut simulator = rc(Components::SineGenerator { amplitude: 1.0, frequency: 1e3, phase: 0.0, dc_offset: 0.0 });
+        let corner = 1.0 / (2.0 * PI * 1e-3);
+
+        let unknowns = simulator.ac(corner);
+        let h = simulator.terminal_voltage(&unknowns, TerminalID::new(3, 1));
+        assert!((h.norm() - 0.5f64.sqrt()).abs() < 1e-9);
+        assert!((h.arg() + PI / 4.0).abs() < 1e-9);
+
+        let sweep = simulator.ac_sweep(&FrequencySweep::Decade { start: 1.0, stop: 1e5, points_per_decade: 100 });
+        let bode = simulator.bode(&sweep, TerminalID::new(3, 1), TerminalID::new(1, 1));
+        let bandwidth = bode.bandwidth().unwrap();
+        assert!((bandwidth - corner).abs() < 0.01 * corner, "bandwidth {} instead of {}", bandwidth, corner);
+        assert!((bode.phase_degrees.last().unwrap() + 90.0).abs() < 1.0);
+    }
+
+    /** An inverting amplifier of gain -10 driven by a 1V sine, whose output is the terminal (4, 2). */
+    fn inverting_amplifier() -> Simulator {
+        simulator(
+            vec![
+                Components::Ground,
+                Components::SineGenerator { amplitude: 1.0, frequency: 1e3, phase: 0.0, dc_offset: 0.0 },
+                Components::Resistor(1e3),
+                Components::Resistor(1e4),
+                Components::OpAmp { gain: 1e5, bandwidth: 10.0, output_resistance: 1.0, slew_rate: 1e9, rails: (-15.0, 15.0) },
+            ],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (4, 1)), ((3, 0), (4, 1)), ((3, 1), (4, 2)), ((4, 0), (0, 0))],
+        )
+    }
+
+    #[test]
+    fn opamp_closed_loop_bandwidth() {
+        // The bandwidth is the gain-bandwidth product over 11.
+        let mut simulator = inverting_amplifier();
+        let unknowns = simulator.ac(100.0);
+        let h = simulator.terminal_voltage(&unknowns, TerminalID::new(4, 2));
+        assert!((h.norm() - 10.0).abs() < 1e-2, "gain of {}", h.norm());
+        assert!((h.arg().abs() - PI).abs() < 1e-2, "phase of {}", h.arg());
+
+        let unknowns = simulator.ac(1e6 / 11.0);
+        let h = simulator.terminal_voltage(&unknowns, TerminalID::new(4, 2));
+        assert!((h.norm() - 10.0 / 2f64.sqrt()).abs() < 0.02 * 10.0, "gain of {}", h.norm());
+
+        let sweep = simulator.ac_sweep(&FrequencySweep::Decade { start: 10.0, stop: 1e7, points_per_decade: 100 });
+        let bode = simulator.bode(&sweep, TerminalID::new(4, 2), TerminalID::new(1, 1));
+        assert!((bode.gain_db[0] - 20.0).abs() < 0.01);
+        let bandwidth = bode.bandwidth().unwrap();
+        assert!((bandwidth - 1e6 / 11.0).abs() < 0.02 * 1e6 / 11.0, "bandwidth {}", bandwidth);
+    }
+
+    #[test]
+    fn sweep_frequencies() {
+        let linear = FrequencySweep::Linear { start: 10.0, stop: 20.0, points: 3 }.frequencies();
+        assert_eq!(linear, vec![10.0, 15.0, 20.0]);
+
+        let decade = FrequencySweep::Decade { start: 1.0, stop: 1e3, points_per_decade: 2 }.frequencies();
+        assert_eq!(decade.len(), 7);
+        assert!((decade[1] - 10f64.sqrt()).abs() < 1e-12);
+        assert!((decade[6] - 1e3).abs() < 1e-9);
+
+        let octave = FrequencySweep::Octave { start: 100.0, stop: 800.0, points_per_octave: 1 }.frequencies();
+        assert_eq!(octave.len(), 4);
+        assert!((octave[3] - 800.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ac_sweep_matches_single_frequencies() {
+        let mut simulator = rc(Components::SineGenerator { amplitude: 1.0, frequency: 1e3, phase: 0.0, dc_offset: 0.0 });
+        let sweep = simulator.ac_sweep(&FrequencySweep::Decade { start: 10.0, stop: 1e4, points_per_decade: 3 });
+        let response = sweep.response(|unknowns| simulator.terminal_voltage(unknowns, TerminalID::new(3, 1)));
+
+        for (frequency, h) in sweep.frequencies.iter().zip(response) {
+            let unknowns = simulator.ac(*frequency);
+            assert!((simulator.terminal_voltage(&unknowns, TerminalID::new(3, 1)) - h).norm() < 1e-12);
+            // |H| = 1 / sqrt(1 + (wRC)^2).
+            let wrc = 2.0 * PI * frequency * 1e-3;
+            assert!((h.norm() - 1.0 / (1.0 + wrc * wrc).sqrt()).abs() < 1e-9);
         }
+    }
 
-        result
+    #[test]
+    fn monte_carlo_divider() {
+        let mut simulator = simulator(
+            vec![Components::Ground, Components::VoltageGenerator(10.0), Components::Resistor(1e3), Components::Resistor(1e3)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))],
+        );
+        simulator.circuit.tolerances = vec![
+            crate::model::Tolerance { component: ComponentID(2), relative: 0.05 },
+            crate::model::Tolerance { component: ComponentID(3), relative: 0.05 },
+        ];
+        let settings = MonteCarlo { trials: 200, seed: 7, distribution: ToleranceDistribution::Uniform };
+        let measure = |simulator: &mut Simulator| {
+            let unknowns = simulator.solve();
+            simulator.terminal_voltage(&unknowns, TerminalID::new(3, 0))
+        };
+
+        let result = simulator.monte_carlo(&settings, measure);
+        let statistics = result.statistics(|v| *v);
+        // The output is 10 * R3 / (R2 + R3), at most 10 * 1.05 / 2 with ±5% resistors.
+        assert!(statistics.min >= 10.0 * 0.95 / 2.0 && statistics.max <= 10.0 * 1.05 / 2.0);
+        assert!((statistics.mean - 5.0).abs() < 0.05);
+        assert!(statistics.standard_deviation > 0.0);
+
+        // The draws only depend on the seed, and the nominal values are restored.
+        assert_eq!(simulator.monte_carlo(&settings, measure).trials, result.trials);
+        assert!(matches!(simulator.circuit.components[2], Components::Resistor(r) if r == 1e3));
     }
 
-    /** Returns the intensity that goes through a certain component as a vector of the dimensions. */
-    fn get_component_intensity_vector(&self, output_terminal_id: TerminalID) -> DVector<f64> {
-        let component = &self.circuit.components[output_terminal_id.component_id.0];
-        let input_terminal_id = Self::get_other_terminal(&output_terminal_id);
+    #[test]
+    fn thevenin_of_a_divider() {
+        // 12V through 1k into 2k, with a 1mA current source across the output.
+        let mut simulator = simulator(
+            vec![Components::Ground, Components::VoltageGenerator(12.0), Components::Resistor(1e3), Components::Resistor(2e3), Components::CurrentGenerator(1e-3)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0)), ((4, 0), (0, 0)), ((4, 1), (3, 0))],
+        );
+        let thevenin = simulator.thevenin(TerminalID::new(3, 0), TerminalID::new(0, 0));
 
-        match component {
-            Components::Resistor(resistance) => {
-                let node_output = self.get_node_id_from_terminal_id(&output_terminal_id);
-                let node_input = self.get_node_id_from_terminal_id(&input_terminal_id);
+        let resistance = 1e3 * 2e3 / 3e3;
+        // The source injects its intensity into the output, by its terminal 1.
+        let voltage = 12.0 * 2.0 / 3.0 + 1e-3 * resistance;
+        assert!((thevenin.resistance - resistance).abs() < 1e-9);
+        assert!((thevenin.voltage - voltage).abs() < 1e-9);
+        assert!((thevenin.norton_current() - voltage / resistance).abs() < 1e-12);
 
-                let v_output = self.unknown_node_voltage(node_output);
-                let v_input = self.unknown_node_voltage(node_input);
+        // Swapping the nodes changes the sign of the voltage only.
+        let swapped = simulator.thevenin(TerminalID::new(0, 0), TerminalID::new(3, 0));
+        assert!((swapped.voltage + thevenin.voltage).abs() < 1e-9);
+        assert!((swapped.resistance - resistance).abs() < 1e-9);
+    }
 
-                (v_output - v_input) / *resistance
-            }
-            Components::VoltageGenerator(_) => {
-                let generator_id = self.get_vgenerator_id_from_component_id(&output_terminal_id.component_id);
-                let intensity = self.unknown_vgenerator_intensity(generator_id);
+    #[test]
+    fn rc_periodic_steady_state() {
+        // A 1kHz square wave into an RC of 1ms swings between e^-0.5 / (1 + e^-0.5) and 1 / (1 + e^-0.5).
+        let square = Components::PulseGenerator { v1: 0.0, v2: 1.0, delay: 0.0, rise: 1e-9, fall: 1e-9, width: 0.5e-3, period: 1e-3 };
+        let mut simulator = rc(square);
+        let period = simulator.periodic_steady_state(&Shooting::new(1e-3, 1000));
 
-                // This intensity is directed from 0->1. So if we want the intensity on the other
-                // terminal, we need to invert it.
-                if output_terminal_id.idx == 1 {
-                    intensity
-                } else {
-                    -intensity
-                }
+        let output = period.waveform(|unknowns| simulator.terminal_voltage(unknowns, TerminalID::new(3, 1)));
+        let decay = (-0.5f64).exp();
+        assert!((output[0] - decay / (1.0 + decay)).abs() < 5e-3, "V(0) = {}", output[0]);
+        assert!((output[500] - 1.0 / (1.0 + decay)).abs() < 5e-3, "V(T / 2) = {}", output[500]);
+        assert!((output[1000] - output[0]).abs() < 1e-5);
+    }
+
+    #[test]
+    fn s_parameters_of_a_shunt_resistor() {
+        // A 25Ω shunt (two 50Ω in parallel) between two 50Ω ports: S11 = -Z0 / (2R + Z0) and S21 = 2R / (2R + Z0).
+        let shunt = |resistance| simulator(
+            vec![Components::Ground, Components::Resistor(resistance), Components::Resistor(resistance)],
+            &[((1, 0), (0, 0)), ((2, 0), (0, 0)), ((1, 1), (2, 1))],
+        );
+        let port = |positive| Port { positive, negative: TerminalID::new(0, 0), impedance: 50.0 };
+        let ports = [port(TerminalID::new(1, 1)), port(TerminalID::new(2, 1))];
+        let parameters = shunt(50.0).s_parameters(&ports, &FrequencySweep::Linear { start: 1e3, stop: 1e6, points: 3 });
+
+        assert_eq!(parameters.frequencies.len(), 3);
+        for matrix in &parameters.matrices {
+            for (k, j, expected) in [(0, 0, -0.5), (1, 0, 0.5), (0, 1, 0.5), (1, 1, -0.5)] {
+                assert!((matrix[(k, j)] - Complex::new(expected, 0.0)).norm() < 1e-12, "S{}{} = {}", k + 1, j + 1, matrix[(k, j)]);
             }
         }
+
+        // A matched load does not reflect.
+        let parameters = shunt(100.0).s_parameters(&ports[..1], &FrequencySweep::Linear { start: 1e3, stop: 1e3, points: 1 });
+        assert!(parameters.parameter(0, 0)[0].norm() < 1e-12);
     }
 
-    fn get_vgenerator_voltage(&self, vgenerator_id: usize) -> DVector<f64> {
-        let component_id = self.get_component_id_from_vgenerator_id(vgenerator_id);
+    #[test]
+    fn loop_gain_of_a_follower() {
+        // The loop of a voltage follower is broken between the output and the inverting input, its
+        // gain being that of the op-amp: 1e5 with a pole at 10Hz, crossing 0dB at 1MHz.
+        let mut simulator = simulator(
+            vec![
+                Components::Ground,
+                Components::OpAmp { gain: 1e5, bandwidth: 10.0, output_resistance: 1.0, slew_rate: 1e9, rails: (-15.0, 15.0) },
+                Components::VoltageGenerator(0.0),
+            ],
+            &[((1, 0), (0, 0)), ((2, 1), (1, 1)), ((2, 0), (1, 2))],
+        );
+        let loop_gain = simulator.loop_gain(ComponentID(2), &FrequencySweep::Decade { start: 1.0, stop: 1e7, points_per_decade: 50 });
 
-        let terminal_input = TerminalID::new(component_id.0, 0);
-        let terminal_output = TerminalID::new(component_id.0, 1);
+        let low = loop_gain.gains[0];
+        assert!((low.norm() - 1e5 / (1.0 + 0.01f64).sqrt()).abs() < 1e2, "|T| = {}", low.norm());
+        let phase_margin = loop_gain.phase_margin().unwrap();
+        assert!((phase_margin - 90.0).abs() < 1.0, "phase margin {}", phase_margin);
+        assert!(loop_gain.gain_margin().is_none());
+    }
 
-        let node_input = self.get_node_id_from_terminal_id(&terminal_input);
-        let node_output = self.get_node_id_from_terminal_id(&terminal_output);
+    #[test]
+    fn transient_lands_on_events() {
+        let step = Components::PulseGenerator { v1: 0.0, v2: 1.0, delay: 0.0, rise: 1e-9, fall: 1e-9, width: 1.0, period: 2.0 };
+        let mut simulator = rc(step);
+        let events = [
+            Event::Threshold { expression: Expression::parse("V(3.1)").unwrap(), level: 0.5, direction: Crossing::Rising },
+            Event::Breakpoint(0.25e-3),
+            Event::zero_crossing(Expression::parse("V(3.1)").unwrap(), Crossing::Falling),
+        ];
+        let mut heard = Vec::new();
+        let transient = simulator.transient_with_events(1e-3, 1e-5, &events, |occurrence, unknowns| heard.push((*occurrence, unknowns.clone())));
 
-        let v_input = self.unknown_node_voltage(node_input);
-        let v_output = self.unknown_node_voltage(node_output);
+        assert_eq!(heard.len(), 2);
+        assert_eq!(transient.events, heard.iter().map(|(occurrence, _)| *occurrence).collect::<Vec<_>>());
+        let (breakpoint, threshold) = (heard[0].0, heard[1].0);
+        assert_eq!((breakpoint.event, breakpoint.time), (1, 0.25e-3));
+        assert!(transient.times.contains(&0.25e-3));
 
-        v_output - v_input
+        // The analysis lands on the crossing, at tau * ln(2).
+        assert_eq!(threshold.event, 0);
+        assert!((threshold.time - 1e-3 * 2f64.ln()).abs() < 1e-5, "crossing at {}", threshold.time);
+        assert!((simulator.terminal_voltage(&heard[1].1, TerminalID::new(3, 1)) - 0.5).abs() < 1e-5);
     }
 
+    #[test]
+    fn transient_stops_on_conditions() {
+        let step = || Components::PulseGenerator { v1: 0.0, v2: 1.0, delay: 0.0, rise: 1e-9, fall: 1e-9, width: 1.0, period: 2.0 };
+        let charged = Event::Threshold { expression: Expression::parse("V(3.1)").unwrap(), level: 0.9, direction: Crossing::Rising };
+        let settled = [StopCondition::SteadyState { duration: 1e-3, relative_tolerance: 0.0, absolute_tolerance: 1e-3 }];
 
-    /** Returns the result (Y) of the matrix equation (M * X = Y). */
-    fn get_result_vector(&self) -> DVector<f64> {
-        let mut result = DVector::zeros(self.n);
+        // The output reaches 0.9 at tau * ln(10).
+        let (transient, stop) = rc(step()).transient_until(20e-3, 1e-5, &[settled[0].clone(), StopCondition::Event(charged)]);
+        assert_eq!(stop.reason, StopReason::Condition(1));
+        assert!((stop.time - 1e-3 * 10f64.ln()).abs() < 0.02e-3, "stopped at {}", stop.time);
+        assert_eq!(*transient.times.last().unwrap(), stop.time);
 
-        for node_id in 0..(self.nodes.len() - 1) {
-            result[node_id] = 0.0; // Sum of all currents in the node.
+        // It then moves by less than 1mV over the 1ms since tau * ln(1000 * (1 - 1 / e)).
+        let (_, stop) = rc(step()).transient_until(20e-3, 1e-5, &settled);
+        assert_eq!(stop.reason, StopReason::Condition(0));
+        assert!((stop.time - 1e-3 * (1.0 + (1e3 * (1.0 - (-1f64).exp())).ln())).abs() < 0.05e-3, "stopped at {}", stop.time);
+
+        let (_, stop) = rc(step()).transient_until(2e-3, 1e-5, &settled);
+        assert_eq!(stop, Stop { reason: StopReason::Completed, time: 2e-3 });
+    }
+
+    #[test]
+    fn symbolic_transfer_of_an_rc() {
+        let mut simulator = rc(Components::SineGenerator { amplitude: 1.0, frequency: 1e3, phase: 0.0, dc_offset: 0.0 });
+        let transfer = simulator.symbolic_transfer(ComponentID(1), TerminalID::new(3, 1));
+        assert_eq!(transfer.to_string(), "(1) / (R2*C3*s + 1)");
+
+        let corner = 1.0 / (2.0 * PI * 1e-3);
+        let unknowns = simulator.ac(corner);
+        assert!((transfer.response(corner) - simulator.terminal_voltage(&unknowns, TerminalID::new(3, 1))).norm() < 1e-12);
+        // Evaluated with other values, R2 = 2k here.
+        let h = transfer.evaluate(&[2e3, 1e-6], Complex::new(0.0, 2.0 * PI * corner));
+        assert!((h.norm() - 1.0 / 5f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn resistor_heats_up_through_its_thermal_model() {
+        // 10W in a resistor of 10K/W and 1mJ/K heats it by 100K with a time constant of 10ms.
+        let mut simulator = simulator(
+            vec![Components::Ground, Components::VoltageGenerator(10.0), Components::Resistor(10.0)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (0, 0))],
+        );
+        simulator.circuit.thermal_models = vec![crate::model::ThermalModel { component: ComponentID(2), thermal_resistance: 10.0, thermal_capacitance: 1e-3 }];
+        let transient = simulator.transient(10e-3, 1e-5);
+        let heating = simulator.component_temperature(ComponentID(2)) - NOMINAL_TEMPERATURE;
+        assert!((heating - 100.0 * (1.0 - (-1f64).exp())).abs() < 0.1, "heated by {}K", heating);
+        assert_eq!(transient.times.len(), 1001);
+
+        // The circuit and the components without a thermal model stay at the ambient.
+        assert_eq!(simulator.component_temperature(ComponentID(1)), NOMINAL_TEMPERATURE);
+    }
+
+    #[test]
+    fn mixed_signal_loop() {
+        // A 1kHz clock drives a divider through a digital output, read back by a digital input
+        // whose signal is inverted by a gate.
+        let mut simulator = simulator(
+            vec![
+                Components::Ground,
+                Components::DigitalOutput { signal: 0, low: 0.0, high: 5.0, output_resistance: 50.0 },
+                Components::Resistor(950.0),
+                Components::Resistor(1e3),
+                Components::DigitalInput { signal: 1, threshold: 2.0 },
+            ],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0)), ((4, 0), (0, 0)), ((4, 1), (3, 0))],
+        );
+        let mut engine = DigitalEngine::new(3);
+        engine.add_process(Box::new(crate::digital::Clock { output: 0, period: 1e-3 }));
+        engine.add_process(Box::new(crate::digital::Gate { function: crate::model::LogicFunction::Not, inputs: vec![1], output: 2, delay: 1e-6 }));
+        engine.schedule(Change { time: 0.0, signal: 0, value: true });
+        engine.schedule(Change { time: 0.0, signal: 2, value: true });
+
+        let transient = simulator.transient_mixed(2e-3, 1e-4, &mut engine);
+        let output = transient.waveform(|unknowns| simulator.terminal_voltage(unknowns, TerminalID::new(3, 0)));
+        let at = |time: f64| output[transient.times.iter().position(|t| (t - time).abs() < 1e-12).unwrap()];
+        // The output applies from the step following its change, and lands on the edges of the clock.
+        assert_eq!(at(0.0), 0.0);
+        assert!((at(0.4e-3) - 2.5).abs() < 1e-9);
+        assert!(at(0.9e-3).abs() < 1e-9);
+
+        // The input follows at the end of the first step, and the gate 1µs later.
+        let changes: Vec<(usize, bool)> = engine.history().iter().map(|change| (change.signal, change.value)).collect();
+        assert_eq!(&changes[..5], &[(0, true), (2, true), (1, true), (2, false), (0, false)]);
+        assert_eq!(engine.history()[2].time, 1e-4);
+        assert!((engine.history()[3].time - 1.01e-4).abs() < 1e-12);
+        assert!(transient.times.iter().any(|time| (time - 1.01e-4).abs() < 1e-12));
+    }
+
+    #[test]
+    fn fault_analysis_of_a_divider() {
+        // A divider, and a load across the generator.
+        let build = || {
+            let edges: [Edge; 6] = [((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0)), ((4, 0), (1, 1)), ((4, 1), (0, 0))];
+            Circuit::new(
+                vec![Components::Ground, Components::VoltageGenerator(10.0), Components::Resistor(1e3), Components::Resistor(1e3), Components::Resistor(1e3)],
+                edges.iter().map(|(left, right)| (TerminalID::new(left.0, left.1), TerminalID::new(right.0, right.1))).collect(),
+            )
+        };
+        let injection = FaultInjection::new(&build(), vec![Expression::parse("V(3.0)").unwrap()]);
+        assert_eq!(injection.faults.len(), 8);
+
+        let report = Simulator::fault_analysis(build, &injection);
+        assert!((report.nominal[0] - 5.0).abs() < 1e-9);
+        // The ideal generator keeps its voltage when it or the load is shorted.
+        let (generator, load) = (TerminalID::new(1, 0), TerminalID::new(4, 0));
+        assert_eq!(
+            report.undetected(),
+            vec![Fault::Short(generator, TerminalID::new(1, 1)), Fault::Open(load), Fault::Short(load, TerminalID::new(4, 1))],
+        );
+        assert_eq!(report.coverage(), 5.0 / 8.0);
+
+        let short = report.results.iter().find(|result| result.fault == Fault::Short(TerminalID::new(2, 0), TerminalID::new(2, 1))).unwrap();
+        assert!((short.values.as_ref().unwrap()[0] - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn design_rule_check_of_a_divider() {
+        // 10V across two 100Ω resistors: 0.25W, 5V and 50mA each.
+        let mut simulator = simulator(
+            vec![Components::Ground, Components::VoltageGenerator(10.0), Components::Resistor(100.0), Components::Resistor(100.0)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))],
+        );
+        simulator.circuit.ratings = vec![
+            crate::model::Rating { component: ComponentID(2), max_power: Some(0.125), max_voltage: Some(10.0), max_current: None },
+            crate::model::Rating { component: ComponentID(3), max_power: Some(0.5), max_voltage: None, max_current: Some(0.01) },
+        ];
+        let unknowns = simulator.solve();
+
+        let violations = simulator.design_rule_check(&unknowns);
+        assert_eq!(violations.len(), 2);
+        // The smallest margin first.
+        assert_eq!((violations[0].component_id, violations[0].quantity), (ComponentID(3), RatedQuantity::Current));
+        assert!((violations[0].margin() + 4.0).abs() < 1e-9);
+        assert_eq!((violations[1].component_id, violations[1].quantity), (ComponentID(2), RatedQuantity::Power));
+        assert!((violations[1].value - 0.25).abs() < 1e-12);
+        assert_eq!(violations[1].kind, "Resistor");
+    }
+
+    #[test]
+    fn optimize_a_divider_to_a_setpoint() {
+        // 7.5V out of 10V through 1k needs 3k to the ground.
+        let mut simulator = simulator(
+            vec![Components::Ground, Components::VoltageGenerator(10.0), Components::Resistor(1e3), Components::Resistor(1e3)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))],
+        );
+        let parameter = crate::optimization::Parameter { component: ComponentID(3), min: 100.0, max: 1e4 };
+        let result = simulator.optimize(&Optimization::new(vec![parameter]), |simulator| {
+            let unknowns = simulator.solve();
+            optimization::rms_error(&[simulator.terminal_voltage(&unknowns, TerminalID::new(3, 0))], &[7.5])
+        });
+
+        assert!(result.converged);
+        assert!((result.values[0] - 3e3).abs() < 1.0, "R = {}", result.values[0]);
+        assert!(result.error < 1e-3);
+        assert!(matches!(simulator.circuit.components[3], Components::Resistor(r) if r == result.values[0]));
+    }
+
+    #[test]
+    fn max_power_transfer_at_dc_and_in_ac() {
+        // 12V through 1k into 2k: 8V behind 2k/3, giving V^2 / 4R to a matched load.
+        let mut divider = simulator(
+            vec![Components::Ground, Components::VoltageGenerator(12.0), Components::Resistor(1e3), Components::Resistor(2e3)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))],
+        );
+        let transfer = divider.max_power_transfer(TerminalID::new(3, 0), TerminalID::new(0, 0), 0.0);
+        let resistance = 2e3 / 3.0;
+        assert!((transfer.load_impedance - Complex::new(resistance, 0.0)).norm() < 1e-9);
+        assert!((transfer.power - 64.0 / (4.0 * resistance)).abs() < 1e-12);
+        assert!(transfer.load_power(Complex::new(2.0 * resistance, 0.0)) < transfer.power);
+
+        // At the corner of an RC, the source is R / (1 + j) behind 1 / (1 + j).
+        let mut simulator = rc(Components::SineGenerator { amplitude: 1.0, frequency: 1e3, phase: 0.0, dc_offset: 0.0 });
+        let transfer = simulator.max_power_transfer(TerminalID::new(3, 1), TerminalID::new(0, 0), 1.0 / (2.0 * PI * 1e-3));
+        assert!((transfer.source_impedance - Complex::new(500.0, -500.0)).norm() < 1e-6);
+        assert!((transfer.load_impedance - Complex::new(500.0, 500.0)).norm() < 1e-6);
+        // The average power |V|^2 / 8R.
+        assert!((transfer.power - 0.5 / (8.0 * 500.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn back_to_back_transients_start_afresh() {
+        // The cold filament of the lamp draws 12A at the start of each run.
+        let mut lamp = simulator(
+            vec![
+                Components::Ground,
+                Components::VoltageGenerator(12.0),
+                Components::Lamp { cold_resistance: 1.0, temperature_coefficient: 0.005, thermal_resistance: 100.0, thermal_capacitance: 0.01 },
+            ],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (0, 0))],
+        );
+        for _ in 0..2 {
+            let transient = lamp.transient(2.0, 1e-2);
+            let intensity = transient.waveform(|unknowns| lamp.branch_intensity(unknowns, ComponentID(1)).unwrap().abs());
+            assert!((intensity[0] - 12.0).abs() < 1e-9, "inrush of {}A", intensity[0]);
+            assert!(*intensity.last().unwrap() < 6.0);
         }
 
-        for (vgenerator_id, generator) in self.vgenerators.iter().enumerate() {
-            let component = &self.circuit.components[generator.0];
-            let voltage = match component {
-                Components::VoltageGenerator(voltage) => voltage,
-                _ => panic!("Voltage generator expected"),
-            };
-            result[self.nodes.len() - 1 + vgenerator_id] = *voltage;
+        // The step reaches the far end of the line one delay after it starts, in each run.
+        let step = Components::PulseGenerator { v1: 0.0, v2: 1.0, delay: 0.0, rise: 1e-12, fall: 1e-12, width: 1.0, period: 2.0 };
+        let mut line = simulator(
+            vec![
+                Components::Ground,
+                step,
+                Components::Resistor(50.0),
+                Components::TransmissionLine { impedance: 50.0, delay: 10e-9 },
+                Components::Resistor(50.0),
+            ],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 1)), ((3, 0), (0, 0)), ((3, 3), (4, 0)), ((4, 1), (0, 0)), ((3, 2), (0, 0))],
+        );
+        for _ in 0..2 {
+            let transient = line.transient(30e-9, 1e-9);
+            let far = transient.waveform(|unknowns| line.terminal_voltage(unknowns, TerminalID::new(3, 3)));
+            for (time, v) in transient.times.iter().zip(&far) {
+                let expected = if *time < 10e-9 { 0.0 } else if *time < 11e-9 { *v } else { 0.5 };
+                assert!((v - expected).abs() < 1e-6, "{}V at the far end at {}s", v, time);
+            }
         }
+    }
 
-        result
+    #[test]
+    #[should_panic(expected = "The time step must be positive")]
+    fn transient_rejects_a_zero_time_step() {
+        rc(Components::VoltageGenerator(1.0)).transient(1e-3, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The time step must be positive")]
+    fn transient_with_events_rejects_a_nan_time_step() {
+        rc(Components::VoltageGenerator(1.0)).transient_with_events(1e-3, f64::NAN, &[], |_, _| {});
     }
 
+    #[test]
+    fn stepped_runs_start_afresh() {
+        // 0.5Ω blows the fuse, which must be intact again for the 10Ω load.
+        let mut fuse_load = simulator(
+            vec![
+                Components::Ground,
+                Components::VoltageGenerator(1.0),
+                Components::Fuse { resistance: 0.01, rated_current: 1.0, melting_i2t: None },
+                Components::Resistor(1.0),
+            ],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))],
+        );
+        let load_voltage = |simulator: &Simulator, unknowns: &DVector<f64>| simulator.terminal_voltage(unknowns, TerminalID::new(3, 0));
 
-    /** Assuming the terminal is a bipolar terminal, return the other terminal of the same component. */
-    fn get_other_terminal(terminal_id: &TerminalID) -> TerminalID {
-        if terminal_id.idx == 0 {
-            TerminalID::new(terminal_id.component_id.0, 1)
-        } else if terminal_id.idx == 1 {
-            TerminalID::new(terminal_id.component_id.0, 0)
-        } else {
-            panic!("Invalid terminal index")
+        let transients = fuse_load.step(ComponentID(3), &[0.5, 10.0], |simulator| {
+            let transient = simulator.transient(1e-3, 1e-4);
+            load_voltage(simulator, transient.unknowns.last().unwrap())
+        });
+        assert!(transients[0].abs() < 1e-6);
+        assert!((transients[1] - 10.0 / 10.01).abs() < 1e-9, "{}V across the load", transients[1]);
+
+        let operating_points = fuse_load.step(ComponentID(3), &[0.5, 10.0], |simulator| {
+            let unknowns = simulator.solve();
+            simulator.commit(&unknowns);
+            load_voltage(simulator, &unknowns)
+        });
+        assert!((operating_points[1] - 10.0 / 10.01).abs() < 1e-9, "{}V across the load", operating_points[1]);
+    }
+
+    #[test]
+    fn monte_carlo_trials_start_afresh() {
+        // A fuse rated just above the nominal 1A: the trials that draw more blow it, but not the others.
+        let mut simulator = simulator(
+            vec![
+                Components::Ground,
+                Components::VoltageGenerator(1.0),
+                Components::Fuse { resistance: 0.01, rated_current: 1.0, melting_i2t: None },
+                Components::Resistor(1.0),
+            ],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))],
+        );
+        simulator.circuit.tolerances = vec![crate::model::Tolerance { component: ComponentID(3), relative: 0.2 }];
+        let settings = MonteCarlo { trials: 50, seed: 3, distribution: ToleranceDistribution::Uniform };
+        let result = simulator.monte_carlo(&settings, |simulator| {
+            let resistance = simulator.circuit.components[3].value().unwrap();
+            let unknowns = simulator.solve();
+            simulator.commit(&unknowns);
+            let unknowns = simulator.solve();
+            (resistance, simulator.terminal_voltage(&unknowns, TerminalID::new(3, 0)))
+        });
+
+        let mut blown = 0;
+        for (resistance, v) in result.trials {
+            let intact = resistance / (resistance + 0.01);
+            if intact / resistance > 1.0 {
+                blown += 1;
+                assert!(v.abs() < 1e-6, "{}V across {}Ω", v, resistance);
+            } else {
+                assert!((v - intact).abs() < 1e-9, "{}V across {}Ω", v, resistance);
+            }
         }
+        assert!(0 < blown && blown < settings.trials);
     }
 
-    /** Returns the Node ID from which a terminal is connected. */
-    fn get_node_id_from_terminal_id(&self, terminal_id: &TerminalID) -> usize {
-        self.terminal_to_node[terminal_id]
+    #[test]
+    fn optimizer_evaluations_start_afresh() {
+        // The fuse blows below 0.5Ω, where the search starts on its way to 1Ω (0.5V).
+        let mut simulator = simulator(
+            vec![
+                Components::Ground,
+                Components::VoltageGenerator(1.0),
+                Components::Resistor(1.0),
+                Components::Fuse { resistance: 1e-6, rated_current: 2.0 / 3.0, melting_i2t: None },
+                Components::Resistor(0.2),
+            ],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (4, 0)), ((4, 1), (0, 0))],
+        );
+        let parameter = crate::optimization::Parameter { component: ComponentID(4), min: 0.1, max: 10.0 };
+        let result = simulator.optimize(&Optimization::new(vec![parameter]), |simulator| {
+            let unknowns = simulator.solve();
+            simulator.commit(&unknowns);
+            let unknowns = simulator.solve();
+            optimization::rms_error(&[simulator.terminal_voltage(&unknowns, TerminalID::new(4, 0))], &[0.5])
+        });
+
+        assert!((result.values[0] - 1.0).abs() < 1e-3, "R = {}", result.values[0]);
+        assert!(result.error < 1e-3);
     }
 
-    /** Return the voltage generator ID from the component ID. */
-    fn get_vgenerator_id_from_component_id(&self, component_id: &ComponentID) -> usize {
-        self.component_id_to_vgenerator_id[component_id]
+    #[test]
+    fn behavioral_source_with_sqrt_of_time() {
+        let mut simulator = simulator(
+            vec![Components::Ground, Components::BehavioralVoltage(Expression::parse("2 + V(1.0) + sqrt(t)").unwrap()), Components::Resistor(1e3)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (0, 0))],
+        );
+        let operating_point = simulator.operating_point();
+        let v = simulator.terminal_voltage(&operating_point.unknowns, TerminalID::new(2, 0));
+        assert!((v - 2.0).abs() < 1e-9, "{}V across the resistor", v);
     }
 
-    fn get_component_id_from_vgenerator_id(&self, vgenerator_id: usize) -> ComponentID {
-        self.vgenerators[vgenerator_id]
+    #[test]
+    fn adaptive_steps_are_not_stretched_beyond_the_line_delay() {
+        // The steps grow from 4ns to the 10ns delay, and the one from 42ns would be stretched to the 55ns stop.
+        let mut simulator = simulator(
+            vec![
+                Components::Ground,
+                Components::VoltageGenerator(0.0),
+                Components::Resistor(50.0),
+                Components::TransmissionLine { impedance: 50.0, delay: 10e-9 },
+                Components::Resistor(50.0),
+            ],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 1)), ((3, 0), (0, 0)), ((3, 3), (4, 0)), ((4, 1), (0, 0)), ((3, 2), (0, 0))],
+        );
+        let transient = simulator.transient_adaptive(55e-9, &StepControl::new(4e-9, 1e-6));
+
+        assert!((transient.times.last().unwrap() - 55e-9).abs() < 1e-18);
+        assert!(transient.times.windows(2).all(|pair| pair[1] - pair[0] <= 10e-9 * (1.0 + 1e-9)));
     }
 
+    /** A Norton source of 1mA behind 1kOhm, which counts how many times it is stamped. */
+    #[derive(Debug)]
+    struct CountedNorton {
+        stamps: Rc<Cell<usize>>,
+    }
 
-    /** Represents the voltage of a node as a unit vector. */
-    fn unknown_node_voltage(&self, node_id: usize) -> DVector<f64> {
-        if node_id == 0 {
-            // By convention, the node id=0 is the ground node.
-            return DVector::zeros(self.n);
+    impl Component for CountedNorton {
+        fn terminals(&self) -> usize {
+            2
         }
 
-        let idx = node_id - 1;
-
-        let mut result = DVector::zeros(self.n);
-        result[idx] = 1.0;
-        result
+        fn stamp(&self, matrix: &mut DMatrix<f64>, rhs: &mut DVector<f64>, ctx: &StampContext) {
+            self.stamps.set(self.stamps.get() + 1);
+            ctx.stamp_conductance(matrix, 0, 1, 1e-3);
+            ctx.stamp_current(rhs, 0, 1, 1e-3);
+        }
     }
 
-    /** Represents the intensity that go through a voltage generator as a unit vector. */
-    fn unknown_vgenerator_intensity(&self, vgenerator_id: usize) -> DVector<f64> {
-        let idx = self.nodes.len() - 1 + vgenerator_id;
+    #[test]
+    fn custom_components_stamp_once_per_assembly() {
+        let stamps = Rc::new(Cell::new(0));
+        let simulator = simulator(
+            vec![Components::Ground, Components::Custom(Box::new(CountedNorton { stamps: stamps.clone() })), Components::Resistor(1e3)],
+            &[((1, 0), (0, 0)), ((1, 1), (2, 0)), ((2, 1), (0, 0))],
+        );
+        let (matrix, result) = simulator.get_system();
+        assert_eq!(stamps.get(), 1);
 
-        let mut result = DVector::zeros(self.n);
-        result[idx] = 1.0;
-        result
+        // The intensity goes through the source and the resistors in parallel.
+        let unknowns = matrix.lu().solve(&result).unwrap();
+        assert!((voltage(&simulator, &unknowns, 1, 1) - 0.5).abs() < 1e-9);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+use nalgebra::Complex;
+use crate::fourier::interpolate;
+
+/** Window applied to the samples before the FFT, trading frequency resolution for leakage. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl Window {
+    /** Returns the coefficient of the j-th of n samples. */
+    pub fn coefficient(&self, j: usize, n: usize) -> f64 {
+        let x = 2.0 * PI * j as f64 / n as f64;
+        match self {
+            Window::Rectangular => 1.0,
+            Window::Hann => 0.5 - 0.5 * x.cos(),
+            Window::Hamming => 0.54 - 0.46 * x.cos(),
+            Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+        }
+    }
+}
+
+/** Single-sided amplitude spectrum of a waveform. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrum {
+    /** Frequency of each bin, in Hz, from 0 to the Nyquist frequency. */
+    pub frequencies: Vec<f64>,
+    /** Amplitude of each bin, a sine of amplitude A giving a peak of about A. */
+    pub magnitudes: Vec<f64>,
+}
+
+/** Spectra of successive, possibly overlapping, segments of a waveform. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrogram {
+    /** Time at the middle of each segment, in seconds. */
+    pub times: Vec<f64>,
+    /** Frequency of each bin, in Hz. */
+    pub frequencies: Vec<f64>,
+    /** The magnitudes of the bins of each segment, see `Spectrum`. */
+    pub magnitudes: Vec<Vec<f64>>,
+}
+
+/**
+Returns the spectrum of a waveform sampled at the given times (e.g. a waveform of a `Transient`).
+The waveform is resampled at sample_rate in Hz by linear interpolation, and zero-padded to a
+power of two samples.
+*/
+pub fn spectrum(times: &[f64], values: &[f64], sample_rate: f64, window: Window) -> Spectrum {
+    let samples = resample(times, values, sample_rate);
+    let magnitudes = magnitudes(&samples, window);
+    Spectrum { frequencies: frequencies(magnitudes.len(), samples.len().next_power_of_two(), sample_rate), magnitudes }
+}
+
+/**
+Returns the spectrogram of a waveform sampled at the given times, resampled at sample_rate in Hz,
+with segments of segment_length samples (a power of two) overlapping by a fraction between 0
+included and 1 excluded of their length.
+*/
+pub fn spectrogram(times: &[f64], values: &[f64], sample_rate: f64, segment_length: usize, overlap: f64, window: Window) -> Spectrogram {
+    if !segment_length.is_power_of_two() || segment_length < 2 {
+        panic!("The segment length must be a power of two");
+    }
+    if !(0.0..1.0).contains(&overlap) {
+        panic!("The overlap must be between 0 included and 1 excluded");
+    }
+
+    let samples = resample(times, values, sample_rate);
+    let hop = ((segment_length as f64 * (1.0 - overlap)).round() as usize).max(1);
+    let starts: Vec<usize> = (0..).map(|k| k * hop).take_while(|start| start + segment_length <= samples.len()).collect();
+
+    Spectrogram {
+        times: starts.iter().map(|start| times[0] + (start + segment_length / 2) as f64 / sample_rate).collect(),
+        frequencies: frequencies(segment_length / 2 + 1, segment_length, sample_rate),
+        magnitudes: starts.iter().map(|start| magnitudes(&samples[*start..start + segment_length], window)).collect(),
+    }
+}
+
+/** Resamples a waveform on a uniform grid from its first to its last time. */
+fn resample(times: &[f64], values: &[f64], sample_rate: f64) -> Vec<f64> {
+    if times.len() != values.len() || times.len() < 2 {
+        panic!("The waveform must have one value per time and at least two points");
+    }
+    if sample_rate <= 0.0 {
+        panic!("The sample rate must be positive");
+    }
+
+    let count = ((times[times.len() - 1] - times[0]) * sample_rate).floor() as usize + 1;
+    (0..count).map(|j| interpolate(times, values, times[0] + j as f64 / sample_rate)).collect()
+}
+
+/** Returns the frequencies of the first count bins of an FFT of length samples. */
+fn frequencies(count: usize, length: usize, sample_rate: f64) -> Vec<f64> {
+    (0..count).map(|k| k as f64 * sample_rate / length as f64).collect()
+}
+
+/** Returns the single-sided amplitudes of windowed samples, zero-padded to a power of two. */
+fn magnitudes(samples: &[f64], window: Window) -> Vec<f64> {
+    let length = samples.len().next_power_of_two();
+    let coefficients: Vec<f64> = (0..samples.len()).map(|j| window.coefficient(j, samples.len())).collect();
+    // The coherent gain of the window is compensated so that the amplitudes do not depend on it.
+    let gain: f64 = coefficients.iter().sum();
+
+    let mut bins: Vec<Complex<f64>> = samples.iter().zip(&coefficients).map(|(value, coefficient)| Complex::new(value * coefficient, 0.0)).collect();
+    bins.resize(length, Complex::new(0.0, 0.0));
+    fft(&mut bins);
+
+    (0..=length / 2)
+        .map(|k| {
+            let scale = if k == 0 || k == length / 2 { 1.0 } else { 2.0 };
+            scale * bins[k].norm() / gain
+        })
+        .collect()
+}
+
+/** In-place iterative radix-2 FFT, the length being a power of two. */
+fn fft(bins: &mut [Complex<f64>]) {
+    let n = bins.len();
+
+    // Bit-reversal permutation.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            bins.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let twiddle = Complex::from_polar(1.0, -2.0 * PI / length as f64);
+        for start in (0..n).step_by(length) {
+            let mut w = Complex::new(1.0, 0.0);
+            for k in 0..length / 2 {
+                let even = bins[start + k];
+                let odd = bins[start + k + length / 2] * w;
+                bins[start + k] = even + odd;
+                bins[start + k + length / 2] = even - odd;
+                w *= twiddle;
+            }
+        }
+        length <<= 1;
+    }
+}
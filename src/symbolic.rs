@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use nalgebra::Complex;
+
+/** Product of symbols raised to a power, as (symbol index, exponent) sorted by symbol. */
+type Monomial = Vec<(usize, u32)>;
+
+/** Polynomial of several symbols with real coefficients, the symbols being referred to by index. */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polynomial {
+    terms: BTreeMap<Monomial, f64>,
+}
+
+impl Polynomial {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn constant(value: f64) -> Self {
+        let mut polynomial = Self::zero();
+        polynomial.add_term(Vec::new(), value);
+        polynomial
+    }
+
+    pub fn symbol(symbol: usize) -> Self {
+        let mut polynomial = Self::zero();
+        polynomial.add_term(vec![(symbol, 1)], 1.0);
+        polynomial
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /** Returns the terms, as the exponents of the symbols and the coefficient. */
+    pub fn terms(&self) -> impl Iterator<Item = (&[(usize, u32)], f64)> {
+        self.terms.iter().map(|(monomial, coefficient)| (monomial.as_slice(), *coefficient))
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let mut sum = self.clone();
+        for (monomial, coefficient) in &other.terms {
+            sum.add_term(monomial.clone(), *coefficient);
+        }
+        sum
+    }
+
+    pub fn scale(&self, factor: f64) -> Self {
+        let mut product = Self::zero();
+        for (monomial, coefficient) in &self.terms {
+            product.add_term(monomial.clone(), coefficient * factor);
+        }
+        product
+    }
+
+    pub fn multiply(&self, other: &Self) -> Self {
+        let mut product = Self::zero();
+        for (a, coefficient_a) in &self.terms {
+            for (b, coefficient_b) in &other.terms {
+                product.add_term(multiply_monomials(a, b), coefficient_a * coefficient_b);
+            }
+        }
+        product
+    }
+
+    /** Evaluates the polynomial with the given value of each symbol. */
+    pub fn evaluate(&self, values: &[Complex<f64>]) -> Complex<f64> {
+        self.terms
+            .iter()
+            .map(|(monomial, coefficient)| {
+                monomial.iter().fold(Complex::new(*coefficient, 0.0), |product, (symbol, exponent)| product * values[*symbol].powu(*exponent))
+            })
+            .sum()
+    }
+
+    /** Adds a term, removing it if the coefficients cancel out. */
+    fn add_term(&mut self, monomial: Monomial, coefficient: f64) {
+        let sum = self.terms.get(&monomial).copied().unwrap_or(0.0) + coefficient;
+        if sum == 0.0 {
+            self.terms.remove(&monomial);
+        } else {
+            self.terms.insert(monomial, sum);
+        }
+    }
+
+    /** Replaces each monomial by another one. */
+    fn map_monomials(&self, map: impl Fn(&Monomial) -> Monomial) -> Self {
+        let mut mapped = Self::zero();
+        for (monomial, coefficient) in &self.terms {
+            mapped.add_term(map(monomial), *coefficient);
+        }
+        mapped
+    }
+}
+
+fn multiply_monomials(a: &Monomial, b: &Monomial) -> Monomial {
+    let mut exponents: BTreeMap<usize, u32> = a.iter().copied().collect();
+    for (symbol, exponent) in b {
+        *exponents.entry(*symbol).or_insert(0) += exponent;
+    }
+    exponents.into_iter().collect()
+}
+
+/** Returns the exponent of a symbol in a monomial. */
+fn exponent(monomial: &Monomial, symbol: usize) -> u32 {
+    monomial.iter().find(|(s, _)| *s == symbol).map_or(0, |(_, exponent)| *exponent)
+}
+
+/** Returns the monomial with the exponent of a symbol changed by delta. */
+fn shift_exponent(monomial: &Monomial, symbol: usize, delta: i32) -> Monomial {
+    let mut exponents: BTreeMap<usize, u32> = monomial.iter().copied().collect();
+    let exponent = (exponents.get(&symbol).copied().unwrap_or(0) as i32 + delta) as u32;
+    if exponent == 0 {
+        exponents.remove(&symbol);
+    } else {
+        exponents.insert(symbol, exponent);
+    }
+    exponents.into_iter().collect()
+}
+
+/** Adds sign * value to an entry of a matrix, unless the row or the column is the ground. */
+pub(crate) fn stamp(matrix: &mut [Vec<Polynomial>], row: Option<usize>, column: Option<usize>, value: &Polynomial, sign: f64) {
+    if let (Some(row), Some(column)) = (row, column) {
+        matrix[row][column] = matrix[row][column].add(&value.scale(sign));
+    }
+}
+
+/** Stamps an admittance between two nodes, given by their rows. */
+pub(crate) fn stamp_admittance(matrix: &mut [Vec<Polynomial>], a: Option<usize>, b: Option<usize>, admittance: &Polynomial) {
+    stamp(matrix, a, a, admittance, 1.0);
+    stamp(matrix, b, b, admittance, 1.0);
+    stamp(matrix, a, b, admittance, -1.0);
+    stamp(matrix, b, a, admittance, -1.0);
+}
+
+/**
+Returns the determinant of the square submatrix of the rows and the columns, by Laplace expansion
+along its sparsest row, which is fast enough for the sparse matrices of small circuits.
+*/
+pub(crate) fn determinant(matrix: &[Vec<Polynomial>], rows: &[usize], columns: &[usize]) -> Polynomial {
+    if rows.is_empty() {
+        return Polynomial::constant(1.0);
+    }
+
+    let nonzero = |row: usize| columns.iter().filter(|column| !matrix[row][**column].is_zero()).count();
+    let (position, row) = rows.iter().copied().enumerate().min_by_key(|(_, row)| nonzero(*row)).unwrap();
+    let other_rows: Vec<usize> = rows.iter().copied().filter(|r| *r != row).collect();
+
+    let mut sum = Polynomial::zero();
+    for (k, column) in columns.iter().enumerate() {
+        let entry = &matrix[row][*column];
+        if entry.is_zero() {
+            continue;
+        }
+        let other_columns: Vec<usize> = columns.iter().copied().filter(|c| c != column).collect();
+        let sign = if (position + k).is_multiple_of(2) { 1.0 } else { -1.0 };
+        sum = sum.add(&entry.multiply(&determinant(matrix, &other_rows, &other_columns)).scale(sign));
+    }
+    sum
+}
+
+/**
+Transfer function H(s) = N(s) / D(s) of a circuit derived symbolically, see
+`Simulator::symbolic_transfer`. Its coefficients are polynomials of the component symbols.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolicTransfer {
+    /** Name of each symbol, e.g. R1 for the resistor 1 or C2 for the capacitor 2. */
+    pub symbols: Vec<String>,
+    /** Nominal value of each symbol. */
+    pub values: Vec<f64>,
+    /** Coefficients of N, numerator[k] multiplying s^k. */
+    pub numerator: Vec<Polynomial>,
+    /** Coefficients of D, denominator[k] multiplying s^k. */
+    pub denominator: Vec<Polynomial>,
+}
+
+impl SymbolicTransfer {
+    /**
+    Builds the transfer function from the numerator and the denominator computed with the symbol
+    0 being s. The resistors are given as conductances: multiplying N and D by the resistances
+    turns each conductance symbol into the resistance symbol. The common factors of N and D are
+    then removed.
+    */
+    pub(crate) fn new(symbols: Vec<String>, values: Vec<f64>, conductances: &[usize], numerator: Polynomial, denominator: Polynomial) -> Self {
+        // The determinant of the MNA matrix has a degree of at most 1 in each conductance.
+        let to_resistances = |polynomial: &Polynomial| {
+            polynomial.map_monomials(|monomial| {
+                conductances.iter().fold(monomial.clone(), |monomial, symbol| {
+                    let delta = if exponent(&monomial, *symbol) > 0 { -1 } else { 1 };
+                    shift_exponent(&monomial, *symbol, delta)
+                })
+            })
+        };
+        let (numerator, denominator) = (to_resistances(&numerator), to_resistances(&denominator));
+
+        // The greatest common monomial of all the terms.
+        let common: Vec<(usize, u32)> = (0..symbols.len() + 1)
+            .filter_map(|symbol| {
+                let smallest = numerator.terms.keys().chain(denominator.terms.keys()).map(|monomial| exponent(monomial, symbol)).min()?;
+                (smallest > 0).then_some((symbol, smallest))
+            })
+            .collect();
+        let divide = |polynomial: &Polynomial| {
+            polynomial.map_monomials(|monomial| {
+                common.iter().fold(monomial.clone(), |monomial, (symbol, exponent)| shift_exponent(&monomial, *symbol, -(*exponent as i32)))
+            })
+        };
+
+        // The sign is chosen to make the lowest order term of D positive.
+        let (numerator, denominator) = (Self::powers_of_s(&divide(&numerator)), Self::powers_of_s(&divide(&denominator)));
+        let lowest = denominator.iter().find(|coefficient| !coefficient.is_zero()).and_then(|coefficient| coefficient.terms.values().next());
+        let sign = if lowest.is_some_and(|coefficient| *coefficient < 0.0) { -1.0 } else { 1.0 };
+
+        Self {
+            symbols,
+            values,
+            numerator: numerator.iter().map(|coefficient| coefficient.scale(sign)).collect(),
+            denominator: denominator.iter().map(|coefficient| coefficient.scale(sign)).collect(),
+        }
+    }
+
+    /** Splits a polynomial whose symbol 0 is s into its coefficients by power of s, the other symbols being shifted down. */
+    fn powers_of_s(polynomial: &Polynomial) -> Vec<Polynomial> {
+        let mut coefficients = Vec::new();
+        for (monomial, coefficient) in &polynomial.terms {
+            let power = exponent(monomial, 0) as usize;
+            if coefficients.len() <= power {
+                coefficients.resize(power + 1, Polynomial::zero());
+            }
+            let rest = monomial.iter().filter(|(symbol, _)| *symbol != 0).map(|(symbol, exponent)| (symbol - 1, *exponent)).collect();
+            coefficients[power].add_term(rest, *coefficient);
+        }
+        coefficients
+    }
+
+    /** Evaluates H(s) with the given value of each symbol. */
+    pub fn evaluate(&self, values: &[f64], s: Complex<f64>) -> Complex<f64> {
+        let values: Vec<Complex<f64>> = values.iter().map(|value| Complex::new(*value, 0.0)).collect();
+        let polynomial = |coefficients: &[Polynomial]| -> Complex<f64> {
+            coefficients.iter().enumerate().map(|(power, coefficient)| coefficient.evaluate(&values) * s.powu(power as u32)).sum()
+        };
+        polynomial(&self.numerator) / polynomial(&self.denominator)
+    }
+
+    /** Returns H(j 2 pi f) at a frequency in Hz, with the nominal values of the symbols. */
+    pub fn response(&self, frequency: f64) -> Complex<f64> {
+        self.evaluate(&self.values, Complex::new(0.0, 2.0 * std::f64::consts::PI * frequency))
+    }
+
+    fn format_polynomial(&self, f: &mut fmt::Formatter<'_>, coefficients: &[Polynomial]) -> fmt::Result {
+        let mut first = true;
+        for (power, coefficient) in coefficients.iter().enumerate().rev() {
+            for (monomial, value) in coefficient.terms.iter().rev() {
+                let mut factors: Vec<String> = monomial
+                    .iter()
+                    .map(|(symbol, exponent)| match exponent {
+                        1 => self.symbols[*symbol].clone(),
+                        _ => format!("{}^{}", self.symbols[*symbol], exponent),
+                    })
+                    .collect();
+                match power {
+                    0 => {}
+                    1 => factors.push("s".to_string()),
+                    _ => factors.push(format!("s^{}", power)),
+                }
+
+                let magnitude = value.abs();
+                if magnitude != 1.0 || factors.is_empty() {
+                    factors.insert(0, format!("{}", magnitude));
+                }
+                let sign = match (first, *value < 0.0) {
+                    (true, true) => "-",
+                    (true, false) => "",
+                    (false, true) => " - ",
+                    (false, false) => " + ",
+                };
+                write!(f, "{}{}", sign, factors.join("*"))?;
+                first = false;
+            }
+        }
+        if first {
+            write!(f, "0")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SymbolicTransfer {
+    /** Writes H(s) as "(N) / (D)", e.g. "(1) / (R1*C2*s + 1)". */
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        self.format_polynomial(f, &self.numerator)?;
+        write!(f, ") / (")?;
+        self.format_polynomial(f, &self.denominator)?;
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polynomial_arithmetic() {
+        // (x + 1) * (x - 1) = x^2 - 1
+        let x = Polynomial::symbol(0);
+        let product = x.add(&Polynomial::constant(1.0)).multiply(&x.add(&Polynomial::constant(-1.0)));
+        assert_eq!(product.terms().count(), 2);
+        assert_eq!(product.evaluate(&[Complex::new(3.0, 0.0)]), Complex::new(8.0, 0.0));
+        assert!(product.add(&product.scale(-1.0)).is_zero());
+    }
+
+    #[test]
+    fn determinant_of_a_diagonal() {
+        let (a, b) = (Polynomial::symbol(0), Polynomial::symbol(1));
+        let matrix = vec![vec![a.clone(), Polynomial::zero()], vec![Polynomial::constant(5.0), b.clone()]];
+        assert_eq!(determinant(&matrix, &[0, 1], &[0, 1]), a.multiply(&b));
+    }
+}